@@ -0,0 +1,532 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::fmt;
+
+/// A parsed `AgentConnection.condition`, evaluated against the upstream node's output.
+///
+/// Grammar (lowest to highest precedence):
+/// `a || b`, `a && b`, `!a`, `a == b` / `!=` / `<` / `<=` / `>` / `>=`,
+/// then literals (`"text"`, `0.8`, `true`, `false`, `null`), paths such as
+/// `output.intent` or `output.items[0].name`, and parentheses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Literal(Value),
+    Path(Path),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Compare(Box<Condition>, CompareOp, Box<Condition>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Path into the upstream output, always rooted at `output`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    pub segments: Vec<PathSegment>,
+}
+
+/// Static type of an expression; paths are only known at run time
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ValueType {
+    Bool,
+    Number,
+    String,
+    Null,
+    Any,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let condition = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(anyhow!("Unexpected {} at position {}", token.kind, token.pos));
+        }
+
+        match condition.check()? {
+            ValueType::Bool | ValueType::Any => Ok(condition),
+            other => Err(anyhow!("Condition must evaluate to a boolean, but `{}` is a {}", condition, other)),
+        }
+    }
+
+    /// Evaluates the condition; non-boolean operands to `&&`, `||` and `!` are errors, not falsy.
+    pub fn evaluate(&self, output: &Value) -> Result<bool> {
+        as_bool(self, &self.value(output)?)
+    }
+
+    fn value(&self, output: &Value) -> Result<Value> {
+        match self {
+            Condition::Literal(value) => Ok(value.clone()),
+            Condition::Path(path) => Ok(path.resolve(output).cloned().unwrap_or(Value::Null)),
+            Condition::Not(inner) => Ok(Value::Bool(!as_bool(inner, &inner.value(output)?)?)),
+            Condition::And(left, right) => {
+                // Short-circuits like the usual operators
+                if !as_bool(left, &left.value(output)?)? {
+                    return Ok(Value::Bool(false));
+                }
+                Ok(Value::Bool(as_bool(right, &right.value(output)?)?))
+            }
+            Condition::Or(left, right) => {
+                if as_bool(left, &left.value(output)?)? {
+                    return Ok(Value::Bool(true));
+                }
+                Ok(Value::Bool(as_bool(right, &right.value(output)?)?))
+            }
+            Condition::Compare(left, op, right) => {
+                let (a, b) = (left.value(output)?, right.value(output)?);
+                compare(self, &a, *op, &b).map(Value::Bool)
+            }
+        }
+    }
+
+    fn check(&self) -> Result<ValueType> {
+        match self {
+            Condition::Literal(value) => Ok(type_of(value)),
+            Condition::Path(_) => Ok(ValueType::Any),
+            Condition::Not(inner) => {
+                expect_bool(inner)?;
+                Ok(ValueType::Bool)
+            }
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                expect_bool(left)?;
+                expect_bool(right)?;
+                Ok(ValueType::Bool)
+            }
+            Condition::Compare(left, op, right) => {
+                let (a, b) = (left.check()?, right.check()?);
+                let known = a != ValueType::Any && b != ValueType::Any;
+                match op {
+                    CompareOp::Equal | CompareOp::NotEqual => {
+                        if known && a != b && a != ValueType::Null && b != ValueType::Null {
+                            return Err(anyhow!("Cannot compare {} with {} in `{}`", a, b, self));
+                        }
+                    }
+                    _ => {
+                        for side in [a, b] {
+                            if matches!(side, ValueType::Bool | ValueType::Null) {
+                                return Err(anyhow!("`{}` orders a {}; only numbers and strings can be ordered", self, side));
+                            }
+                        }
+                        if known && a != b {
+                            return Err(anyhow!("Cannot compare {} with {} in `{}`", a, b, self));
+                        }
+                    }
+                }
+                Ok(ValueType::Bool)
+            }
+        }
+    }
+}
+
+impl Path {
+    fn resolve<'a>(&self, output: &'a Value) -> Option<&'a Value> {
+        self.segments.iter().try_fold(output, |value, segment| match segment {
+            PathSegment::Key(key) => value.get(key),
+            PathSegment::Index(index) => value.get(index),
+        })
+    }
+}
+
+fn expect_bool(condition: &Condition) -> Result<()> {
+    match condition.check()? {
+        ValueType::Bool | ValueType::Any => Ok(()),
+        other => Err(anyhow!("Expected a boolean but `{}` is a {}", condition, other)),
+    }
+}
+
+fn as_bool(condition: &Condition, value: &Value) -> Result<bool> {
+    value.as_bool()
+        .ok_or_else(|| anyhow!("Expected a boolean but `{}` is {}", condition, describe(value)))
+}
+
+fn compare(condition: &Condition, a: &Value, op: CompareOp, b: &Value) -> Result<bool> {
+    let ordering = match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64().partial_cmp(&y.as_f64()),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        _ => match op {
+            CompareOp::Equal => return Ok(a == b),
+            CompareOp::NotEqual => return Ok(a != b),
+            _ => return Err(anyhow!(
+                "Cannot order {} and {} in `{}`",
+                describe(a),
+                describe(b),
+                condition
+            )),
+        },
+    };
+
+    let ordering = ordering.ok_or_else(|| anyhow!("Cannot compare non-finite numbers in `{}`", condition))?;
+    Ok(match op {
+        CompareOp::Equal => ordering.is_eq(),
+        CompareOp::NotEqual => ordering.is_ne(),
+        CompareOp::Less => ordering.is_lt(),
+        CompareOp::LessOrEqual => ordering.is_le(),
+        CompareOp::Greater => ordering.is_gt(),
+        CompareOp::GreaterOrEqual => ordering.is_ge(),
+    })
+}
+
+fn type_of(value: &Value) -> ValueType {
+    match value {
+        Value::Bool(_) => ValueType::Bool,
+        Value::Number(_) => ValueType::Number,
+        Value::String(_) => ValueType::String,
+        Value::Null => ValueType::Null,
+        _ => ValueType::Any,
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null (missing)".to_string(),
+        Value::Bool(b) => format!("the boolean {}", b),
+        Value::Number(n) => format!("the number {}", n),
+        Value::String(s) => format!("the string \"{}\"", s),
+        Value::Array(_) => "an array".to_string(),
+        Value::Object(_) => "an object".to_string(),
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ValueType::Bool => "boolean",
+            ValueType::Number => "number",
+            ValueType::String => "string",
+            ValueType::Null => "null",
+            ValueType::Any => "value",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("output")?;
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Key(key) => write!(f, ".{}", key)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Literal(value) => write!(f, "{}", value),
+            Condition::Path(path) => write!(f, "{}", path),
+            Condition::Not(inner) => write!(f, "!{}", inner),
+            Condition::And(left, right) => write!(f, "({} && {})", left, right),
+            Condition::Or(left, right) => write!(f, "({} || {})", left, right),
+            Condition::Compare(left, op, right) => {
+                let symbol = match op {
+                    CompareOp::Equal => "==",
+                    CompareOp::NotEqual => "!=",
+                    CompareOp::Less => "<",
+                    CompareOp::LessOrEqual => "<=",
+                    CompareOp::Greater => ">",
+                    CompareOp::GreaterOrEqual => ">=",
+                };
+                write!(f, "{} {} {}", left, symbol, right)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(f64),
+    Str(String),
+    Dot,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    And,
+    Or,
+    Not,
+    Op(CompareOp),
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Ident(name) => write!(f, "`{}`", name),
+            TokenKind::Number(n) => write!(f, "number {}", n),
+            TokenKind::Str(s) => write!(f, "string \"{}\"", s),
+            TokenKind::Dot => f.write_str("`.`"),
+            TokenKind::LParen => f.write_str("`(`"),
+            TokenKind::RParen => f.write_str("`)`"),
+            TokenKind::LBracket => f.write_str("`[`"),
+            TokenKind::RBracket => f.write_str("`]`"),
+            TokenKind::And => f.write_str("`&&`"),
+            TokenKind::Or => f.write_str("`||`"),
+            TokenKind::Not => f.write_str("`!`"),
+            TokenKind::Op(_) => f.write_str("comparison operator"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    pos: usize,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let pos = i;
+        let next = chars.get(i + 1).copied();
+
+        let kind = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '.' => TokenKind::Dot,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
+            '&' if next == Some('&') => { i += 1; TokenKind::And }
+            '|' if next == Some('|') => { i += 1; TokenKind::Or }
+            '=' if next == Some('=') => { i += 1; TokenKind::Op(CompareOp::Equal) }
+            '!' if next == Some('=') => { i += 1; TokenKind::Op(CompareOp::NotEqual) }
+            '<' if next == Some('=') => { i += 1; TokenKind::Op(CompareOp::LessOrEqual) }
+            '>' if next == Some('=') => { i += 1; TokenKind::Op(CompareOp::GreaterOrEqual) }
+            '!' => TokenKind::Not,
+            '<' => TokenKind::Op(CompareOp::Less),
+            '>' => TokenKind::Op(CompareOp::Greater),
+            '"' | '\'' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(anyhow!("Unterminated string starting at position {}", pos)),
+                        Some(&ch) if ch == c => break,
+                        Some('\\') => {
+                            i += 1;
+                            match chars.get(i) {
+                                Some('n') => text.push('\n'),
+                                Some('t') => text.push('\t'),
+                                Some(&escaped) => text.push(escaped),
+                                None => return Err(anyhow!("Unterminated string starting at position {}", pos)),
+                            }
+                        }
+                        Some(&ch) => text.push(ch),
+                    }
+                    i += 1;
+                }
+                TokenKind::Str(text)
+            }
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let start = i;
+                i += 1;
+                while chars.get(i).is_some_and(|ch| ch.is_ascii_digit() || *ch == '.') {
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().collect();
+                let number = literal.parse::<f64>()
+                    .map_err(|_| anyhow!("Invalid number `{}` at position {}", literal, start))?;
+                tokens.push(Token { kind: TokenKind::Number(number), pos });
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while chars.get(i).is_some_and(|ch| ch.is_alphanumeric() || *ch == '_' || *ch == '-') {
+                    i += 1;
+                }
+                let name: String = chars[start..i].iter().collect();
+                tokens.push(Token { kind: TokenKind::Ident(name), pos });
+                continue;
+            }
+            other => return Err(anyhow!("Unexpected character '{}' at position {}", other, pos)),
+        };
+
+        tokens.push(Token { kind, pos });
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.peek().map(|t| &t.kind) == Some(kind) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<()> {
+        match self.next() {
+            Some(token) if token.kind == kind => Ok(()),
+            Some(token) => Err(anyhow!("Expected {} but found {} at position {}", kind, token.kind, token.pos)),
+            None => Err(anyhow!("Expected {} but the condition ended", kind)),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Condition> {
+        let mut left = self.parse_and()?;
+        while self.eat(&TokenKind::Or) {
+            let right = self.parse_and()?;
+            left = Condition::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Condition> {
+        let mut left = self.parse_unary()?;
+        while self.eat(&TokenKind::And) {
+            let right = self.parse_unary()?;
+            left = Condition::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Condition> {
+        if self.eat(&TokenKind::Not) {
+            return Ok(Condition::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Condition> {
+        let left = self.parse_primary()?;
+        if let Some(TokenKind::Op(op)) = self.peek().map(|t| t.kind.clone()) {
+            self.pos += 1;
+            let right = self.parse_primary()?;
+            return Ok(Condition::Compare(Box::new(left), op, Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_primary(&mut self) -> Result<Condition> {
+        let token = self.next().ok_or_else(|| anyhow!("Condition ended unexpectedly"))?;
+        match token.kind {
+            TokenKind::Number(n) => serde_json::Number::from_f64(n)
+                .map(|n| Condition::Literal(Value::Number(n)))
+                .ok_or_else(|| anyhow!("Invalid number at position {}", token.pos)),
+            TokenKind::Str(s) => Ok(Condition::Literal(Value::String(s))),
+            TokenKind::LParen => {
+                let inner = self.parse_or()?;
+                self.expect(TokenKind::RParen)?;
+                Ok(inner)
+            }
+            TokenKind::Ident(name) => match name.as_str() {
+                "true" => Ok(Condition::Literal(Value::Bool(true))),
+                "false" => Ok(Condition::Literal(Value::Bool(false))),
+                "null" => Ok(Condition::Literal(Value::Null)),
+                "output" => self.parse_path(),
+                _ => Err(anyhow!(
+                    "Unknown name `{}` at position {}; fields must be referenced as `output.{}`",
+                    name, token.pos, name
+                )),
+            },
+            other => Err(anyhow!("Unexpected {} at position {}", other, token.pos)),
+        }
+    }
+
+    fn parse_path(&mut self) -> Result<Condition> {
+        let mut segments = Vec::new();
+        loop {
+            if self.eat(&TokenKind::Dot) {
+                match self.next() {
+                    Some(Token { kind: TokenKind::Ident(key), .. }) => segments.push(PathSegment::Key(key)),
+                    Some(token) => return Err(anyhow!("Expected a field name after `.` at position {}", token.pos)),
+                    None => return Err(anyhow!("Expected a field name after `.`")),
+                }
+            } else if self.eat(&TokenKind::LBracket) {
+                match self.next() {
+                    Some(Token { kind: TokenKind::Number(n), .. }) if n >= 0.0 && n.fract() == 0.0 => {
+                        segments.push(PathSegment::Index(n as usize))
+                    }
+                    Some(Token { kind: TokenKind::Str(key), .. }) => segments.push(PathSegment::Key(key)),
+                    Some(token) => return Err(anyhow!("Expected an index or quoted key at position {}", token.pos)),
+                    None => return Err(anyhow!("Expected an index after `[`")),
+                }
+                self.expect(TokenKind::RBracket)?;
+            } else {
+                return Ok(Condition::Path(Path { segments }));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_conditions() {
+        let output = serde_json::json!({
+            "intent": "file_operation",
+            "confidence": 0.92,
+            "files": [{ "name": "notes.md" }],
+            "done": false
+        });
+
+        let passes = |source: &str| Condition::parse(source).unwrap().evaluate(&output).unwrap();
+        assert!(passes(r#"output.intent == "file_operation" && output.confidence > 0.8"#));
+        assert!(!passes("output.confidence >= 0.95 || output.done"));
+        assert!(passes("!(output.done) && output.files[0].name == 'notes.md'"));
+        assert!(passes("output.missing == null"));
+
+        // Runtime type mismatches are reported rather than treated as false
+        let err = Condition::parse("output.intent > 3").unwrap().evaluate(&output).unwrap_err();
+        assert!(err.to_string().contains("Cannot order"));
+    }
+
+    #[test]
+    fn test_parse_errors_are_descriptive() {
+        let message = |source: &str| Condition::parse(source).unwrap_err().to_string();
+        assert!(message("intent == 'x'").contains("output.intent"));
+        assert!(message("output.ready && 0.5 == 'high'").contains("Cannot compare"));
+        assert!(message("output.score + 1").contains("position 13"));
+        assert!(message("(output.ok").contains("Expected `)`"));
+        assert!(message("42").contains("must evaluate to a boolean"));
+        assert!(message("output.ok && 1").contains("Expected a boolean"));
+    }
+}
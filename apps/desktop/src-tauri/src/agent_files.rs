@@ -0,0 +1,421 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::agents::{
+    execution_order, validate_workflow, AgentConnection, AgentNode, AgentStatus, AgentSystem, AgentType,
+    AgentWorkflow, WorkflowSettings,
+};
+
+/// Identifies workflow files; anything else is rejected on import
+pub const WORKFLOW_FILE_FORMAT: &str = "localbrain-workflow";
+
+/// Bumped when the file layout changes in a way older readers cannot handle
+pub const WORKFLOW_FILE_VERSION: u32 = 1;
+
+/// Canvas layout used for nodes imported without a position
+const LAYOUT_ORIGIN: (f32, f32) = (100.0, 80.0);
+const LAYOUT_SPACING: (f32, f32) = (240.0, 140.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkflowFileFormat {
+    Yaml,
+    Json,
+}
+
+impl WorkflowFileFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "yaml" | "yml" => Ok(WorkflowFileFormat::Yaml),
+            "json" => Ok(WorkflowFileFormat::Json),
+            other => Err(anyhow!("Unsupported workflow file format '{}' (expected 'yaml' or 'json')", other)),
+        }
+    }
+}
+
+/// A workflow as written to disk, meant to be kept in git and edited by hand:
+///
+/// ```yaml
+/// format: localbrain-workflow
+/// version: 1
+/// name: Voice assistant
+/// description: Answers spoken questions    # optional
+/// settings:                                # optional, see WorkflowSettings
+///   max_concurrency: 2
+///   cancel_on_failure: false
+/// requires:                                # filled in on export, checked on import
+///   agents: [voice_input, response_generator]
+///   plugins: []                            # plugin IDs named by a node's `config.plugin`
+/// nodes:
+///   - id: listen                           # referenced by connections
+///     agent: voice_input                   # executor key, see get_available_agents
+///     name: Listen                         # optional, defaults to the agent's name
+///     type: input                          # optional, defaults to the agent's type
+///     config: { wakeWord: hey brain }      # optional
+///     position: { x: 100, y: 80 }          # optional, laid out left to right when missing
+///   - id: answer
+///     agent: response_generator
+/// connections:
+///   - from: listen
+///     to: answer
+///     label: on_error                      # optional
+///     condition: output.text != ''         # optional
+/// ```
+///
+/// Files do not carry a workflow ID; every import creates a new workflow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowFile {
+    pub format: String,
+    pub version: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default)]
+    pub settings: WorkflowSettings,
+    #[serde(default)]
+    pub requires: WorkflowRequirements,
+    #[serde(default)]
+    pub nodes: Vec<WorkflowFileNode>,
+    #[serde(default)]
+    pub connections: Vec<WorkflowFileConnection>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowRequirements {
+    #[serde(default)]
+    pub agents: Vec<String>,
+    #[serde(default)]
+    pub plugins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowFileNode {
+    pub id: String,
+    pub agent: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub agent_type: Option<AgentType>,
+    #[serde(default, skip_serializing_if = "is_empty_config")]
+    pub config: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<NodePosition>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodePosition {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowFileConnection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub from: String,
+    pub to: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+}
+
+fn is_empty_config(config: &serde_json::Value) -> bool {
+    config.is_null() || config.as_object().is_some_and(|c| c.is_empty())
+}
+
+/// Serializes a stored workflow to the file schema
+pub async fn export_workflow(system: &AgentSystem, workflow_id: &str, format: WorkflowFileFormat) -> Result<String> {
+    let workflow = system.get_workflow(workflow_id).await?;
+    let file = to_file(&workflow);
+    Ok(match format {
+        WorkflowFileFormat::Yaml => serde_yaml::to_string(&file)?,
+        WorkflowFileFormat::Json => serde_json::to_string_pretty(&file)?,
+    })
+}
+
+/// Validates a YAML or JSON workflow file against the installed agents and plugins, then saves
+/// it as a new workflow. Every problem found is reported at once, so a hand-written file can be
+/// fixed in one pass.
+pub async fn import_workflow(system: &AgentSystem, text: &str, installed_plugins: &[String]) -> Result<AgentWorkflow> {
+    let file = parse_file(text)?;
+    let agents = system.registered_agents().await;
+    let workflow = from_file(file, &agents, installed_plugins)?;
+    system.update_workflow(workflow.clone()).await?;
+    Ok(workflow)
+}
+
+fn parse_file(text: &str) -> Result<WorkflowFile> {
+    // YAML is a superset of JSON, but the JSON parser gives clearer errors for JSON files
+    let file: WorkflowFile = if text.trim_start().starts_with('{') {
+        serde_json::from_str(text).map_err(|e| anyhow!("Invalid workflow JSON: {}", e))?
+    } else {
+        serde_yaml::from_str(text).map_err(|e| anyhow!("Invalid workflow YAML: {}", e))?
+    };
+
+    if file.format != WORKFLOW_FILE_FORMAT {
+        return Err(anyhow!("Not a workflow file: format is '{}', expected '{}'", file.format, WORKFLOW_FILE_FORMAT));
+    }
+    if file.version > WORKFLOW_FILE_VERSION {
+        return Err(anyhow!(
+            "Workflow file version {} is newer than this app supports ({})",
+            file.version,
+            WORKFLOW_FILE_VERSION
+        ));
+    }
+    Ok(file)
+}
+
+fn to_file(workflow: &AgentWorkflow) -> WorkflowFile {
+    let agents: BTreeSet<String> = workflow.nodes.iter().map(|n| n.agent_key.clone()).collect();
+    WorkflowFile {
+        format: WORKFLOW_FILE_FORMAT.to_string(),
+        version: WORKFLOW_FILE_VERSION,
+        name: workflow.name.clone(),
+        description: workflow.description.clone(),
+        settings: workflow.settings.clone(),
+        requires: WorkflowRequirements {
+            agents: agents.into_iter().collect(),
+            plugins: node_plugins(workflow.nodes.iter().map(|n| &n.config)).into_iter().collect(),
+        },
+        nodes: workflow.nodes.iter().map(|node| WorkflowFileNode {
+            id: node.id.clone(),
+            agent: node.agent_key.clone(),
+            name: Some(node.name.clone()),
+            agent_type: Some(node.agent_type.clone()),
+            config: node.config.clone(),
+            position: Some(NodePosition { x: node.x, y: node.y }),
+        }).collect(),
+        connections: workflow.connections.iter().map(|connection| WorkflowFileConnection {
+            id: Some(connection.id.clone()),
+            from: connection.from.clone(),
+            to: connection.to.clone(),
+            label: connection.label.clone(),
+            condition: connection.condition.clone(),
+        }).collect(),
+    }
+}
+
+/// Builds a workflow from a parsed file. `agents` maps executor keys to their default name and type.
+fn from_file(
+    file: WorkflowFile,
+    agents: &HashMap<String, (String, AgentType)>,
+    installed_plugins: &[String],
+) -> Result<AgentWorkflow> {
+    let mut problems: Vec<String> = Vec::new();
+
+    let mut node_ids: HashSet<&str> = HashSet::new();
+    for node in &file.nodes {
+        if node.id.trim().is_empty() {
+            problems.push("A node has an empty id".to_string());
+        } else if !node_ids.insert(node.id.as_str()) {
+            problems.push(format!("Node id '{}' is used more than once", node.id));
+        }
+    }
+
+    // Missing executors are listed together since they are usually fixed by installing something
+    let wanted_agents: BTreeSet<&str> = file.nodes.iter()
+        .map(|n| n.agent.as_str())
+        .chain(file.requires.agents.iter().map(String::as_str))
+        .collect();
+    let missing_agents: Vec<&str> = wanted_agents.into_iter().filter(|a| !agents.contains_key(*a)).collect();
+    if !missing_agents.is_empty() {
+        problems.push(format!("Missing agents: {}", missing_agents.join(", ")));
+    }
+    let wanted_plugins: BTreeSet<String> = node_plugins(file.nodes.iter().map(|n| &n.config))
+        .into_iter()
+        .chain(file.requires.plugins.iter().cloned())
+        .collect();
+    let missing_plugins: Vec<String> = wanted_plugins.into_iter().filter(|p| !installed_plugins.contains(p)).collect();
+    if !missing_plugins.is_empty() {
+        problems.push(format!("Missing plugins: {}", missing_plugins.join(", ")));
+    }
+
+    let mut connection_ids: HashSet<String> = HashSet::new();
+    for (i, connection) in file.connections.iter().enumerate() {
+        for end in [&connection.from, &connection.to] {
+            if !node_ids.contains(end.as_str()) {
+                problems.push(format!("Connection {} -> {} references unknown node '{}'", connection.from, connection.to, end));
+            }
+        }
+        let id = connection.id.clone().unwrap_or_else(|| format!("connection-{}", i + 1));
+        if !connection_ids.insert(id.clone()) {
+            problems.push(format!("Connection id '{}' is used more than once", id));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(anyhow!("Workflow file has {} problem(s):\n- {}", problems.len(), problems.join("\n- ")));
+    }
+
+    let now = Utc::now();
+    let positions: Vec<Option<NodePosition>> = file.nodes.iter().map(|n| n.position).collect();
+    let nodes = file.nodes.into_iter().map(|node| {
+        let (default_name, default_type) = agents[&node.agent].clone();
+        let position = node.position.unwrap_or(NodePosition { x: 0.0, y: 0.0 });
+        AgentNode {
+            id: node.id,
+            name: node.name.unwrap_or(default_name),
+            agent_key: node.agent,
+            agent_type: node.agent_type.unwrap_or(default_type),
+            x: position.x,
+            y: position.y,
+            config: if node.config.is_null() { serde_json::json!({}) } else { node.config },
+            status: AgentStatus::Idle,
+            created_at: now,
+            updated_at: now,
+        }
+    }).collect();
+    let connections = file.connections.into_iter().enumerate().map(|(i, connection)| AgentConnection {
+        id: connection.id.unwrap_or_else(|| format!("connection-{}", i + 1)),
+        from: connection.from,
+        to: connection.to,
+        label: connection.label,
+        condition: connection.condition,
+    }).collect();
+
+    let mut workflow = AgentWorkflow {
+        id: Uuid::new_v4().to_string(),
+        name: file.name,
+        description: file.description,
+        nodes,
+        connections,
+        settings: file.settings,
+        created_at: now,
+        updated_at: now,
+    };
+
+    // Cycles, bad conditions and invalid node policies are caught here rather than on first run
+    validate_workflow(&workflow)?;
+    layout_missing_positions(&mut workflow, &positions)?;
+    Ok(workflow)
+}
+
+/// Places nodes without a position in columns by dependency depth, top to bottom in file order
+fn layout_missing_positions(workflow: &mut AgentWorkflow, positions: &[Option<NodePosition>]) -> Result<()> {
+    let order = execution_order(workflow)?;
+    let index: HashMap<String, usize> = workflow.nodes.iter()
+        .enumerate()
+        .map(|(i, n)| (n.id.clone(), i))
+        .collect();
+
+    let mut depth = vec![0usize; workflow.nodes.len()];
+    for &i in &order {
+        for connection in workflow.connections.iter().filter(|c| c.from == workflow.nodes[i].id) {
+            let child = index[&connection.to];
+            depth[child] = depth[child].max(depth[i] + 1);
+        }
+    }
+
+    let mut rows: HashMap<usize, usize> = HashMap::new();
+    for (i, node) in workflow.nodes.iter_mut().enumerate() {
+        let row = rows.entry(depth[i]).or_insert(0);
+        if positions[i].is_none() {
+            node.x = LAYOUT_ORIGIN.0 + LAYOUT_SPACING.0 * depth[i] as f32;
+            node.y = LAYOUT_ORIGIN.1 + LAYOUT_SPACING.1 * *row as f32;
+        }
+        *row += 1;
+    }
+    Ok(())
+}
+
+/// Plugin IDs named by nodes' `config.plugin`
+fn node_plugins<'a>(configs: impl Iterator<Item = &'a serde_json::Value>) -> BTreeSet<String> {
+    configs
+        .filter_map(|config| config.get("plugin").and_then(|p| p.as_str()))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_store::AgentStore;
+    use crate::security::SecurityManager;
+    use std::sync::Arc;
+
+    const HAND_WRITTEN: &str = r#"
+format: localbrain-workflow
+version: 1
+name: Triage
+nodes:
+  - id: read
+    agent: file_reader
+    config: { path: notes.md }
+  - id: check
+    agent: conditional
+    config: { condition: "output.size > 0" }
+  - id: shape
+    agent: data_transform
+    name: Shape
+    position: { x: 5, y: 7 }
+connections:
+  - { from: read, to: check }
+  - { from: check, to: shape, label: "true" }
+"#;
+
+    #[tokio::test]
+    async fn test_import_and_export_round_trip() {
+        let system = AgentSystem::new(AgentStore::open_in_memory().unwrap(), Arc::new(SecurityManager::new())).await.unwrap();
+        let workflow = import_workflow(&system, HAND_WRITTEN, &[]).await.unwrap();
+
+        // Names and types default from the agent; missing positions are laid out by depth
+        assert_eq!(workflow.nodes[0].name, "File Reader");
+        assert!(matches!(workflow.nodes[1].agent_type, AgentType::Condition));
+        assert_eq!((workflow.nodes[0].x, workflow.nodes[0].y), LAYOUT_ORIGIN);
+        assert_eq!(workflow.nodes[1].x, LAYOUT_ORIGIN.0 + LAYOUT_SPACING.0);
+        assert_eq!((workflow.nodes[2].x, workflow.nodes[2].y), (5.0, 7.0));
+        assert_eq!(workflow.connections[1].id, "connection-2");
+
+        for format in [WorkflowFileFormat::Yaml, WorkflowFileFormat::Json] {
+            let text = export_workflow(&system, &workflow.id, format).await.unwrap();
+            let copy = import_workflow(&system, &text, &[]).await.unwrap();
+            assert_ne!(copy.id, workflow.id);
+            assert_eq!(serde_json::to_value(&copy.nodes[2].config).unwrap(), serde_json::to_value(&workflow.nodes[2].config).unwrap());
+            assert_eq!(copy.nodes[1].x, workflow.nodes[1].x);
+            assert_eq!(copy.connections[1].label.as_deref(), Some("true"));
+        }
+
+        let text = export_workflow(&system, &workflow.id, WorkflowFileFormat::Yaml).await.unwrap();
+        let file: WorkflowFile = serde_yaml::from_str(&text).unwrap();
+        assert_eq!(file.requires.agents, ["conditional", "data_transform", "file_reader"]);
+        assert_eq!(system.list_workflows().await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_every_problem() {
+        let system = AgentSystem::new(AgentStore::open_in_memory().unwrap(), Arc::new(SecurityManager::new())).await.unwrap();
+        let text = r#"
+format: localbrain-workflow
+version: 1
+name: Broken
+requires: { plugins: [weather] }
+nodes:
+  - { id: a, agent: voice_input }
+  - { id: a, agent: summarizer }
+  - { id: b, agent: translator, config: { plugin: deepl } }
+connections:
+  - { from: a, to: c }
+"#;
+        let err = import_workflow(&system, text, &["weather".to_string()]).await.unwrap_err().to_string();
+        assert!(err.contains("4 problem(s)"), "{}", err);
+        assert!(err.contains("Node id 'a' is used more than once"));
+        assert!(err.contains("Missing agents: summarizer, translator"));
+        assert!(err.contains("Missing plugins: deepl"));
+        assert!(err.contains("unknown node 'c'"));
+        assert!(system.list_workflows().await.is_empty());
+
+        let err = import_workflow(&system, "format: localbrain-workflow\nversion: 9\nname: Future\n", &[]).await.unwrap_err();
+        assert!(err.to_string().contains("newer than this app supports"));
+        let err = import_workflow(&system, r#"{"format": "localbrain-workflow", "version": 1, "name": "x", "nodez": []}"#, &[]).await.unwrap_err();
+        assert!(err.to_string().contains("unknown field `nodez`"));
+    }
+}
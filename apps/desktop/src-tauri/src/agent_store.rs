@@ -0,0 +1,524 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use chrono::{DateTime, Utc};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::agents::{legacy_agent_key, truncate_value, AgentWorkflow, NodeRunResult, WorkflowRun, WorkflowRunSummary};
+
+/// Version of the stored workflow definition; bump it and extend `migrate_definition`
+/// whenever `AgentWorkflow` changes shape.
+pub const WORKFLOW_SCHEMA_VERSION: i64 = 2;
+
+/// Finished runs kept per workflow; older ones are deleted when a run finishes
+const MAX_RUNS_PER_WORKFLOW: usize = 100;
+
+/// Finished runs older than this are deleted regardless of count
+const MAX_RUN_AGE_DAYS: i64 = 30;
+
+/// Maximum serialized size of a node input or output kept in the run history
+const STORED_VALUE_LIMIT: usize = 16 * 1024;
+
+/// SQLite store for agent workflows and their run history
+pub struct AgentStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl AgentStore {
+    pub fn open(app_data_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(app_data_dir)?;
+        Self::from_connection(Connection::open(app_data_dir.join("agents.db"))?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute("PRAGMA foreign_keys = ON", [])?;
+        Self::create_tables(&conn)?;
+
+        // Runs still marked running were cut short by a crash or shutdown
+        conn.execute(
+            "UPDATE workflow_runs SET status = 'error', error = 'Interrupted', finished_at = ?1
+             WHERE status = 'running'",
+            params![Utc::now().to_rfc3339()],
+        )?;
+        Self::prune_runs(&conn, None)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn create_tables(conn: &Connection) -> Result<()> {
+        // Full workflow definitions (nodes, positions, configs, connections) as JSON
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS workflows (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                schema_version INTEGER NOT NULL,
+                definition TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS workflow_runs (
+                id TEXT PRIMARY KEY,
+                workflow_id TEXT NOT NULL REFERENCES workflows(id) ON DELETE CASCADE,
+                status TEXT NOT NULL,
+                input TEXT NOT NULL,
+                error TEXT,
+                started_at TEXT NOT NULL,
+                finished_at TEXT
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_workflow_runs_workflow ON workflow_runs(workflow_id, started_at)",
+            [],
+        )?;
+
+        // One row per node per run, in execution order
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS workflow_run_nodes (
+                run_id TEXT NOT NULL REFERENCES workflow_runs(id) ON DELETE CASCADE,
+                position INTEGER NOT NULL,
+                node_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                status TEXT NOT NULL,
+                input TEXT NOT NULL,
+                output TEXT NOT NULL,
+                error TEXT,
+                started_at TEXT,
+                duration_ms INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (run_id, position)
+            )",
+            [],
+        )?;
+
+        // Columns added after the first release of the store
+        Self::ensure_column(conn, "workflow_run_nodes", "attempts", "INTEGER NOT NULL DEFAULT 1")?;
+
+        Ok(())
+    }
+
+    fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists = conn.prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))?
+            .exists(params![column])?;
+        if !exists {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        }
+        Ok(())
+    }
+
+    pub async fn save_workflow(&self, workflow: &AgentWorkflow) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "INSERT INTO workflows (id, name, schema_version, definition, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(id) DO UPDATE SET name = ?2, schema_version = ?3, definition = ?4, updated_at = ?6",
+            params![
+                workflow.id,
+                workflow.name,
+                WORKFLOW_SCHEMA_VERSION,
+                serde_json::to_string(workflow)?,
+                workflow.created_at.to_rfc3339(),
+                workflow.updated_at.to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Loads every stored workflow; definitions that cannot be read are reported and skipped.
+    pub async fn load_workflows(&self) -> Result<Vec<AgentWorkflow>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare("SELECT id, schema_version, definition FROM workflows ORDER BY created_at")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let mut workflows = Vec::with_capacity(rows.len());
+        for (id, version, definition) in rows {
+            match migrate_definition(version, &definition) {
+                Ok(workflow) => {
+                    // Write migrated definitions back so they are only upgraded once
+                    if version < WORKFLOW_SCHEMA_VERSION {
+                        conn.execute(
+                            "UPDATE workflows SET schema_version = ?2, definition = ?3 WHERE id = ?1",
+                            params![id, WORKFLOW_SCHEMA_VERSION, serde_json::to_string(&workflow)?],
+                        )?;
+                    }
+                    workflows.push(workflow);
+                }
+                Err(e) => eprintln!("Skipping stored workflow {}: {}", id, e),
+            }
+        }
+        Ok(workflows)
+    }
+
+    pub async fn delete_workflow(&self, workflow_id: &str) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute("DELETE FROM workflows WHERE id = ?1", params![workflow_id])?;
+        Ok(())
+    }
+
+    pub async fn start_run(&self, run: &WorkflowRun) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "INSERT INTO workflow_runs (id, workflow_id, status, input, started_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                run.id,
+                run.workflow_id,
+                status_name(&run.status)?,
+                serde_json::to_string(&truncate_value(&run.input, STORED_VALUE_LIMIT))?,
+                run.started_at.to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Writes the final status and every node result of a run.
+    pub async fn finish_run(&self, run: &WorkflowRun) -> Result<()> {
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE workflow_runs SET status = ?2, error = ?3, finished_at = ?4 WHERE id = ?1",
+            params![
+                run.id,
+                status_name(&run.status)?,
+                run.error,
+                run.finished_at.map(|t| t.to_rfc3339())
+            ],
+        )?;
+        tx.execute("DELETE FROM workflow_run_nodes WHERE run_id = ?1", params![run.id])?;
+        for (position, node) in run.nodes.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO workflow_run_nodes
+                 (run_id, position, node_id, name, status, input, output, error, started_at, duration_ms, attempts)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    run.id,
+                    position as i64,
+                    node.node_id,
+                    node.name,
+                    status_name(&node.status)?,
+                    serde_json::to_string(&truncate_value(&node.input, STORED_VALUE_LIMIT))?,
+                    serde_json::to_string(&truncate_value(&node.output, STORED_VALUE_LIMIT))?,
+                    node.error,
+                    node.started_at.map(|t| t.to_rfc3339()),
+                    node.duration_ms as i64,
+                    node.attempts
+                ],
+            )?;
+        }
+        Self::prune_runs(&tx, Some(&run.workflow_id))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Deletes finished runs past the age limit, then all but the newest runs of
+    /// `workflow_id` (or of every workflow). Node rows go with them by cascade.
+    fn prune_runs(conn: &Connection, workflow_id: Option<&str>) -> Result<()> {
+        let cutoff = Utc::now() - chrono::Duration::days(MAX_RUN_AGE_DAYS);
+        conn.execute(
+            "DELETE FROM workflow_runs WHERE status != 'running' AND started_at < ?1",
+            params![cutoff.to_rfc3339()],
+        )?;
+        conn.execute(
+            "DELETE FROM workflow_runs WHERE id IN (
+                SELECT id FROM (
+                    SELECT id, status, ROW_NUMBER() OVER (PARTITION BY workflow_id ORDER BY started_at DESC) AS rank
+                    FROM workflow_runs WHERE ?1 IS NULL OR workflow_id = ?1
+                ) WHERE rank > ?2 AND status != 'running'
+            )",
+            params![workflow_id, MAX_RUNS_PER_WORKFLOW as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent runs first, optionally limited to one workflow.
+    pub async fn list_runs(&self, workflow_id: Option<&str>, limit: usize) -> Result<Vec<WorkflowRunSummary>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, workflow_id, status, error, started_at, finished_at FROM workflow_runs
+             WHERE ?1 IS NULL OR workflow_id = ?1
+             ORDER BY started_at DESC LIMIT ?2",
+        )?;
+        let runs = stmt.query_map(params![workflow_id, limit as i64], Self::row_to_summary)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(runs)
+    }
+
+    pub async fn get_run(&self, run_id: &str) -> Result<Option<WorkflowRun>> {
+        let conn = self.conn.lock().await;
+        let header = conn.query_row(
+            "SELECT id, workflow_id, status, error, started_at, finished_at, input FROM workflow_runs WHERE id = ?1",
+            params![run_id],
+            |row| Ok((Self::row_to_summary(row)?, row.get::<_, String>(6)?)),
+        ).optional()?;
+        let Some((summary, input)) = header else {
+            return Ok(None);
+        };
+
+        let mut stmt = conn.prepare(
+            "SELECT node_id, name, status, input, output, error, started_at, duration_ms, attempts
+             FROM workflow_run_nodes WHERE run_id = ?1 ORDER BY position",
+        )?;
+        let nodes = stmt.query_map(params![run_id], |row| {
+            Ok(NodeRunResult {
+                node_id: row.get(0)?,
+                name: row.get(1)?,
+                status: parse_status(&row.get::<_, String>(2)?),
+                input: parse_json(&row.get::<_, String>(3)?),
+                output: parse_json(&row.get::<_, String>(4)?),
+                error: row.get(5)?,
+                started_at: row.get::<_, Option<String>>(6)?.as_deref().and_then(parse_time),
+                duration_ms: row.get::<_, i64>(7)? as u64,
+                attempts: row.get(8)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(WorkflowRun {
+            id: summary.id,
+            workflow_id: summary.workflow_id,
+            status: summary.status,
+            input: parse_json(&input),
+            error: summary.error,
+            started_at: summary.started_at,
+            finished_at: summary.finished_at,
+            nodes,
+        }))
+    }
+
+    fn row_to_summary(row: &Row) -> rusqlite::Result<WorkflowRunSummary> {
+        let started_at: String = row.get(4)?;
+        Ok(WorkflowRunSummary {
+            id: row.get(0)?,
+            workflow_id: row.get(1)?,
+            status: parse_status(&row.get::<_, String>(2)?),
+            error: row.get(3)?,
+            started_at: parse_time(&started_at).unwrap_or_else(Utc::now),
+            finished_at: row.get::<_, Option<String>>(5)?.as_deref().and_then(parse_time),
+        })
+    }
+}
+
+/// Brings a stored definition up to the current `AgentWorkflow` shape.
+fn migrate_definition(version: i64, definition: &str) -> Result<AgentWorkflow> {
+    if version > WORKFLOW_SCHEMA_VERSION {
+        return Err(anyhow!(
+            "schema version {} is newer than this app supports ({})",
+            version,
+            WORKFLOW_SCHEMA_VERSION
+        ));
+    }
+    let mut value: serde_json::Value = serde_json::from_str(definition)?;
+
+    // Version 1 matched executors by display name
+    if version < 2 {
+        if let Some(nodes) = value.get_mut("nodes").and_then(|n| n.as_array_mut()) {
+            for node in nodes {
+                if node.get("agent_key").is_none() {
+                    let key = legacy_agent_key(node["name"].as_str().unwrap_or_default());
+                    node["agent_key"] = serde_json::Value::String(key);
+                }
+            }
+        }
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Statuses are stored by their serde names so the database matches the API.
+fn status_name<T: serde::Serialize>(status: &T) -> Result<String> {
+    match serde_json::to_value(status)? {
+        serde_json::Value::String(name) => Ok(name),
+        other => Err(anyhow!("Unexpected status value {}", other)),
+    }
+}
+
+fn parse_status<T: serde::de::DeserializeOwned + Default>(name: &str) -> T {
+    serde_json::from_value(serde_json::Value::String(name.to_string())).unwrap_or_default()
+}
+
+fn parse_json(text: &str) -> serde_json::Value {
+    serde_json::from_str(text).unwrap_or(serde_json::Value::Null)
+}
+
+fn parse_time(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text).ok().map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{AgentExecutor, AgentNode, AgentStatus, AgentSystem, AgentType, RunStatus};
+    use crate::security::SecurityManager;
+    use async_trait::async_trait;
+
+    struct FailingAgent;
+
+    #[async_trait]
+    impl AgentExecutor for FailingAgent {
+        async fn execute(&self, _input: serde_json::Value, _config: &serde_json::Value) -> Result<serde_json::Value> {
+            Err(anyhow!("disk full"))
+        }
+
+        fn get_type(&self) -> AgentType { AgentType::Output }
+        fn get_name(&self) -> &str { "Fail" }
+    }
+
+    fn node(id: &str, name: &str, agent_key: &str, agent_type: AgentType) -> AgentNode {
+        AgentNode {
+            id: id.to_string(),
+            name: name.to_string(),
+            agent_key: agent_key.to_string(),
+            agent_type,
+            x: 120.0,
+            y: 40.0,
+            config: serde_json::json!({ "mapping": { "query": "$.query" } }),
+            status: AgentStatus::Idle,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_workflows_and_runs_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let system = AgentSystem::new(AgentStore::open(dir.path()).unwrap(), Arc::new(SecurityManager::new())).await.unwrap();
+        system.register_executor("fail", Box::new(FailingAgent)).await;
+
+        let id = system.create_workflow("Nightly".to_string(), "Sync notes".to_string()).await.unwrap();
+        system.add_node(&id, node("in", "Start", "data_transform", AgentType::Input)).await.unwrap();
+        system.add_node(&id, node("out", "Fail", "fail", AgentType::Output)).await.unwrap();
+        system.add_connection(&id, crate::agents::AgentConnection {
+            id: "c1".to_string(),
+            from: "in".to_string(),
+            to: "out".to_string(),
+            label: None,
+            condition: None,
+        }).await.unwrap();
+
+        let err = system.execute_workflow(&id, serde_json::json!({ "query": "hi" }), |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("disk full"));
+        drop(system);
+
+        let system = AgentSystem::new(AgentStore::open(dir.path()).unwrap(), Arc::new(SecurityManager::new())).await.unwrap();
+        let workflow = system.get_workflow(&id).await.unwrap();
+        assert_eq!(workflow.nodes.len(), 2);
+        assert_eq!(workflow.nodes[0].x, 120.0);
+        assert_eq!(workflow.connections[0].to, "out");
+
+        let runs = system.list_runs(Some(&id), 10).await.unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].status, RunStatus::Error);
+
+        let run = system.get_run(&runs[0].id).await.unwrap();
+        assert_eq!(run.input["query"], "hi");
+        assert_eq!(run.nodes[0].status, AgentStatus::Success);
+        assert_eq!(run.nodes[0].output["query"], "hi");
+        assert_eq!(run.nodes[1].status, AgentStatus::Error);
+        assert_eq!(run.nodes[1].error.as_deref(), Some("disk full"));
+        assert!(run.finished_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_run_history_is_capped_and_pruned() {
+        let store = AgentStore::open_in_memory().unwrap();
+        let id = "wf-busy".to_string();
+        store.save_workflow(&AgentWorkflow {
+            id: id.clone(),
+            name: "Busy".to_string(),
+            description: String::new(),
+            nodes: Vec::new(),
+            connections: Vec::new(),
+            settings: Default::default(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }).await.unwrap();
+
+        let run = |started_at: DateTime<Utc>| WorkflowRun {
+            id: uuid::Uuid::new_v4().to_string(),
+            workflow_id: id.clone(),
+            status: RunStatus::Success,
+            input: serde_json::json!({ "text": "x".repeat(STORED_VALUE_LIMIT * 2) }),
+            error: None,
+            started_at,
+            finished_at: Some(started_at),
+            nodes: vec![NodeRunResult {
+                node_id: "n1".to_string(),
+                name: "Echo".to_string(),
+                status: AgentStatus::Success,
+                input: serde_json::Value::Null,
+                output: serde_json::json!({ "text": "é".repeat(STORED_VALUE_LIMIT) }),
+                error: None,
+                started_at: Some(started_at),
+                duration_ms: 1,
+                attempts: 1,
+            }],
+        };
+
+        let stale = run(Utc::now() - chrono::Duration::days(MAX_RUN_AGE_DAYS + 1));
+        store.start_run(&stale).await.unwrap();
+        store.finish_run(&stale).await.unwrap();
+        for i in 0..MAX_RUNS_PER_WORKFLOW + 5 {
+            let recent = run(Utc::now() - chrono::Duration::minutes(i as i64));
+            store.start_run(&recent).await.unwrap();
+            store.finish_run(&recent).await.unwrap();
+        }
+
+        let runs = store.list_runs(Some(&id), 1000).await.unwrap();
+        assert_eq!(runs.len(), MAX_RUNS_PER_WORKFLOW);
+        assert!(runs.iter().all(|summary| summary.id != stale.id));
+
+        let kept = store.get_run(&runs[0].id).await.unwrap().unwrap();
+        assert!(kept.input.to_string().len() <= STORED_VALUE_LIMIT + 8);
+        assert!(kept.nodes[0].output.as_str().unwrap().ends_with('…'));
+    }
+
+    #[tokio::test]
+    async fn test_version_one_workflows_get_agent_keys() {
+        let store = AgentStore::open_in_memory().unwrap();
+        let legacy = serde_json::json!({
+            "id": "wf-1",
+            "name": "Legacy",
+            "description": "",
+            "nodes": [{
+                "id": "n1",
+                "name": "Intent Classifier",
+                "agent_type": "processor",
+                "x": 0.0,
+                "y": 0.0,
+                "config": {},
+                "status": "idle",
+                "created_at": Utc::now(),
+                "updated_at": Utc::now()
+            }],
+            "connections": [],
+            "created_at": Utc::now(),
+            "updated_at": Utc::now()
+        });
+        {
+            let conn = store.conn.lock().await;
+            conn.execute(
+                "INSERT INTO workflows (id, name, schema_version, definition, created_at, updated_at) VALUES ('wf-1', 'Legacy', 1, ?1, '', '')",
+                params![legacy.to_string()],
+            ).unwrap();
+        }
+
+        let workflows = store.load_workflows().await.unwrap();
+        assert_eq!(workflows[0].nodes[0].agent_key, "intent_classifier");
+
+        let conn = store.conn.lock().await;
+        let version: i64 = conn.query_row("SELECT schema_version FROM workflows WHERE id = 'wf-1'", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, WORKFLOW_SCHEMA_VERSION);
+    }
+}
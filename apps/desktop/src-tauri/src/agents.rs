@@ -1,16 +1,31 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 use async_trait::async_trait;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use base64::Engine as _;
+use base64::engine::general_purpose;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::agent_conditions::Condition;
+use crate::agent_store::AgentStore;
+use crate::knowledge_datasets::{read_rows, DatasetFormat, MAX_DATASET_BYTES};
+use crate::security::SecurityManager;
+use crate::tool_executor::{Tool, TerminalTool};
+use crate::voice::VoiceSessionConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentNode {
     pub id: String,
     pub name: String,
+    #[serde(default)]
+    pub agent_key: String, // Executor registry key, e.g. "intent_classifier"
     pub agent_type: AgentType,
     pub x: f32,
     pub y: f32,
@@ -31,14 +46,17 @@ pub enum AgentType {
     Storage,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AgentStatus {
+    #[default]
     Idle,
     Running,
     Success,
     Error,
     Disabled,
+    Skipped,
+    Cancelled,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,40 +75,234 @@ pub struct AgentWorkflow {
     pub description: String,
     pub nodes: Vec<AgentNode>,
     pub connections: Vec<AgentConnection>,
+    #[serde(default)]
+    pub settings: WorkflowSettings,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// How a workflow's nodes are scheduled when it runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkflowSettings {
+    #[serde(default)]
+    pub max_concurrency: Option<usize>, // Nodes of one run executing at once; capped by the global limit
+    #[serde(default)]
+    pub cancel_on_failure: bool,        // Abort running sibling branches as soon as a node fails
+}
+
+/// Outcome of a single node within a workflow run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeRunResult {
+    pub node_id: String,
+    pub name: String,
+    pub status: AgentStatus,
+    pub input: serde_json::Value,
+    pub output: serde_json::Value,
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>, // None for skipped nodes
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub attempts: u32, // Executions including retries; 0 for skipped nodes
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Success,
+    #[default]
+    Error,
+}
+
+/// A recorded workflow execution with per-node results in execution order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRun {
+    pub id: String,
+    pub workflow_id: String,
+    pub status: RunStatus,
+    pub input: serde_json::Value,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub nodes: Vec<NodeRunResult>,
+}
+
+/// Live update for one node of a running workflow. Outputs are truncated to keep events small;
+/// the run history keeps a larger excerpt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeEvent {
+    pub run_id: String,
+    pub workflow_id: String,
+    pub node_id: String,
+    pub status: AgentStatus,
+    pub timestamp: DateTime<Utc>,
+    pub duration_ms: Option<u64>,
+    pub output: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+impl NodeEvent {
+    /// Tauri event the frontend listens on for this update
+    pub fn event_name(&self) -> &'static str {
+        match self.status {
+            AgentStatus::Running => "workflow-node-started",
+            AgentStatus::Error => "workflow-node-failed",
+            _ => "workflow-node-finished",
+        }
+    }
+}
+
+/// Maximum serialized size of an output carried in a `NodeEvent`
+const EVENT_OUTPUT_LIMIT: usize = 2048;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRunSummary {
+    pub id: String,
+    pub workflow_id: String,
+    pub status: RunStatus,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
 #[async_trait]
 pub trait AgentExecutor: Send + Sync {
     async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value>;
+    
+    /// Runs `execute` until `cancel` fires. Executors whose work outlives their future, such
+    /// as blocking reads, override this to stop that work themselves.
+    async fn execute_cancellable(&self, input: serde_json::Value, config: &serde_json::Value, cancel: CancellationToken) -> Result<serde_json::Value> {
+        tokio::select! {
+            outcome = self.execute(input, config) => outcome,
+            _ = cancel.cancelled() => Err(anyhow!("Cancelled")),
+        }
+    }
+    
     fn get_type(&self) -> AgentType;
     fn get_name(&self) -> &str;
 }
 
+/// App settings the built-in agents depend on, refreshed via `AgentSystem::configure`
+#[derive(Debug, Clone)]
+pub struct AgentEnvironment {
+    pub openai_api_key: Option<String>,
+    pub openai_organization_id: Option<String>,
+    pub openai_model: String,
+    pub ollama_model: String,
+    pub offline_mode: bool,     // Forces local STT, TTS and LLMs
+    pub stt_provider: String,   // "openai" or "whisper-cpp"
+    pub tts_provider: String,   // "openai" or "piper"
+    pub voice_model: String,
+    pub response_voice: String,
+    pub allowed_roots: Vec<String>,
+}
+
+impl Default for AgentEnvironment {
+    fn default() -> Self {
+        let config = &crate::config::CONFIG;
+        Self {
+            openai_api_key: crate::config::get_openai_api_key(),
+            openai_organization_id: None,
+            openai_model: config.default_openai_chat_model.clone(),
+            ollama_model: config.default_ollama_model.clone(),
+            offline_mode: false,
+            stt_provider: "openai".to_string(),
+            tts_provider: "openai".to_string(),
+            voice_model: "whisper-1".to_string(),
+            response_voice: config.default_tts_voice.clone(),
+            allowed_roots: Vec::new(),
+        }
+    }
+}
+
+type SharedEnvironment = Arc<RwLock<AgentEnvironment>>;
+
+/// Intents the classifier chooses from when a node does not configure its own
+const DEFAULT_INTENTS: [&str; 4] = ["weather_query", "file_operation", "command_execution", "general_query"];
+
+/// Upper bound on the upstream context passed to the response generator
+const MAX_CONTEXT_CHARS: usize = 8000;
+
+/// Largest file the File Reader loads into a workflow
+const MAX_FILE_READ_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Rows returned for tabular files unless the node sets `maxRows`
+const DEFAULT_FILE_ROWS: usize = 1000;
+
+/// Nodes executing at once across every running workflow
+const MAX_CONCURRENT_NODES: usize = 8;
+
+/// Retry limits for a node's `maxRetries` and `retryBackoff` config keys
+const MAX_NODE_RETRIES: u64 = 10;
+const DEFAULT_RETRY_BACKOFF_MS: u64 = 500;
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
 // Built-in agent executors
-pub struct VoiceInputAgent;
-pub struct IntentClassifierAgent;
-pub struct CommandExecutorAgent;
-pub struct ResponseGeneratorAgent;
-pub struct VoiceOutputAgent;
-pub struct FileReaderAgent;
+pub struct VoiceInputAgent {
+    environment: SharedEnvironment,
+}
+
+pub struct IntentClassifierAgent {
+    environment: SharedEnvironment,
+}
+
+pub struct CommandExecutorAgent {
+    terminal: TerminalTool,
+}
+
+pub struct ResponseGeneratorAgent {
+    environment: SharedEnvironment,
+}
+
+pub struct VoiceOutputAgent {
+    environment: SharedEnvironment,
+}
+
+pub struct FileReaderAgent {
+    environment: SharedEnvironment,
+}
+
 pub struct DataTransformAgent;
 pub struct ConditionalAgent;
 
 #[async_trait]
 impl AgentExecutor for VoiceInputAgent {
-    async fn execute(&self, _input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
-        let stt_provider = config["sttProvider"].as_str().unwrap_or("openai");
-        let wake_word = config["wakeWord"].as_str().unwrap_or("Hey Brain");
+    async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
+        let environment = self.environment.read().await.clone();
+        let wake_word = config["wakeWord"].as_str();
         
-        // In real implementation, this would capture audio and transcribe it
-        Ok(serde_json::json!({
-            "transcript": "Sample voice input",
-            "provider": stt_provider,
-            "wakeWord": wake_word,
-            "timestamp": Utc::now()
-        }))
+        // Typed text skips transcription so workflows can be tried without a microphone
+        if let Some(text) = input["transcript"].as_str().or(input["text"].as_str()) {
+            return Ok(voice_input_output(text, "text", wake_word));
+        }
+        
+        let encoded = input["audio"].as_str()
+            .ok_or_else(|| anyhow!("Voice Input needs base64 `audio` or a `transcript` in its input"))?;
+        let encoded = encoded.split_once("base64,").map_or(encoded, |(_, data)| data);
+        let audio = general_purpose::STANDARD.decode(encoded.trim())
+            .map_err(|e| anyhow!("Invalid audio data: {}", e))?;
+        
+        let stt_provider = if environment.offline_mode {
+            "whisper-cpp".to_string()
+        } else {
+            config["sttProvider"].as_str().unwrap_or(&environment.stt_provider).to_string()
+        };
+        let session = VoiceSessionConfig {
+            mode: "chain".to_string(),
+            stt_provider: stt_provider.clone(),
+            tts_provider: environment.tts_provider.clone(),
+            voice_model: config["model"].as_str().unwrap_or(&environment.voice_model).to_string(),
+            response_voice: environment.response_voice.clone(),
+        };
+        
+        let transcript = crate::voice::with_voice_manager(|manager| {
+            if environment.openai_api_key.is_some() {
+                manager.set_api_key(environment.openai_api_key.clone());
+            }
+            Box::pin(async move { manager.transcribe_audio(&audio, &session).await })
+        }).await?;
+        
+        Ok(voice_input_output(&transcript, &stt_provider, wake_word))
     }
     
     fn get_type(&self) -> AgentType { AgentType::Input }
@@ -103,27 +315,24 @@ impl AgentExecutor for IntentClassifierAgent {
         let transcript = input["transcript"].as_str()
             .ok_or_else(|| anyhow!("No transcript in input"))?;
         
-        let model = config["model"].as_str().unwrap_or("gpt-4o");
-        let temperature = config["temperature"].as_f64().unwrap_or(0.3);
-        
-        // In real implementation, this would use AI to classify intent
-        let intent = if transcript.contains("weather") {
-            "weather_query"
-        } else if transcript.contains("file") || transcript.contains("open") {
-            "file_operation"
-        } else if transcript.contains("run") || transcript.contains("execute") {
-            "command_execution"
-        } else {
-            "general_query"
+        let intents: Vec<String> = match config["intents"].as_array() {
+            Some(intents) => intents.iter().filter_map(|i| i.as_str()).map(str::to_string).collect(),
+            None => DEFAULT_INTENTS.iter().map(|i| i.to_string()).collect(),
         };
+        if intents.is_empty() {
+            return Err(anyhow!("Intent Classifier has no intents configured"));
+        }
         
-        Ok(serde_json::json!({
-            "intent": intent,
-            "confidence": 0.95,
-            "transcript": transcript,
-            "model": model,
-            "temperature": temperature
-        }))
+        let environment = self.environment.read().await.clone();
+        let messages = [serde_json::json!({ "role": "user", "content": build_intent_prompt(transcript, &intents) })];
+        let temperature = config["temperature"].as_f64().unwrap_or(0.0);
+        let reply = complete_chat(&environment, config, &messages, temperature, true).await?;
+        
+        let mut classification = parse_intent_reply(&reply.content, &intents)?;
+        classification["transcript"] = serde_json::json!(transcript);
+        classification["model"] = serde_json::json!(reply.model);
+        classification["provider"] = serde_json::json!(reply.provider);
+        Ok(classification)
     }
     
     fn get_type(&self) -> AgentType { AgentType::Processor }
@@ -133,20 +342,45 @@ impl AgentExecutor for IntentClassifierAgent {
 #[async_trait]
 impl AgentExecutor for CommandExecutorAgent {
     async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
-        let command = input["command"].as_str()
-            .unwrap_or(input["transcript"].as_str().unwrap_or(""));
+        // Never fall back to the raw transcript: only explicit commands are run. Commands from
+        // upstream nodes (e.g. the intent classifier's model output) need a per-node opt-in.
+        let configured = config["command"].as_str().map(str::trim).filter(|c| !c.is_empty());
+        let command = match configured {
+            Some(command) => command,
+            None => {
+                let command = input["command"].as_str()
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .ok_or_else(|| anyhow!("No command in input or node config"))?;
+                if !config["allowInputCommands"].as_bool().unwrap_or(false) {
+                    return Err(anyhow!(
+                        "Refusing to run `{}` from upstream input: set allowInputCommands in the node config to allow it",
+                        command
+                    ));
+                }
+                command
+            }
+        };
         
-        let max_retries = config["maxRetries"].as_u64().unwrap_or(3);
-        let timeout = config["timeout"].as_u64().unwrap_or(30000);
+        let mut args = serde_json::json!({ "command": command });
+        if let Some(dir) = config["workingDir"].as_str() {
+            args["working_dir"] = serde_json::json!(dir);
+        }
+        
+        // The terminal tool runs it without a shell and enforces the command allowlist
+        let result = self.terminal.execute(args).await?;
+        if !result.success {
+            return Err(anyhow!(
+                "Command `{}` failed: {}",
+                command,
+                result.error.unwrap_or_else(|| "unknown error".to_string()).trim()
+            ));
+        }
         
-        // In real implementation, this would execute commands safely
         Ok(serde_json::json!({
             "command": command,
             "status": "executed",
-            "output": format!("Executed: {}", command),
-            "exitCode": 0,
-            "maxRetries": max_retries,
-            "timeout": timeout
+            "output": result.output,
         }))
     }
     
@@ -157,18 +391,41 @@ impl AgentExecutor for CommandExecutorAgent {
 #[async_trait]
 impl AgentExecutor for ResponseGeneratorAgent {
     async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
-        let query = input["query"].as_str()
-            .unwrap_or(input["transcript"].as_str().unwrap_or(""));
+        if input.is_null() {
+            return Err(anyhow!("No input to respond to"));
+        }
         
-        let model = config["model"].as_str().unwrap_or("gpt-4o");
+        let query = input["query"].as_str()
+            .or(input["transcript"].as_str())
+            .or(input.as_str())
+            .unwrap_or("Summarize the result of the previous step for the user.");
         let style = config["style"].as_str().unwrap_or("concise");
+        let system = config["systemPrompt"].as_str().map(str::to_string).unwrap_or_else(|| {
+            format!("You are LocalBrain, a helpful desktop assistant. Answer in a {} style.", style)
+        });
+        
+        // Anything beyond the bare question (intent, command output, file contents) is context
+        let mut content = query.to_string();
+        if !input.is_string() {
+            let context: String = serde_json::to_string_pretty(&input)?.chars().take(MAX_CONTEXT_CHARS).collect();
+            content.push_str("\n\nContext from earlier steps:\n");
+            content.push_str(&context);
+        }
+        
+        let environment = self.environment.read().await.clone();
+        let messages = [
+            serde_json::json!({ "role": "system", "content": system }),
+            serde_json::json!({ "role": "user", "content": content }),
+        ];
+        let temperature = config["temperature"].as_f64().unwrap_or(0.7);
+        let reply = complete_chat(&environment, config, &messages, temperature, false).await?;
         
-        // In real implementation, this would generate AI responses
         Ok(serde_json::json!({
-            "response": format!("Generated response for: {}", query),
-            "model": model,
+            "response": reply.content,
+            "model": reply.model,
+            "provider": reply.provider,
             "style": style,
-            "tokens": 150
+            "usage": reply.usage
         }))
     }
     
@@ -180,17 +437,38 @@ impl AgentExecutor for ResponseGeneratorAgent {
 impl AgentExecutor for VoiceOutputAgent {
     async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
         let text = input["response"].as_str()
-            .unwrap_or(input["text"].as_str().unwrap_or(""));
+            .or(input["text"].as_str())
+            .or(input.as_str())
+            .filter(|t| !t.trim().is_empty())
+            .ok_or_else(|| anyhow!("No text to speak in input"))?
+            .to_string();
         
-        let tts_provider = config["ttsProvider"].as_str().unwrap_or("openai");
-        let voice = config["voice"].as_str().unwrap_or("maple");
+        let environment = self.environment.read().await.clone();
+        let tts_provider = if environment.offline_mode {
+            "piper".to_string()
+        } else {
+            config["ttsProvider"].as_str().unwrap_or(&environment.tts_provider).to_string()
+        };
+        let voice = config["voice"].as_str().unwrap_or(&environment.response_voice).to_string();
+        let options = HashMap::from([
+            ("provider".to_string(), tts_provider.clone()),
+            ("voice".to_string(), voice.clone()),
+        ]);
+        
+        let speech_text = text.clone();
+        let audio = crate::voice::with_voice_manager(|manager| {
+            if environment.openai_api_key.is_some() {
+                manager.set_api_key(environment.openai_api_key.clone());
+            }
+            Box::pin(async move { manager.speak_text(&speech_text, &options).await })
+        }).await?;
         
-        // In real implementation, this would synthesize speech
         Ok(serde_json::json!({
             "text": text,
             "provider": tts_provider,
             "voice": voice,
-            "audioUrl": "data:audio/mp3;base64,..."
+            "mimeType": "audio/mpeg",
+            "audioUrl": format!("data:audio/mpeg;base64,{}", general_purpose::STANDARD.encode(&audio))
         }))
     }
     
@@ -198,26 +476,496 @@ impl AgentExecutor for VoiceOutputAgent {
     fn get_name(&self) -> &str { "Voice Output" }
 }
 
+#[async_trait]
+impl AgentExecutor for FileReaderAgent {
+    async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
+        self.execute_cancellable(input, config, CancellationToken::new()).await
+    }
+    
+    async fn execute_cancellable(&self, input: serde_json::Value, config: &serde_json::Value, cancel: CancellationToken) -> Result<serde_json::Value> {
+        let requested = config["path"].as_str()
+            .or(input["path"].as_str())
+            .ok_or_else(|| anyhow!("No path in input or node config"))?;
+        let roots = self.environment.read().await.allowed_roots.clone();
+        let path = resolve_allowed_path(requested, &roots)?;
+        
+        let size = tokio::fs::metadata(&path).await?.len();
+        if size > MAX_FILE_READ_BYTES {
+            return Err(anyhow!("{} is {} bytes; the File Reader limit is {}", path.display(), size, MAX_FILE_READ_BYTES));
+        }
+        
+        let format = config["format"].as_str().unwrap_or("auto").to_lowercase();
+        let max_rows = config["maxRows"].as_u64().map(|n| n as usize).unwrap_or(DEFAULT_FILE_ROWS);
+        // Aborting the task doesn't stop a blocking thread, so the read itself checks the token
+        tokio::task::spawn_blocking(move || read_file_value(&path, &format, max_rows, &cancel)).await?
+    }
+    
+    fn get_type(&self) -> AgentType { AgentType::Input }
+    fn get_name(&self) -> &str { "File Reader" }
+}
+
+#[async_trait]
+impl AgentExecutor for DataTransformAgent {
+    async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
+        let mapping = &config["mapping"];
+        let template = config["template"].as_str();
+        if mapping.is_null() && template.is_none() {
+            return Err(anyhow!("Data Transform needs a `mapping` or a `template` in its config"));
+        }
+        
+        let mut output = if mapping.is_null() {
+            serde_json::json!({})
+        } else {
+            apply_mapping(mapping, &input)?
+        };
+        if let Some(template) = template {
+            let text = render_template(template, &input)?;
+            match output.as_object_mut() {
+                Some(object) => { object.insert("text".to_string(), serde_json::json!(text)); }
+                None => return Err(anyhow!("`mapping` must be an object when a `template` is also set")),
+            }
+        }
+        Ok(output)
+    }
+    
+    fn get_type(&self) -> AgentType { AgentType::Transform }
+    fn get_name(&self) -> &str { "Data Transform" }
+}
+
+#[async_trait]
+impl AgentExecutor for ConditionalAgent {
+    async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
+        let source = config["condition"].as_str()
+            .ok_or_else(|| anyhow!("Conditional needs a `condition` in its config"))?;
+        let condition = Condition::parse(source).map_err(|e| anyhow!("Invalid condition: {}", e))?;
+        let matched = condition.evaluate(&input)?;
+        
+        // The executor routes `true`/`false` labelled edges and forwards `value` downstream
+        Ok(serde_json::json!({
+            "matched": matched,
+            "branch": if matched { "true" } else { "false" },
+            "value": input
+        }))
+    }
+    
+    fn get_type(&self) -> AgentType { AgentType::Condition }
+    fn get_name(&self) -> &str { "Conditional" }
+}
+
+/// Resolves `requested` and checks it lies inside one of the allowed roots. Both sides are
+/// canonicalized so `..` segments and symlinks cannot escape a root.
+fn resolve_allowed_path(requested: &str, allowed_roots: &[String]) -> Result<PathBuf> {
+    let path = std::path::Path::new(requested).canonicalize()
+        .map_err(|e| anyhow!("Cannot open {}: {}", requested, e))?;
+    let allowed = allowed_roots.iter()
+        .filter_map(|root| std::path::Path::new(root).canonicalize().ok())
+        .any(|root| path.starts_with(root));
+    if !allowed {
+        return Err(anyhow!("{} is outside the allowed roots", requested));
+    }
+    if !path.is_file() {
+        return Err(anyhow!("{} is not a file", requested));
+    }
+    Ok(path)
+}
+
+fn read_file_value(path: &std::path::Path, format: &str, max_rows: usize, cancel: &CancellationToken) -> Result<serde_json::Value> {
+    let open = || -> Result<CancellableReader<std::fs::File>> {
+        Ok(CancellableReader { inner: std::fs::File::open(path)?, cancel: cancel.clone() })
+    };
+    let read_all = || -> Result<Vec<u8>> {
+        if std::fs::metadata(path)?.len() > MAX_DATASET_BYTES {
+            return Err(anyhow!("{} is larger than {} MB", path.display(), MAX_DATASET_BYTES / (1024 * 1024)));
+        }
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut open()?, &mut bytes)?;
+        Ok(bytes)
+    };
+    let dataset_format = match format {
+        "auto" => DatasetFormat::from_path(path),
+        "text" => None,
+        other => Some(serde_json::from_value::<DatasetFormat>(serde_json::json!(other))
+            .map_err(|_| anyhow!("Unknown file format '{}'", other))?),
+    };
+    let display_path = path.to_string_lossy();
+    
+    match dataset_format {
+        // A JSON document is returned as-is rather than split into rows
+        Some(DatasetFormat::Json) => {
+            let data: serde_json::Value = serde_json::from_slice(&read_all()?)
+                .map_err(|e| anyhow!("{} is not valid JSON: {}", display_path, e))?;
+            Ok(serde_json::json!({ "path": display_path, "format": "json", "data": data }))
+        }
+        Some(dataset_format) => {
+            let mut rows = Vec::new();
+            let mut columns: Vec<String> = Vec::new();
+            let mut total = 0usize;
+            let file = CancellableFile { file: std::fs::File::open(path)?, cancel: cancel.clone() };
+            read_rows(dataset_format, file, &mut |row| {
+                total += 1;
+                if rows.len() < max_rows {
+                    for (name, _) in &row {
+                        if !columns.contains(name) {
+                            columns.push(name.clone());
+                        }
+                    }
+                    rows.push(serde_json::Value::Object(row.into_iter().collect()));
+                }
+            })?;
+            Ok(serde_json::json!({
+                "path": display_path,
+                "format": dataset_format,
+                "columns": columns,
+                "rows": rows,
+                "rowCount": total,
+                "truncated": total > rows.len()
+            }))
+        }
+        None => {
+            let content = String::from_utf8_lossy(&read_all()?).into_owned();
+            Ok(serde_json::json!({ "path": display_path, "format": "text", "content": content }))
+        }
+    }
+}
+
+/// Fails reads once its token is cancelled, so a file read on a blocking thread stops with its run
+struct CancellableReader<R> {
+    inner: R,
+    cancel: CancellationToken,
+}
+
+impl<R: std::io::Read> std::io::Read for CancellableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.cancel.is_cancelled() {
+            return Err(std::io::Error::other("Cancelled"));
+        }
+        self.inner.read(buf)
+    }
+}
+
+/// A dataset file whose reads stop with its run; Parquet reads it in place by offset
+struct CancellableFile {
+    file: std::fs::File,
+    cancel: CancellationToken,
+}
+
+impl parquet::file::reader::Length for CancellableFile {
+    fn len(&self) -> u64 {
+        self.file.len()
+    }
+}
+
+impl parquet::file::reader::ChunkReader for CancellableFile {
+    type T = CancellableReader<std::io::BufReader<std::fs::File>>;
+
+    fn get_read(&self, start: u64) -> parquet::errors::Result<Self::T> {
+        Ok(CancellableReader { inner: self.file.get_read(start)?, cancel: self.cancel.clone() })
+    }
+
+    fn get_bytes(&self, start: u64, length: usize) -> parquet::errors::Result<bytes::Bytes> {
+        if self.cancel.is_cancelled() {
+            return Err(std::io::Error::other("Cancelled").into());
+        }
+        self.file.get_bytes(start, length)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Key(String),
+    Index(i64), // Negative indexes count from the end
+    Wildcard,
+}
+
+/// Parses JSONPath-style (`$.items[0].name`, `$.items[*]`) and jq-style (`.items[-1]`) paths.
+fn parse_selectors(path: &str) -> Result<Vec<Selector>> {
+    let trimmed = path.trim();
+    let rest = trimmed.strip_prefix('$').unwrap_or(trimmed);
+    let chars: Vec<char> = rest.chars().collect();
+    let mut selectors = Vec::new();
+    let mut i = 0;
+    
+    while i < chars.len() {
+        match chars[i] {
+            '.' => {
+                i += 1;
+                let start = i;
+                while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                    i += 1;
+                }
+                let key: String = chars[start..i].iter().collect();
+                match key.as_str() {
+                    "" => {} // A bare `.`, or `.[0]` as in jq
+                    "*" => selectors.push(Selector::Wildcard),
+                    _ => selectors.push(Selector::Key(key)),
+                }
+            }
+            '[' => {
+                let end = chars[i..].iter().position(|&c| c == ']')
+                    .map(|offset| i + offset)
+                    .ok_or_else(|| anyhow!("Unclosed `[` in path '{}'", path))?;
+                let inner: String = chars[i + 1..end].iter().collect();
+                let inner = inner.trim();
+                let selector = if inner == "*" {
+                    Selector::Wildcard
+                } else if let Some(quoted) = inner.strip_prefix('"').and_then(|q| q.strip_suffix('"'))
+                    .or_else(|| inner.strip_prefix('\'').and_then(|q| q.strip_suffix('\'')))
+                {
+                    Selector::Key(quoted.to_string())
+                } else {
+                    Selector::Index(inner.parse().map_err(|_| anyhow!("Invalid index '{}' in path '{}'", inner, path))?)
+                };
+                selectors.push(selector);
+                i = end + 1;
+            }
+            other => return Err(anyhow!("Unexpected '{}' in path '{}'; paths start with `$` or `.`", other, path)),
+        }
+    }
+    
+    Ok(selectors)
+}
+
+/// Selects from `value`. Missing keys give null; after a wildcard the result is an array
+/// of every match.
+fn select_path(value: &serde_json::Value, path: &str) -> Result<serde_json::Value> {
+    let selectors = parse_selectors(path)?;
+    let mut current = vec![value.clone()];
+    let mut multiple = false;
+    
+    for selector in &selectors {
+        current = current.into_iter().flat_map(|v| -> Vec<serde_json::Value> {
+            match (selector, v) {
+                (Selector::Key(key), serde_json::Value::Object(mut map)) => map.remove(key).into_iter().collect(),
+                (Selector::Index(index), serde_json::Value::Array(mut items)) => {
+                    let len = items.len() as i64;
+                    let position = if *index < 0 { len + index } else { *index };
+                    if (0..len).contains(&position) { vec![items.swap_remove(position as usize)] } else { Vec::new() }
+                }
+                (Selector::Wildcard, serde_json::Value::Array(items)) => items,
+                (Selector::Wildcard, serde_json::Value::Object(map)) => map.into_iter().map(|(_, v)| v).collect(),
+                _ => Vec::new(),
+            }
+        }).collect();
+        multiple |= *selector == Selector::Wildcard;
+    }
+    
+    Ok(if multiple {
+        serde_json::Value::Array(current)
+    } else {
+        current.into_iter().next().unwrap_or(serde_json::Value::Null)
+    })
+}
+
+/// Strings starting with `$` or `.` are paths into the input, objects map recursively
+/// and anything else is a literal.
+fn apply_mapping(mapping: &serde_json::Value, input: &serde_json::Value) -> Result<serde_json::Value> {
+    match mapping {
+        serde_json::Value::String(path) if path.starts_with('$') || path.starts_with('.') => select_path(input, path),
+        serde_json::Value::Object(fields) => {
+            let mut output = serde_json::Map::new();
+            for (key, field) in fields {
+                output.insert(key.clone(), apply_mapping(field, input)?);
+            }
+            Ok(serde_json::Value::Object(output))
+        }
+        literal => Ok(literal.clone()),
+    }
+}
+
+/// Replaces `{{ path }}` placeholders; strings are inserted raw, other values as JSON.
+fn render_template(template: &str, input: &serde_json::Value) -> Result<String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..].find("}}")
+            .ok_or_else(|| anyhow!("Unclosed `{{{{` in template"))?;
+        let path = rest[start + 2..start + end].trim();
+        let path = if path.starts_with('$') || path.starts_with('.') { path.to_string() } else { format!(".{}", path) };
+        match select_path(input, &path)? {
+            serde_json::Value::Null => {}
+            serde_json::Value::String(text) => rendered.push_str(&text),
+            other => rendered.push_str(&other.to_string()),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+fn voice_input_output(transcript: &str, provider: &str, wake_word: Option<&str>) -> serde_json::Value {
+    let trimmed = transcript.trim();
+    let (text, detected) = match wake_word.filter(|w| !w.is_empty()) {
+        Some(wake_word) if trimmed.len() >= wake_word.len()
+            && trimmed.is_char_boundary(wake_word.len())
+            && trimmed[..wake_word.len()].eq_ignore_ascii_case(wake_word) => {
+            (trimmed[wake_word.len()..].trim_start_matches([',', '.', '!', ' ']), true)
+        }
+        _ => (trimmed, false),
+    };
+    
+    serde_json::json!({
+        "transcript": text,
+        "wakeWordDetected": detected,
+        "provider": provider,
+        "timestamp": Utc::now()
+    })
+}
+
+pub fn build_intent_prompt(transcript: &str, intents: &[String]) -> String {
+    format!(
+        "Classify the user's request into exactly one intent.\n\
+         Reply with JSON only, in the form \
+         {{\"intent\": \"...\", \"confidence\": 0.0, \"entities\": {{}}, \"command\": null}}.\n\
+         - intent: one of {}.\n\
+         - confidence: how sure you are, from 0 to 1.\n\
+         - entities: names, paths, places or dates mentioned in the request.\n\
+         - command: for command_execution, the single shell command to run; otherwise null.\n\n\
+         Request: {}",
+        intents.join(", "),
+        transcript
+    )
+}
+
+/// Validates a classifier reply; models sometimes wrap the JSON in prose or code fences.
+pub fn parse_intent_reply(reply: &str, intents: &[String]) -> Result<serde_json::Value> {
+    let start = reply.find('{').ok_or_else(|| anyhow!("Model reply contained no JSON"))?;
+    let end = reply.rfind('}').filter(|&end| end > start)
+        .ok_or_else(|| anyhow!("Model reply contained no JSON"))?;
+    let value: serde_json::Value = serde_json::from_str(&reply[start..=end])?;
+    
+    let returned = value["intent"].as_str().unwrap_or_default().trim();
+    let intent = intents.iter()
+        .find(|i| i.eq_ignore_ascii_case(returned))
+        .ok_or_else(|| anyhow!("Model returned unknown intent '{}'", returned))?;
+    let confidence = value["confidence"].as_f64().unwrap_or(0.0).clamp(0.0, 1.0);
+    let command = value["command"].as_str().map(str::trim).filter(|c| !c.is_empty());
+    
+    Ok(serde_json::json!({
+        "intent": intent,
+        "confidence": confidence,
+        "entities": if value["entities"].is_object() { value["entities"].clone() } else { serde_json::json!({}) },
+        "command": command
+    }))
+}
+
+struct ChatReply {
+    content: String,
+    model: String,
+    provider: &'static str,
+    usage: serde_json::Value,
+}
+
+/// Chat completion through OpenAI, or Ollama when the app is offline. Nodes pick the
+/// model with `model` (OpenAI) and `localModel` (Ollama).
+async fn complete_chat(
+    environment: &AgentEnvironment,
+    config: &serde_json::Value,
+    messages: &[serde_json::Value],
+    temperature: f64,
+    json_output: bool,
+) -> Result<ChatReply> {
+    if environment.offline_mode {
+        let model = config["localModel"].as_str().unwrap_or(&environment.ollama_model).to_string();
+        let prompt = messages.iter()
+            .map(|m| format!("{}: {}", m["role"].as_str().unwrap_or("user"), m["content"].as_str().unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let content = crate::ollama::OllamaClient::new(None).generate(&model, &prompt, temperature as f32).await?;
+        return Ok(ChatReply { content, model, provider: "ollama", usage: serde_json::Value::Null });
+    }
+    
+    let api_key = environment.openai_api_key.clone()
+        .ok_or_else(|| anyhow!("OpenAI API key not configured"))?;
+    let model = config["model"].as_str().unwrap_or(&environment.openai_model).to_string();
+    let mut body = serde_json::json!({
+        "model": model,
+        "messages": messages,
+        "temperature": temperature,
+    });
+    if json_output {
+        body["response_format"] = serde_json::json!({ "type": "json_object" });
+    }
+    
+    let url = format!("{}{}", crate::config::CONFIG.api.openai_base_url, crate::config::CONFIG.api.openai_chat_endpoint);
+    let mut request = reqwest::Client::new()
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", api_key));
+    if let Some(org_id) = &environment.openai_organization_id {
+        request = request.header("OpenAI-Organization", org_id);
+    }
+    
+    let response = request.json(&body).send().await?;
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow!("OpenAI chat error: {}", error_text));
+    }
+    
+    let data: serde_json::Value = response.json().await?;
+    let content = data["choices"][0]["message"]["content"].as_str()
+        .ok_or_else(|| anyhow!("Invalid response format from chat API"))?
+        .to_string();
+    Ok(ChatReply { content, model, provider: "openai", usage: data["usage"].clone() })
+}
+
+/// Node statuses of a run in progress; only the final state is written to the store
+struct LiveRun {
+    workflow_id: String,
+    started_at: DateTime<Utc>,
+    statuses: HashMap<String, AgentStatus>,
+}
+
 pub struct AgentSystem {
-    executors: Arc<RwLock<HashMap<String, Box<dyn AgentExecutor>>>>,
+    executors: Arc<RwLock<HashMap<String, Arc<dyn AgentExecutor>>>>,
     workflows: Arc<RwLock<HashMap<String, AgentWorkflow>>>,
+    live_runs: Arc<RwLock<HashMap<String, LiveRun>>>, // Keyed by run ID
+    environment: SharedEnvironment,
+    store: AgentStore,
+    node_permits: Arc<Semaphore>, // Global concurrency limit shared by all runs
 }
 
 impl AgentSystem {
-    pub fn new() -> Self {
-        let mut executors: HashMap<String, Box<dyn AgentExecutor>> = HashMap::new();
+    pub async fn new(store: AgentStore, security_manager: Arc<SecurityManager>) -> Result<Self> {
+        let mut executors: HashMap<String, Arc<dyn AgentExecutor>> = HashMap::new();
+        let environment: SharedEnvironment = Arc::new(RwLock::new(AgentEnvironment::default()));
         
         // Register built-in agents
-        executors.insert("voice_input".to_string(), Box::new(VoiceInputAgent));
-        executors.insert("intent_classifier".to_string(), Box::new(IntentClassifierAgent));
-        executors.insert("command_executor".to_string(), Box::new(CommandExecutorAgent));
-        executors.insert("response_generator".to_string(), Box::new(ResponseGeneratorAgent));
-        executors.insert("voice_output".to_string(), Box::new(VoiceOutputAgent));
+        executors.insert("voice_input".to_string(), Arc::new(VoiceInputAgent { environment: environment.clone() }));
+        executors.insert("intent_classifier".to_string(), Arc::new(IntentClassifierAgent { environment: environment.clone() }));
+        executors.insert("command_executor".to_string(), Arc::new(CommandExecutorAgent {
+            terminal: TerminalTool::new(security_manager),
+        }));
+        executors.insert("response_generator".to_string(), Arc::new(ResponseGeneratorAgent { environment: environment.clone() }));
+        executors.insert("voice_output".to_string(), Arc::new(VoiceOutputAgent { environment: environment.clone() }));
+        executors.insert("file_reader".to_string(), Arc::new(FileReaderAgent { environment: environment.clone() }));
+        executors.insert("data_transform".to_string(), Arc::new(DataTransformAgent));
+        executors.insert("conditional".to_string(), Arc::new(ConditionalAgent));
         
-        Self {
+        // Workflows are cached in memory and written through to the store. Nodes left
+        // running by a crash are shown idle again.
+        let workflows = store.load_workflows().await?
+            .into_iter()
+            .map(|mut w| {
+                for node in w.nodes.iter_mut().filter(|n| n.status == AgentStatus::Running) {
+                    node.status = AgentStatus::Idle;
+                }
+                (w.id.clone(), w)
+            })
+            .collect();
+        
+        Ok(Self {
             executors: Arc::new(RwLock::new(executors)),
-            workflows: Arc::new(RwLock::new(HashMap::new())),
-        }
+            workflows: Arc::new(RwLock::new(workflows)),
+            live_runs: Arc::new(RwLock::new(HashMap::new())),
+            environment,
+            store,
+            node_permits: Arc::new(Semaphore::new(MAX_CONCURRENT_NODES)),
+        })
+    }
+    
+    /// Applies updated app settings to the built-in agents
+    pub async fn configure(&self, environment: AgentEnvironment) {
+        *self.environment.write().await = environment;
     }
     
     pub async fn create_workflow(&self, name: String, description: String) -> Result<String> {
@@ -227,24 +975,28 @@ impl AgentSystem {
             description,
             nodes: Vec::new(),
             connections: Vec::new(),
+            settings: WorkflowSettings::default(),
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
         
         let workflow_id = workflow.id.clone();
         let mut workflows = self.workflows.write().await;
+        self.store.save_workflow(&workflow).await?;
         workflows.insert(workflow_id.clone(), workflow);
         
         Ok(workflow_id)
     }
     
-    pub async fn add_node(&self, workflow_id: &str, node: AgentNode) -> Result<()> {
+    pub async fn add_node(&self, workflow_id: &str, mut node: AgentNode) -> Result<()> {
+        self.check_agent_key(&mut node).await?;
         let mut workflows = self.workflows.write().await;
         let workflow = workflows.get_mut(workflow_id)
             .ok_or_else(|| anyhow!("Workflow not found"))?;
         
         workflow.nodes.push(node);
         workflow.updated_at = Utc::now();
+        self.store.save_workflow(workflow).await?;
         
         Ok(())
     }
@@ -256,15 +1008,85 @@ impl AgentSystem {
         
         workflow.connections.push(connection);
         workflow.updated_at = Utc::now();
+        self.store.save_workflow(workflow).await?;
         
         Ok(())
     }
     
-    pub async fn execute_workflow(&self, workflow_id: &str, initial_input: serde_json::Value) -> Result<serde_json::Value> {
-        let workflows = self.workflows.read().await;
-        let workflow = workflows.get(workflow_id)
-            .ok_or_else(|| anyhow!("Workflow not found"))?;
+    /// Registered executor keys with each agent's display name and type
+    pub async fn registered_agents(&self) -> HashMap<String, (String, AgentType)> {
+        self.executors.read().await.iter()
+            .map(|(key, executor)| (key.clone(), (executor.get_name().to_string(), executor.get_type())))
+            .collect()
+    }
+    
+    pub async fn register_executor(&self, key: &str, executor: Box<dyn AgentExecutor>) {
+        self.executors.write().await.insert(key.to_string(), Arc::from(executor));
+    }
+    
+    pub async fn execute_workflow<F>(&self, workflow_id: &str, initial_input: serde_json::Value, on_event: F) -> Result<serde_json::Value>
+    where
+        F: Fn(&NodeEvent) + Send + Sync,
+    {
+        // Snapshot the workflow so edits on the canvas don't block on a running workflow
+        let workflow = self.get_workflow(workflow_id).await?;
+        
+        let mut run = WorkflowRun {
+            id: Uuid::new_v4().to_string(),
+            workflow_id: workflow_id.to_string(),
+            status: RunStatus::Running,
+            input: initial_input,
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None,
+            nodes: Vec::new(),
+        };
+        self.store.start_run(&run).await?;
+        self.live_runs.write().await.insert(run.id.clone(), LiveRun {
+            workflow_id: workflow_id.to_string(),
+            started_at: run.started_at,
+            statuses: workflow.nodes.iter()
+                .filter(|n| n.status != AgentStatus::Disabled)
+                .map(|n| (n.id.clone(), AgentStatus::Idle))
+                .collect(),
+        });
         
+        let outcome = self.run_nodes(&workflow, &run.id, &run.input, &mut run.nodes, &on_event).await;
+        self.save_final_statuses(&run.id).await;
+        run.finished_at = Some(Utc::now());
+        match &outcome {
+            Ok(()) => run.status = RunStatus::Success,
+            Err(e) => {
+                run.status = RunStatus::Error;
+                run.error = Some(e.to_string());
+            }
+        }
+        self.store.finish_run(&run).await?;
+        outcome?;
+        
+        Ok(serde_json::json!({
+            "runId": run.id,
+            "workflowId": workflow_id,
+            "order": run.nodes.iter().map(|r| r.node_id.clone()).collect::<Vec<_>>(),
+            "results": run.nodes,
+            "timestamp": Utc::now()
+        }))
+    }
+    
+    /// Runs the workflow's nodes as soon as their inputs are settled, executing independent
+    /// branches concurrently. Results are appended to `results` in dependency order, so a failed
+    /// run still records everything that happened before the failure.
+    async fn run_nodes<F>(
+        &self,
+        workflow: &AgentWorkflow,
+        run_id: &str,
+        initial_input: &serde_json::Value,
+        results: &mut Vec<NodeRunResult>,
+        on_event: &F,
+    ) -> Result<()>
+    where
+        F: Fn(&NodeEvent) + Send + Sync,
+    {
         // Find input nodes
         let input_nodes: Vec<_> = workflow.nodes.iter()
             .filter(|n| matches!(n.agent_type, AgentType::Input))
@@ -274,49 +1096,305 @@ impl AgentSystem {
             return Err(anyhow!("No input nodes in workflow"));
         }
         
-        // Simple execution - in reality, this would be more sophisticated
-        let mut results = HashMap::new();
-        results.insert("initial_input".to_string(), initial_input);
-        
-        // Execute nodes in order (simplified - real implementation would handle dependencies)
-        for node in &workflow.nodes {
-            let executors = self.executors.read().await;
-            if let Some(executor) = executors.get(&node.name.to_lowercase().replace(" ", "_")) {
-                let input = results.get("initial_input").cloned()
-                    .unwrap_or_else(|| serde_json::Value::Null);
-                
-                match executor.execute(input, &node.config).await {
-                    Ok(output) => {
-                        results.insert(node.id.clone(), output);
+        let order = execution_order(workflow)?;
+        let conditions = parse_conditions(workflow)?;
+        let policies = workflow.nodes.iter().map(NodePolicy::from_node).collect::<Result<Vec<_>>>()?;
+        
+        // Snapshot the registry so registering executors doesn't wait on long runs
+        let executors = self.executors.read().await.clone();
+        
+        // Stored workflows may name executors that are no longer registered (e.g. a removed plugin)
+        if let Some(node) = workflow.nodes.iter().find(|n| !executors.contains_key(&n.agent_key)) {
+            return Err(anyhow!("Node '{}' references unknown agent '{}'", node.name, node.agent_key));
+        }
+        
+        let index: HashMap<&str, usize> = workflow.nodes.iter()
+            .enumerate()
+            .map(|(i, n)| (n.id.as_str(), i))
+            .collect();
+        let incoming: Vec<Vec<&AgentConnection>> = workflow.nodes.iter()
+            .map(|n| workflow.connections.iter().filter(|c| c.to == n.id).collect())
+            .collect();
+        
+        let mut outputs: HashMap<String, serde_json::Value> = HashMap::new();
+        let mut errors: HashMap<String, serde_json::Value> = HashMap::new(); // Handled failures, sent along on_error edges
+        let mut branches: HashMap<String, bool> = HashMap::new(); // Conditional nodes' decisions
+        let mut finished: Vec<Option<NodeRunResult>> = vec![None; workflow.nodes.len()];
+        let mut scheduled = vec![false; workflow.nodes.len()];
+        let mut queue: VecDeque<(usize, serde_json::Value)> = VecDeque::new();
+        let mut running: HashMap<usize, (DateTime<Utc>, serde_json::Value)> = HashMap::new();
+        let mut tasks = JoinSet::new();
+        let mut failure: Option<anyhow::Error> = None;
+        let mut cancelled = false;
+        // Aborting a task only drops its future; the token also stops work running elsewhere
+        let cancel = CancellationToken::new();
+        let _cancel_on_exit = cancel.clone().drop_guard();
+        let run_permits = Arc::new(Semaphore::new(
+            workflow.settings.max_concurrency.unwrap_or(MAX_CONCURRENT_NODES).max(1),
+        ));
+        let report = |node_id: &str, status: AgentStatus, duration_ms: Option<u64>, output: Option<&serde_json::Value>, error: Option<String>| {
+            on_event(&NodeEvent {
+                run_id: run_id.to_string(),
+                workflow_id: workflow.id.clone(),
+                node_id: node_id.to_string(),
+                status,
+                timestamp: Utc::now(),
+                duration_ms,
+                output: output.map(|output| truncate_value(output, EVENT_OUTPUT_LIMIT)),
+                error,
+            });
+        };
+        
+        loop {
+            // Queue every node whose join is satisfied; walking in dependency order lets skips cascade
+            if failure.is_none() {
+                for &i in &order {
+                    if scheduled[i] {
+                        continue;
+                    }
+                    let node = &workflow.nodes[i];
+                    let parents = match active_parents(&incoming[i], &outputs, &errors, &branches, &conditions) {
+                        Ok(parents) => parents,
+                        Err(e) => {
+                            failure = Some(e);
+                            break;
+                        }
+                    };
+                    let settled = incoming[i].iter().all(|c| finished[index[c.from.as_str()]].is_some());
+                    let ready = match policies[i].join {
+                        JoinMode::All => settled,
+                        JoinMode::First => settled || !parents.is_empty(),
+                    };
+                    if !ready {
+                        continue;
+                    }
+                    scheduled[i] = true;
+                    
+                    if !incoming[i].is_empty() && parents.is_empty() {
+                        finished[i] = Some(NodeRunResult {
+                            node_id: node.id.clone(),
+                            name: node.name.clone(),
+                            status: AgentStatus::Skipped,
+                            input: serde_json::Value::Null,
+                            output: serde_json::Value::Null,
+                            error: None,
+                            started_at: None,
+                            duration_ms: 0,
+                            attempts: 0,
+                        });
+                        self.set_node_status(run_id, &node.id, AgentStatus::Skipped).await;
+                        report(&node.id, AgentStatus::Skipped, None, None, None);
+                        continue;
                     }
-                    Err(e) => {
-                        return Err(anyhow!("Node {} execution failed: {}", node.name, e));
+                    
+                    let input = match (parents.is_empty(), policies[i].join) {
+                        (true, _) => initial_input.clone(),
+                        (false, JoinMode::First) => merge_inputs(&parents[..1]),
+                        (false, JoinMode::All) => merge_inputs(&parents),
+                    };
+                    queue.push_back((i, input));
+                }
+            }
+            
+            if failure.is_some() {
+                queue.clear();
+                if workflow.settings.cancel_on_failure && !cancelled {
+                    cancel.cancel();
+                    tasks.abort_all();
+                    cancelled = true;
+                }
+            }
+            
+            // Start the next queued node once a permit frees up, handling completions meanwhile
+            let joined = if queue.is_empty() {
+                match tasks.join_next().await {
+                    Some(joined) => joined,
+                    None => break,
+                }
+            } else {
+                tokio::select! {
+                    biased;
+                    Some(joined) = tasks.join_next() => joined,
+                    permits = acquire_permits(&run_permits, &self.node_permits) => {
+                        let permits = permits?;
+                        let Some((i, input)) = queue.pop_front() else { continue };
+                        let node = &workflow.nodes[i];
+                        self.set_node_status(run_id, &node.id, AgentStatus::Running).await;
+                        report(&node.id, AgentStatus::Running, None, None, None);
+                        running.insert(i, (Utc::now(), input.clone()));
+                        
+                        let executor = executors[&node.agent_key].clone();
+                        let config = node.config.clone();
+                        let policy = policies[i].clone();
+                        let node_cancel = cancel.child_token();
+                        tasks.spawn(async move {
+                            let _permits = permits;
+                            let timer = Instant::now();
+                            let (attempts, outcome) = execute_with_policy(executor.as_ref(), input, &config, &policy, &node_cancel).await;
+                            (i, timer.elapsed().as_millis() as u64, attempts, outcome)
+                        });
+                        continue;
+                    }
+                }
+            };
+            
+            let (i, duration_ms, attempts, outcome) = match joined {
+                Ok(completed) => completed,
+                Err(e) => {
+                    // Aborted tasks are recorded below; anything else is a panicking executor
+                    if !e.is_cancelled() && failure.is_none() {
+                        failure = Some(anyhow!("Agent task failed: {}", e));
                     }
+                    continue;
+                }
+            };
+            let node = &workflow.nodes[i];
+            let (started_at, input) = running.remove(&i).unwrap_or((Utc::now(), serde_json::Value::Null));
+            let mut result = NodeRunResult {
+                node_id: node.id.clone(),
+                name: node.name.clone(),
+                status: AgentStatus::Success,
+                input,
+                output: serde_json::Value::Null,
+                error: None,
+                started_at: Some(started_at),
+                duration_ms,
+                attempts,
+            };
+            match outcome {
+                Ok(output) => {
+                    self.set_node_status(run_id, &node.id, AgentStatus::Success).await;
+                    report(&node.id, AgentStatus::Success, Some(duration_ms), Some(&output), None);
+                    // Conditional nodes pass the value they tested, not their decision, downstream
+                    let forwarded = match (&node.agent_type, output["matched"].as_bool()) {
+                        (AgentType::Condition, Some(matched)) if output.get("value").is_some() => {
+                            branches.insert(node.id.clone(), matched);
+                            output["value"].clone()
+                        }
+                        _ => output.clone(),
+                    };
+                    outputs.insert(node.id.clone(), forwarded);
+                    result.output = output;
+                }
+                Err(e) => {
+                    let message = match attempts {
+                        1 => e.to_string(),
+                        _ => format!("{} (after {} attempts)", e, attempts),
+                    };
+                    self.set_node_status(run_id, &node.id, AgentStatus::Error).await;
+                    report(&node.id, AgentStatus::Error, Some(duration_ms), None, Some(message.clone()));
+                    
+                    // Failures routed to a handler, or on nodes set to continue, don't fail the run
+                    let routed = workflow.connections.iter().any(|c| c.from == node.id && is_error_edge(c));
+                    if routed || policies[i].continue_on_error {
+                        errors.insert(node.id.clone(), serde_json::json!({
+                            "error": message,
+                            "nodeId": node.id,
+                            "input": result.input,
+                        }));
+                    } else if failure.is_none() {
+                        failure = Some(anyhow!("Node {} execution failed: {}", node.name, message));
+                    }
+                    result.status = AgentStatus::Error;
+                    result.error = Some(message);
                 }
             }
+            finished[i] = Some(result);
         }
         
-        Ok(serde_json::json!({
-            "workflowId": workflow_id,
-            "results": results,
-            "timestamp": Utc::now()
-        }))
+        // Nodes still marked running were aborted when a sibling failed, or their task panicked
+        for (i, (started_at, input)) in running {
+            let node = &workflow.nodes[i];
+            let status = if cancelled { AgentStatus::Cancelled } else { AgentStatus::Error };
+            finished[i] = Some(NodeRunResult {
+                node_id: node.id.clone(),
+                name: node.name.clone(),
+                status: status.clone(),
+                input,
+                output: serde_json::Value::Null,
+                error: (!cancelled).then(|| "Agent task panicked".to_string()),
+                started_at: Some(started_at),
+                duration_ms: (Utc::now() - started_at).num_milliseconds().max(0) as u64,
+                attempts: 1,
+            });
+            self.set_node_status(run_id, &node.id, status.clone()).await;
+            report(&node.id, status, None, None, None);
+        }
+        results.extend(order.iter().filter_map(|&i| finished[i].take()));
+        
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+    
+    /// Rejects nodes whose `agent_key` is not a registered executor. Clients that predate
+    /// agent keys send none, so it is derived from the node name the way it used to be.
+    async fn check_agent_key(&self, node: &mut AgentNode) -> Result<()> {
+        if node.agent_key.is_empty() {
+            node.agent_key = legacy_agent_key(&node.name);
+        }
+        if !self.executors.read().await.contains_key(&node.agent_key) {
+            return Err(anyhow!("Node '{}' references unknown agent '{}'", node.name, node.agent_key));
+        }
+        Ok(())
+    }
+    
+    /// Records a node's progress in its run. Disabled nodes are not tracked.
+    async fn set_node_status(&self, run_id: &str, node_id: &str, status: AgentStatus) {
+        let mut live_runs = self.live_runs.write().await;
+        if let Some(status_slot) = live_runs.get_mut(run_id).and_then(|run| run.statuses.get_mut(node_id)) {
+            *status_slot = status;
+        }
+    }
+    
+    /// Ends a run's live tracking and stores its final node statuses on the workflow,
+    /// so the canvas shows the outcome after a restart.
+    async fn save_final_statuses(&self, run_id: &str) {
+        let Some(run) = self.live_runs.write().await.remove(run_id) else { return };
+        let mut workflows = self.workflows.write().await;
+        let Some(workflow) = workflows.get_mut(&run.workflow_id) else { return };
+        apply_statuses(workflow, &run.statuses);
+        if let Err(e) = self.store.save_workflow(workflow).await {
+            eprintln!("Failed to save node status for workflow {}: {}", run.workflow_id, e);
+        }
+    }
+    
+    /// Shows the statuses of the workflow's most recently started run, if one is in progress.
+    async fn with_live_statuses(&self, mut workflow: AgentWorkflow) -> AgentWorkflow {
+        let live_runs = self.live_runs.read().await;
+        let latest = live_runs.values()
+            .filter(|run| run.workflow_id == workflow.id)
+            .max_by_key(|run| run.started_at);
+        if let Some(run) = latest {
+            apply_statuses(&mut workflow, &run.statuses);
+        }
+        workflow
     }
     
     pub async fn list_workflows(&self) -> Vec<AgentWorkflow> {
-        let workflows = self.workflows.read().await;
-        workflows.values().cloned().collect()
+        let workflows: Vec<_> = self.workflows.read().await.values().cloned().collect();
+        let mut listed = Vec::with_capacity(workflows.len());
+        for workflow in workflows {
+            listed.push(self.with_live_statuses(workflow).await);
+        }
+        listed
     }
     
     pub async fn get_workflow(&self, workflow_id: &str) -> Result<AgentWorkflow> {
-        let workflows = self.workflows.read().await;
-        workflows.get(workflow_id)
+        let workflow = self.workflows.read().await
+            .get(workflow_id)
             .cloned()
-            .ok_or_else(|| anyhow!("Workflow not found"))
+            .ok_or_else(|| anyhow!("Workflow not found"))?;
+        Ok(self.with_live_statuses(workflow).await)
     }
     
-    pub async fn update_workflow(&self, workflow: AgentWorkflow) -> Result<()> {
+    pub async fn update_workflow(&self, mut workflow: AgentWorkflow) -> Result<()> {
+        for node in &mut workflow.nodes {
+            self.check_agent_key(node).await?;
+        }
         let mut workflows = self.workflows.write().await;
+        self.store.save_workflow(&workflow).await?;
         workflows.insert(workflow.id.clone(), workflow);
         Ok(())
     }
@@ -325,9 +1403,19 @@ impl AgentSystem {
         let mut workflows = self.workflows.write().await;
         workflows.remove(workflow_id)
             .ok_or_else(|| anyhow!("Workflow not found"))?;
+        self.store.delete_workflow(workflow_id).await?;
         Ok(())
     }
     
+    pub async fn list_runs(&self, workflow_id: Option<&str>, limit: usize) -> Result<Vec<WorkflowRunSummary>> {
+        self.store.list_runs(workflow_id, limit).await
+    }
+    
+    pub async fn get_run(&self, run_id: &str) -> Result<WorkflowRun> {
+        self.store.get_run(run_id).await?
+            .ok_or_else(|| anyhow!("Run not found"))
+    }
+    
     pub fn get_available_agents(&self) -> Vec<(String, String, AgentType)> {
         vec![
             ("voice_input".to_string(), "Voice Input".to_string(), AgentType::Input),
@@ -342,15 +1430,274 @@ impl AgentSystem {
     }
 }
 
+/// Copies run statuses onto the matching nodes; disabled nodes keep their status.
+fn apply_statuses(workflow: &mut AgentWorkflow, statuses: &HashMap<String, AgentStatus>) {
+    for node in workflow.nodes.iter_mut().filter(|n| n.status != AgentStatus::Disabled) {
+        if let Some(status) = statuses.get(&node.id) {
+            node.status = status.clone();
+        }
+    }
+}
+
+/// Topologically sorts the workflow by its connections (Kahn's algorithm).
+/// Ties are broken by position in `nodes`, so runs are deterministic.
+pub fn execution_order(workflow: &AgentWorkflow) -> Result<Vec<usize>> {
+    let index: HashMap<&str, usize> = workflow.nodes.iter()
+        .enumerate()
+        .map(|(i, n)| (n.id.as_str(), i))
+        .collect();
+    
+    let mut in_degree = vec![0usize; workflow.nodes.len()];
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); workflow.nodes.len()];
+    for connection in &workflow.connections {
+        let from = *index.get(connection.from.as_str())
+            .ok_or_else(|| anyhow!("Connection {} starts at unknown node {}", connection.id, connection.from))?;
+        let to = *index.get(connection.to.as_str())
+            .ok_or_else(|| anyhow!("Connection {} ends at unknown node {}", connection.id, connection.to))?;
+        if !children[from].contains(&to) {
+            children[from].push(to);
+            in_degree[to] += 1;
+        }
+    }
+    
+    let mut ready: BTreeSet<usize> = (0..workflow.nodes.len())
+        .filter(|&i| in_degree[i] == 0)
+        .collect();
+    let mut order = Vec::with_capacity(workflow.nodes.len());
+    while let Some(current) = ready.pop_first() {
+        order.push(current);
+        for &child in &children[current] {
+            in_degree[child] -= 1;
+            if in_degree[child] == 0 {
+                ready.insert(child);
+            }
+        }
+    }
+    
+    if order.len() < workflow.nodes.len() {
+        let stuck: Vec<&str> = (0..workflow.nodes.len())
+            .filter(|&i| in_degree[i] > 0)
+            .map(|i| workflow.nodes[i].name.as_str())
+            .collect();
+        return Err(anyhow!("Workflow contains a cycle involving: {}", stuck.join(", ")));
+    }
+    
+    Ok(order)
+}
+
+/// Checks everything a run would reject up front: unknown node references, cycles, invalid
+/// conditions and invalid node policies.
+pub fn validate_workflow(workflow: &AgentWorkflow) -> Result<()> {
+    execution_order(workflow)?;
+    parse_conditions(workflow)?;
+    for node in &workflow.nodes {
+        NodePolicy::from_node(node)?;
+    }
+    Ok(())
+}
+
+/// Parses every connection condition up front so a typo fails the run before any node executes.
+fn parse_conditions(workflow: &AgentWorkflow) -> Result<HashMap<String, Condition>> {
+    let mut conditions = HashMap::new();
+    for connection in &workflow.connections {
+        let Some(source) = connection.condition.as_deref().map(str::trim).filter(|c| !c.is_empty()) else {
+            continue;
+        };
+        let condition = Condition::parse(source)
+            .map_err(|e| anyhow!("Invalid condition on connection {}: {}", connection_label(connection), e))?;
+        conditions.insert(connection.id.clone(), condition);
+    }
+    Ok(conditions)
+}
+
+/// When a node with several incoming connections starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JoinMode {
+    All,   // Once every parent has finished or been skipped (default)
+    First, // As soon as any parent succeeds, with that parent's output
+}
+
+/// Execution policy read from generic node config keys, applied the same way to every executor
+#[derive(Debug, Clone)]
+struct NodePolicy {
+    join: JoinMode,            // `join`: "all" or "first"
+    timeout: Option<Duration>, // `timeout` per attempt, in milliseconds
+    max_retries: u32,          // `maxRetries`
+    retry_backoff: Duration,   // `retryBackoff` in milliseconds, doubled after each failed attempt
+    continue_on_error: bool,   // `continueOnError`: record the failure and keep running the workflow
+}
+
+impl NodePolicy {
+    fn from_node(node: &AgentNode) -> Result<Self> {
+        let config = &node.config;
+        let join = match config.get("join").and_then(|j| j.as_str()) {
+            None | Some("all") => JoinMode::All,
+            Some("first") => JoinMode::First,
+            Some(other) => return Err(anyhow!("Node '{}' has unknown join mode '{}' (expected 'all' or 'first')", node.name, other)),
+        };
+        let max_retries = config_u64(node, "maxRetries")?.unwrap_or(0);
+        if max_retries > MAX_NODE_RETRIES {
+            return Err(anyhow!("Node '{}' sets maxRetries to {} (at most {} allowed)", node.name, max_retries, MAX_NODE_RETRIES));
+        }
+        Ok(Self {
+            join,
+            timeout: config_u64(node, "timeout")?.filter(|&ms| ms > 0).map(Duration::from_millis),
+            max_retries: max_retries as u32,
+            retry_backoff: Duration::from_millis(config_u64(node, "retryBackoff")?.unwrap_or(DEFAULT_RETRY_BACKOFF_MS)),
+            continue_on_error: config.get("continueOnError").and_then(|c| c.as_bool()).unwrap_or(false),
+        })
+    }
+}
+
+fn config_u64(node: &AgentNode, key: &str) -> Result<Option<u64>> {
+    match node.config.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(value) => value.as_u64()
+            .map(Some)
+            .ok_or_else(|| anyhow!("Node '{}' has invalid {} {} (expected a non-negative integer)", node.name, key, value)),
+    }
+}
+
+/// Runs one node under its policy: every attempt is bounded by the timeout and failed attempts
+/// are retried with exponential backoff. Returns the number of attempts made.
+async fn execute_with_policy(
+    executor: &dyn AgentExecutor,
+    input: serde_json::Value,
+    config: &serde_json::Value,
+    policy: &NodePolicy,
+    cancel: &CancellationToken,
+) -> (u32, Result<serde_json::Value>) {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let attempt = cancel.child_token();
+        let call = executor.execute_cancellable(input.clone(), config, attempt.clone());
+        let outcome = match policy.timeout {
+            Some(limit) => match tokio::time::timeout(limit, call).await {
+                Ok(outcome) => outcome,
+                Err(_) => {
+                    // Dropping the call killed any child process; the token stops blocking work,
+                    // so a retry never runs beside the attempt it replaces
+                    attempt.cancel();
+                    Err(anyhow!("Timed out after {}ms", limit.as_millis()))
+                }
+            },
+            None => call.await,
+        };
+        if outcome.is_ok() || attempts > policy.max_retries {
+            return (attempts, outcome);
+        }
+        let backoff = policy.retry_backoff.saturating_mul(1 << (attempts - 1).min(16));
+        tokio::time::sleep(backoff.min(MAX_RETRY_BACKOFF)).await;
+    }
+}
+
+/// Sources of the node's incoming connections that are active, with the value each passes on,
+/// in connection order. Regular edges carry a successful output and must sit on the taken branch
+/// and pass their condition; `on_error` edges carry the source's failure instead.
+fn active_parents(
+    incoming: &[&AgentConnection],
+    outputs: &HashMap<String, serde_json::Value>,
+    errors: &HashMap<String, serde_json::Value>,
+    branches: &HashMap<String, bool>,
+    conditions: &HashMap<String, Condition>,
+) -> Result<Vec<(String, serde_json::Value)>> {
+    let mut parents: Vec<(String, serde_json::Value)> = Vec::new();
+    for connection in incoming {
+        let source = if is_error_edge(connection) { errors } else { outputs };
+        let Some(value) = source.get(&connection.from) else { continue };
+        if let (Some(wanted), Some(&matched)) = (branch_label(connection), branches.get(&connection.from)) {
+            if wanted != matched {
+                continue;
+            }
+        }
+        let passes = match conditions.get(&connection.id) {
+            Some(condition) => condition.evaluate(value)
+                .map_err(|e| anyhow!("Condition on connection {} failed: {}", connection_label(connection), e))?,
+            None => true,
+        };
+        if passes && !parents.iter().any(|(id, _)| id == &connection.from) {
+            parents.push((connection.from.clone(), value.clone()));
+        }
+    }
+    Ok(parents)
+}
+
+/// Takes a run permit before a global one so a run at its own limit doesn't hold global permits
+async fn acquire_permits(
+    run_permits: &Arc<Semaphore>,
+    node_permits: &Arc<Semaphore>,
+) -> Result<(OwnedSemaphorePermit, OwnedSemaphorePermit)> {
+    let run_permit = run_permits.clone().acquire_owned().await?;
+    let node_permit = node_permits.clone().acquire_owned().await?;
+    Ok((run_permit, node_permit))
+}
+
+/// Executor key for nodes saved before `agent_key` existed, which were matched by display name
+pub fn legacy_agent_key(name: &str) -> String {
+    name.to_lowercase().replace(' ', "_")
+}
+
+/// Edges labelled `on_error` only carry their source's failure, routing it to a handler node
+fn is_error_edge(connection: &AgentConnection) -> bool {
+    connection.label.as_deref().is_some_and(|l| l.trim().eq_ignore_ascii_case("on_error"))
+}
+
+/// `true`/`false` labels on edges leaving a Conditional node select its branches
+fn branch_label(connection: &AgentConnection) -> Option<bool> {
+    match connection.label.as_deref()?.trim().to_lowercase().as_str() {
+        "true" | "yes" | "then" => Some(true),
+        "false" | "no" | "else" => Some(false),
+        _ => None,
+    }
+}
+
+fn connection_label(connection: &AgentConnection) -> String {
+    match &connection.label {
+        Some(label) => format!("'{}' ({} -> {})", label, connection.from, connection.to),
+        None => format!("{} -> {}", connection.from, connection.to),
+    }
+}
+
+/// Values whose JSON exceeds `limit` bytes are replaced by a truncated string of that JSON.
+pub(crate) fn truncate_value(value: &serde_json::Value, limit: usize) -> serde_json::Value {
+    let text = value.to_string();
+    if text.len() <= limit {
+        return value.clone();
+    }
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    serde_json::Value::String(format!("{}…", &text[..end]))
+}
+
+/// A single parent's output is passed through as-is; several parents are
+/// merged into an object keyed by parent node ID.
+fn merge_inputs(parents: &[(String, serde_json::Value)]) -> serde_json::Value {
+    if let [(_, value)] = parents {
+        return value.clone();
+    }
+    
+    let merged: serde_json::Map<String, serde_json::Value> = parents.iter().cloned().collect();
+    serde_json::Value::Object(merged)
+}
+
 // Global agent system instance
 use once_cell::sync::Lazy;
 
-static AGENT_SYSTEM: Lazy<Arc<tokio::sync::Mutex<Option<AgentSystem>>>> = 
+static AGENT_SYSTEM: Lazy<Arc<tokio::sync::Mutex<Option<Arc<AgentSystem>>>>> = 
     Lazy::new(|| Arc::new(tokio::sync::Mutex::new(None)));
 
-pub async fn initialize_agent_system() -> Result<()> {
-    let system = AgentSystem::new();
-    *AGENT_SYSTEM.lock().await = Some(system);
+pub async fn initialize_agent_system(
+    app_data_dir: PathBuf,
+    security_manager: Arc<SecurityManager>,
+    environment: AgentEnvironment,
+) -> Result<()> {
+    let store = AgentStore::open(&app_data_dir)?;
+    let system = AgentSystem::new(store, security_manager).await?;
+    system.configure(environment).await;
+    *AGENT_SYSTEM.lock().await = Some(Arc::new(system));
     Ok(())
 }
 
@@ -358,9 +1705,537 @@ pub async fn with_agent_system<F, R>(f: F) -> Result<R>
 where
     F: FnOnce(&AgentSystem) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<R>> + Send + '_>>,
 {
-    let guard = AGENT_SYSTEM.lock().await;
-    match guard.as_ref() {
-        Some(system) => f(system).await,
+    // Release the lock before running so long workflows don't block other agent commands
+    let system = AGENT_SYSTEM.lock().await.clone();
+    match system {
+        Some(system) => f(&system).await,
         None => Err(anyhow!("Agent system not initialized")),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EchoAgent;
+
+    #[async_trait]
+    impl AgentExecutor for EchoAgent {
+        async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
+            Ok(serde_json::json!({ "tag": config["tag"], "input": input }))
+        }
+
+        fn get_type(&self) -> AgentType { AgentType::Processor }
+        fn get_name(&self) -> &str { "Echo" }
+    }
+
+    /// Sleeps for `config.ms`, tracking how many calls overlap. Fails when `config.fail` is set
+    /// or for the first `config.failFirst` calls.
+    #[derive(Default)]
+    struct SleepAgent {
+        active: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+        calls: std::sync::atomic::AtomicU64,
+    }
+
+    #[async_trait]
+    impl AgentExecutor for Arc<SleepAgent> {
+        async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
+            use std::sync::atomic::Ordering;
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(config["ms"].as_u64().unwrap_or(0))).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if config["fail"].as_bool().unwrap_or(false) || call < config["failFirst"].as_u64().unwrap_or(0) {
+                return Err(anyhow!("sleeper failed"));
+            }
+            Ok(serde_json::json!({ "tag": config["tag"], "input": input }))
+        }
+
+        fn get_type(&self) -> AgentType { AgentType::Processor }
+        fn get_name(&self) -> &str { "Sleep" }
+    }
+
+    /// Works on a blocking thread that only stops once its token is cancelled
+    struct BlockingAgent {
+        running: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AgentExecutor for BlockingAgent {
+        async fn execute(&self, input: serde_json::Value, config: &serde_json::Value) -> Result<serde_json::Value> {
+            self.execute_cancellable(input, config, CancellationToken::new()).await
+        }
+
+        async fn execute_cancellable(&self, _input: serde_json::Value, _config: &serde_json::Value, cancel: CancellationToken) -> Result<serde_json::Value> {
+            use std::sync::atomic::Ordering;
+            let running = self.running.clone();
+            tokio::task::spawn_blocking(move || {
+                running.fetch_add(1, Ordering::SeqCst);
+                while !cancel.is_cancelled() {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+                running.fetch_sub(1, Ordering::SeqCst);
+            }).await?;
+            Err(anyhow!("stopped"))
+        }
+
+        fn get_type(&self) -> AgentType { AgentType::Processor }
+        fn get_name(&self) -> &str { "Blocking" }
+    }
+
+    fn sleeper(id: &str, ms: u64) -> AgentNode {
+        let mut node = node(id, AgentType::Processor);
+        node.agent_key = "sleep".to_string();
+        node.config = serde_json::json!({ "tag": id, "ms": ms });
+        node
+    }
+
+    async fn parallel_system(nodes: Vec<AgentNode>, connections: Vec<AgentConnection>, settings: WorkflowSettings) -> (AgentSystem, String, Arc<SleepAgent>) {
+        let (system, id) = system_with(Vec::new(), Vec::new()).await;
+        let sleep = Arc::new(SleepAgent::default());
+        system.register_executor("sleep", Box::new(sleep.clone())).await;
+        let mut workflow = system.get_workflow(&id).await.unwrap();
+        workflow.nodes = nodes;
+        workflow.connections = connections;
+        workflow.settings = settings;
+        system.update_workflow(workflow).await.unwrap();
+        (system, id, sleep)
+    }
+
+    fn node(id: &str, agent_type: AgentType) -> AgentNode {
+        AgentNode {
+            id: id.to_string(),
+            name: format!("Echo {}", id),
+            agent_key: "echo".to_string(),
+            agent_type,
+            x: 0.0,
+            y: 0.0,
+            config: serde_json::json!({ "tag": id }),
+            status: AgentStatus::Idle,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn connect(from: &str, to: &str) -> AgentConnection {
+        AgentConnection {
+            id: format!("{}-{}", from, to),
+            from: from.to_string(),
+            to: to.to_string(),
+            label: None,
+            condition: None,
+        }
+    }
+
+    fn connect_if(from: &str, to: &str, condition: &str) -> AgentConnection {
+        AgentConnection {
+            condition: Some(condition.to_string()),
+            ..connect(from, to)
+        }
+    }
+
+    async fn system_with(nodes: Vec<AgentNode>, connections: Vec<AgentConnection>) -> (AgentSystem, String) {
+        let system = AgentSystem::new(AgentStore::open_in_memory().unwrap(), Arc::new(SecurityManager::new())).await.unwrap();
+        system.register_executor("echo", Box::new(EchoAgent)).await;
+        let id = system.create_workflow("test".to_string(), String::new()).await.unwrap();
+        let mut workflow = system.get_workflow(&id).await.unwrap();
+        workflow.nodes = nodes;
+        workflow.connections = connections;
+        system.update_workflow(workflow).await.unwrap();
+        (system, id)
+    }
+
+    #[tokio::test]
+    async fn test_outputs_flow_along_connections() {
+        // Declared out of order: the diamond a -> (b, c) -> d must still run a first and d last
+        let nodes = vec![
+            node("d", AgentType::Output),
+            node("c", AgentType::Processor),
+            node("b", AgentType::Processor),
+            node("a", AgentType::Input),
+        ];
+        let connections = vec![connect("a", "b"), connect("a", "c"), connect("b", "d"), connect("c", "d")];
+        let (system, id) = system_with(nodes, connections).await;
+
+        let run = system.execute_workflow(&id, serde_json::json!("start"), |_| {}).await.unwrap();
+        assert_eq!(run["order"], serde_json::json!(["a", "c", "b", "d"]));
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results[0]["output"]["input"], "start");
+        assert_eq!(results[1]["input"]["tag"], "a");
+        let merged = &results[3]["input"];
+        assert_eq!(merged["b"]["tag"], "b");
+        assert_eq!(merged["c"]["tag"], "c");
+    }
+
+    #[tokio::test]
+    async fn test_conditions_select_branches() {
+        let nodes = vec![
+            node("a", AgentType::Input),
+            node("b", AgentType::Processor),
+            node("c", AgentType::Processor),
+            node("d", AgentType::Output),
+        ];
+        let connections = vec![
+            connect_if("a", "b", r#"output.tag == "a" && output.input.score > 0.8"#),
+            connect_if("a", "c", "output.input.score <= 0.8"),
+            connect("c", "d"),
+        ];
+        let (system, id) = system_with(nodes, connections).await;
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let run = system.execute_workflow(&id, serde_json::json!({ "score": 0.9 }), |event| {
+            events.lock().unwrap().push((event.event_name(), event.node_id.clone()));
+        }).await.unwrap();
+        let status: Vec<_> = run["results"].as_array().unwrap().iter()
+            .map(|r| r["status"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(status, ["success", "success", "skipped", "skipped"]);
+
+        let events = events.into_inner().unwrap();
+        // Inactive branches are skipped as soon as they are decided, before b starts
+        assert_eq!(events, [
+            ("workflow-node-started", "a".to_string()),
+            ("workflow-node-finished", "a".to_string()),
+            ("workflow-node-finished", "c".to_string()),
+            ("workflow-node-finished", "d".to_string()),
+            ("workflow-node-started", "b".to_string()),
+            ("workflow-node-finished", "b".to_string()),
+        ]);
+
+        // The stored workflow shows the outcome of the latest run
+        let stored: Vec<_> = system.get_workflow(&id).await.unwrap().nodes.into_iter().map(|n| n.status).collect();
+        assert_eq!(stored, [AgentStatus::Success, AgentStatus::Success, AgentStatus::Skipped, AgentStatus::Skipped]);
+
+        // Invalid conditions fail before any node runs
+        let mut workflow = system.get_workflow(&id).await.unwrap();
+        workflow.connections[1].condition = Some("output.input.score <".to_string());
+        system.update_workflow(workflow).await.unwrap();
+        let err = system.execute_workflow(&id, serde_json::Value::Null, |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("Invalid condition on connection a -> c"));
+    }
+
+    #[test]
+    fn test_parse_intent_reply() {
+        let intents: Vec<String> = DEFAULT_INTENTS.iter().map(|i| i.to_string()).collect();
+        let reply = "Sure! ```json\n{\"intent\": \"Command_Execution\", \"confidence\": 1.4, \"command\": \"git status\"}\n```";
+        let parsed = parse_intent_reply(reply, &intents).unwrap();
+        assert_eq!(parsed["intent"], "command_execution");
+        assert_eq!(parsed["confidence"], 1.0);
+        assert_eq!(parsed["command"], "git status");
+        assert_eq!(parsed["entities"], serde_json::json!({}));
+
+        let err = parse_intent_reply(r#"{"intent": "book_flight"}"#, &intents).unwrap_err();
+        assert!(err.to_string().contains("unknown intent 'book_flight'"));
+
+        let heard = voice_input_output("hey brain, what's on my calendar", "openai", Some("Hey Brain"));
+        assert_eq!(heard["transcript"], "what's on my calendar");
+        assert_eq!(heard["wakeWordDetected"], true);
+    }
+
+    #[tokio::test]
+    async fn test_command_executor_uses_terminal_allowlist() {
+        let agent = CommandExecutorAgent { terminal: TerminalTool::new(Arc::new(SecurityManager::new())) };
+        let opted_in = serde_json::json!({ "allowInputCommands": true });
+
+        let output = agent.execute(serde_json::json!({ "command": "echo 'hello  there'" }), &opted_in).await.unwrap();
+        assert_eq!(output["output"].as_str().unwrap().trim(), "hello  there");
+
+        // Upstream commands (typically model output) need the node to opt in
+        let err = agent.execute(serde_json::json!({ "command": "echo hello" }), &serde_json::Value::Null).await.unwrap_err();
+        assert!(err.to_string().contains("allowInputCommands"));
+        let output = agent.execute(serde_json::Value::Null, &serde_json::json!({ "command": "echo configured" })).await.unwrap();
+        assert_eq!(output["output"].as_str().unwrap().trim(), "configured");
+
+        for command in ["rm -rf /tmp/nothing", "echo hi; rm -rf ~", "echo $(whoami)", "find / -delete", "python -c 'print(1)'", "python3 -Bc 'x'", "git -c core.pager=sh log"] {
+            let err = agent.execute(serde_json::json!({ "command": command }), &opted_in).await.unwrap_err();
+            assert!(err.to_string().contains("Command not allowed"), "{}: {}", command, err);
+        }
+
+        // A transcript is never treated as a command
+        let err = agent.execute(serde_json::json!({ "transcript": "ls" }), &opted_in).await.unwrap_err();
+        assert!(err.to_string().contains("No command"));
+    }
+
+    #[tokio::test]
+    async fn test_file_reader_stays_in_allowed_roots() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("people.csv"), "name,age\nAda,36\nAlan,41\n").unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "nope").unwrap();
+
+        let environment = AgentEnvironment {
+            allowed_roots: vec![root.path().to_string_lossy().into_owned()],
+            ..Default::default()
+        };
+        let agent = FileReaderAgent { environment: Arc::new(RwLock::new(environment)) };
+
+        let path = root.path().join("people.csv");
+        let read = agent.execute(serde_json::json!({ "path": path }), &serde_json::json!({ "maxRows": 1 })).await.unwrap();
+        assert_eq!(read["columns"], serde_json::json!(["name", "age"]));
+        assert_eq!(read["rows"], serde_json::json!([{ "name": "Ada", "age": 36 }]));
+        assert_eq!(read["truncated"], true);
+
+        // Neither a direct path nor `..` traversal may leave the root
+        let escape = root.path().join("..").join(outside.path().file_name().unwrap()).join("secret.txt");
+        for path in [outside.path().join("secret.txt"), escape] {
+            let err = agent.execute(serde_json::json!({ "path": path }), &serde_json::Value::Null).await.unwrap_err();
+            assert!(err.to_string().contains("outside the allowed roots"));
+        }
+
+        // A cancelled run stops the blocking read instead of leaving it to finish
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let err = agent.execute_cancellable(serde_json::json!({ "path": path }), &serde_json::Value::Null, cancel).await.unwrap_err();
+        assert!(err.to_string().contains("Cancelled"));
+    }
+
+    #[tokio::test]
+    async fn test_data_transform_mapping_and_template() {
+        let input = serde_json::json!({
+            "intent": "file_operation",
+            "files": [{ "name": "a.md", "size": 10 }, { "name": "b.md", "size": 20 }]
+        });
+        let config = serde_json::json!({
+            "mapping": {
+                "kind": "$.intent",
+                "names": "$.files[*].name",
+                "last": { "size": ".files[-1].size" },
+                "source": "canvas"
+            },
+            "template": "{{ intent }}: {{ $.files[0].name }} and {{ files[1].size }} more bytes"
+        });
+
+        let output = DataTransformAgent.execute(input, &config).await.unwrap();
+        assert_eq!(output, serde_json::json!({
+            "kind": "file_operation",
+            "names": ["a.md", "b.md"],
+            "last": { "size": 20 },
+            "source": "canvas",
+            "text": "file_operation: a.md and 20 more bytes"
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_conditional_routes_labelled_edges() {
+        let mut gate = node("gate", AgentType::Condition);
+        gate.agent_key = "conditional".to_string();
+        gate.config = serde_json::json!({ "condition": "output.input > 0.5" });
+        let nodes = vec![node("a", AgentType::Input), gate, node("high", AgentType::Output), node("low", AgentType::Output)];
+        let mut to_high = connect("gate", "high");
+        to_high.label = Some("true".to_string());
+        let mut to_low = connect("gate", "low");
+        to_low.label = Some("else".to_string());
+        let (system, id) = system_with(nodes, vec![connect("a", "gate"), to_high, to_low]).await;
+
+        let run = system.execute_workflow(&id, serde_json::json!(0.9), |_| {}).await.unwrap();
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results[1]["output"]["branch"], "true");
+        // The taken branch receives the tested value rather than the decision
+        assert_eq!(results[2]["status"], "success");
+        assert_eq!(results[2]["input"]["tag"], "a");
+        assert_eq!(results[3]["status"], "skipped");
+    }
+
+    #[tokio::test]
+    async fn test_cycle_is_rejected() {
+        let nodes = vec![node("a", AgentType::Input), node("b", AgentType::Processor), node("c", AgentType::Processor)];
+        let connections = vec![connect("a", "b"), connect("b", "c"), connect("c", "b")];
+        let (system, id) = system_with(nodes, connections).await;
+
+        let err = system.execute_workflow(&id, serde_json::Value::Null, |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("cycle"));
+    }
+
+    #[tokio::test]
+    async fn test_nodes_resolve_executors_by_agent_key() {
+        // Renaming a node on the canvas must not change which executor runs it
+        let mut renamed = node("a", AgentType::Input);
+        renamed.name = "My Classifier".to_string();
+        let (system, id) = system_with(vec![renamed], Vec::new()).await;
+        let result = system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap();
+        assert_eq!(result["results"][0]["output"]["input"], 1);
+
+        let mut unknown = node("b", AgentType::Processor);
+        unknown.agent_key = "summarizer".to_string();
+        let err = system.add_node(&id, unknown).await.unwrap_err();
+        assert!(err.to_string().contains("unknown agent 'summarizer'"));
+
+        // A stored workflow whose executor has gone away fails before any node runs
+        system.workflows.write().await.get_mut(&id).unwrap().nodes[0].agent_key = "removed_plugin".to_string();
+        let err = system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("unknown agent 'removed_plugin'"));
+        let runs = system.list_runs(Some(&id), 10).await.unwrap();
+        assert_eq!(runs[0].status, RunStatus::Error);
+    }
+
+    #[tokio::test]
+    async fn test_independent_branches_run_concurrently() {
+        let nodes = vec![node("a", AgentType::Input), sleeper("b", 80), sleeper("c", 40), sleeper("d", 60), node("e", AgentType::Output)];
+        let connections = vec![connect("a", "b"), connect("a", "c"), connect("a", "d"), connect("b", "e"), connect("c", "e"), connect("d", "e")];
+        let (system, id, sleep) = parallel_system(nodes.clone(), connections.clone(), WorkflowSettings::default()).await;
+
+        let run = system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap();
+        assert_eq!(sleep.peak.load(std::sync::atomic::Ordering::SeqCst), 3);
+        // Results follow dependency order, not completion order, and the join waits for every branch
+        assert_eq!(run["order"], serde_json::json!(["a", "b", "c", "d", "e"]));
+        let joined = &run["results"][4]["input"];
+        assert_eq!(joined["b"]["tag"], "b");
+        assert_eq!(joined["c"]["tag"], "c");
+        assert_eq!(joined["d"]["tag"], "d");
+
+        let settings = WorkflowSettings { max_concurrency: Some(1), ..Default::default() };
+        let (system, id, sleep) = parallel_system(nodes, connections, settings).await;
+        system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap();
+        assert_eq!(sleep.peak.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_first_success_join_takes_the_fastest_parent() {
+        let mut join = node("d", AgentType::Output);
+        join.config = serde_json::json!({ "tag": "d", "join": "first" });
+        let nodes = vec![node("a", AgentType::Input), sleeper("slow", 150), sleeper("fast", 10), join];
+        let connections = vec![connect("a", "slow"), connect("a", "fast"), connect("slow", "d"), connect("fast", "d")];
+        let (system, id, _) = parallel_system(nodes, connections, WorkflowSettings::default()).await;
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let run = system.execute_workflow(&id, serde_json::json!(1), |event| {
+            events.lock().unwrap().push((event.event_name(), event.node_id.clone()));
+        }).await.unwrap();
+        assert_eq!(run["results"][3]["input"]["tag"], "fast");
+
+        // d ran once, before the slow branch finished
+        let events = events.into_inner().unwrap();
+        let position = |name: &str, node: &str| events.iter().position(|e| e.0 == name && e.1 == node).unwrap();
+        assert!(position("workflow-node-finished", "d") < position("workflow-node-finished", "slow"));
+        assert_eq!(events.iter().filter(|e| e.1 == "d").count(), 2);
+
+        let mut workflow = system.get_workflow(&id).await.unwrap();
+        workflow.nodes[3].config["join"] = serde_json::json!("any");
+        system.update_workflow(workflow).await.unwrap();
+        let err = system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("unknown join mode 'any'"));
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_attempts_stop_before_retrying() {
+        let running = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let agent = BlockingAgent { running: running.clone() };
+        let policy = NodePolicy {
+            join: JoinMode::All,
+            timeout: Some(Duration::from_millis(20)),
+            max_retries: 1,
+            retry_backoff: Duration::from_millis(50),
+            continue_on_error: false,
+        };
+
+        let (attempts, outcome) = execute_with_policy(&agent, serde_json::Value::Null, &serde_json::Value::Null, &policy, &CancellationToken::new()).await;
+        assert_eq!(attempts, 2);
+        assert!(outcome.unwrap_err().to_string().contains("Timed out after 20ms"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(running.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failure_cancels_siblings_when_configured() {
+        let mut failing = sleeper("b", 10);
+        failing.config["fail"] = serde_json::json!(true);
+        let nodes = vec![node("a", AgentType::Input), failing, sleeper("c", 300), node("d", AgentType::Output)];
+        let connections = vec![connect("a", "b"), connect("a", "c"), connect("c", "d")];
+
+        // By default running siblings finish but nothing new starts
+        let (system, id, _) = parallel_system(nodes.clone(), connections.clone(), WorkflowSettings::default()).await;
+        let err = system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("sleeper failed"));
+        let run = system.get_run(&system.list_runs(Some(&id), 1).await.unwrap()[0].id).await.unwrap();
+        let status: Vec<_> = run.nodes.iter().map(|n| (n.node_id.as_str(), n.status.clone())).collect();
+        assert_eq!(status, [("a", AgentStatus::Success), ("b", AgentStatus::Error), ("c", AgentStatus::Success)]);
+
+        let settings = WorkflowSettings { cancel_on_failure: true, ..Default::default() };
+        let (system, id, _) = parallel_system(nodes, connections, settings).await;
+        let timer = Instant::now();
+        system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap_err();
+        assert!(timer.elapsed() < std::time::Duration::from_millis(250));
+        let run = system.get_run(&system.list_runs(Some(&id), 1).await.unwrap()[0].id).await.unwrap();
+        let status: Vec<_> = run.nodes.iter().map(|n| (n.node_id.as_str(), n.status.clone())).collect();
+        assert_eq!(status, [("a", AgentStatus::Success), ("b", AgentStatus::Error), ("c", AgentStatus::Cancelled)]);
+    }
+
+    #[tokio::test]
+    async fn test_live_statuses_stay_in_memory_until_the_run_ends() {
+        let nodes = vec![node("a", AgentType::Input), sleeper("b", 200)];
+        let (system, id, _) = parallel_system(nodes, vec![connect("a", "b")], WorkflowSettings::default()).await;
+
+        let stored_status = |workflows: Vec<AgentWorkflow>| workflows[0].nodes[1].status.clone();
+        let (run, _) = tokio::join!(
+            system.execute_workflow(&id, serde_json::json!(1), |_| {}),
+            async {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                assert_eq!(system.get_workflow(&id).await.unwrap().nodes[1].status, AgentStatus::Running);
+                assert_eq!(stored_status(system.store.load_workflows().await.unwrap()), AgentStatus::Idle);
+            }
+        );
+        run.unwrap();
+        assert!(system.live_runs.read().await.is_empty());
+        assert_eq!(stored_status(system.store.load_workflows().await.unwrap()), AgentStatus::Success);
+
+        // A crash mid-run leaves nothing running after a restart
+        let dir = tempfile::tempdir().unwrap();
+        let store = AgentStore::open(dir.path()).unwrap();
+        let mut workflow = system.get_workflow(&id).await.unwrap();
+        workflow.nodes[1].status = AgentStatus::Running;
+        store.save_workflow(&workflow).await.unwrap();
+        drop(store);
+        let system = AgentSystem::new(AgentStore::open(dir.path()).unwrap(), Arc::new(SecurityManager::new())).await.unwrap();
+        assert_eq!(system.get_workflow(&id).await.unwrap().nodes[1].status, AgentStatus::Idle);
+    }
+
+    #[tokio::test]
+    async fn test_node_policies_retry_and_route_errors() {
+        let mut flaky = sleeper("b", 0);
+        flaky.config = serde_json::json!({ "tag": "b", "failFirst": 2, "maxRetries": 2, "retryBackoff": 1 });
+        let (system, id, _) = parallel_system(vec![node("a", AgentType::Input), flaky], vec![connect("a", "b")], WorkflowSettings::default()).await;
+        let run = system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap();
+        let stored = system.get_run(run["runId"].as_str().unwrap()).await.unwrap();
+        assert_eq!(stored.nodes[1].status, AgentStatus::Success);
+        assert_eq!(stored.nodes[1].attempts, 3);
+
+        // A timed-out node is routed to its handler; its regular downstream is skipped
+        let mut slow = sleeper("b", 500);
+        slow.config["timeout"] = serde_json::json!(20);
+        let mut on_error = connect("b", "handler");
+        on_error.label = Some("on_error".to_string());
+        let nodes = vec![node("a", AgentType::Input), slow, node("handler", AgentType::Processor), node("c", AgentType::Output)];
+        let (system, id, _) = parallel_system(nodes, vec![connect("a", "b"), on_error, connect("b", "c")], WorkflowSettings::default()).await;
+        let run = system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap();
+        let stored = system.get_run(run["runId"].as_str().unwrap()).await.unwrap();
+        let status: Vec<_> = stored.nodes.iter().map(|n| (n.node_id.as_str(), n.status.clone())).collect();
+        assert_eq!(status, [
+            ("a", AgentStatus::Success),
+            ("b", AgentStatus::Error),
+            ("handler", AgentStatus::Success),
+            ("c", AgentStatus::Skipped),
+        ]);
+        assert_eq!(stored.nodes[1].error.as_deref(), Some("Timed out after 20ms"));
+        assert_eq!(stored.nodes[2].input["nodeId"], "b");
+        assert_eq!(stored.nodes[2].input["input"]["input"], 1);
+
+        // continueOnError records the failure without failing the run
+        let mut failing = sleeper("b", 0);
+        failing.config = serde_json::json!({ "fail": true, "maxRetries": 1, "retryBackoff": 1, "continueOnError": true });
+        let nodes = vec![node("a", AgentType::Input), failing, node("c", AgentType::Output)];
+        let (system, id, _) = parallel_system(nodes, vec![connect("a", "b"), connect("a", "c")], WorkflowSettings::default()).await;
+        let run = system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap();
+        assert_eq!(run["results"][1]["error"], "sleeper failed (after 2 attempts)");
+        assert_eq!(run["results"][2]["status"], "success");
+        assert_eq!(system.list_runs(Some(&id), 1).await.unwrap()[0].status, RunStatus::Success);
+
+        let mut workflow = system.get_workflow(&id).await.unwrap();
+        workflow.nodes[1].config["maxRetries"] = serde_json::json!("lots");
+        system.update_workflow(workflow).await.unwrap();
+        let err = system.execute_workflow(&id, serde_json::json!(1), |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("invalid maxRetries"));
+    }
+}
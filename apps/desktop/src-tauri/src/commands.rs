@@ -46,6 +46,26 @@ pub struct AppSettings {
     pub terminal_settings: TerminalSettings,
     pub voice_settings: VoiceSettings,
     pub security_settings: SecuritySettings,
+    #[serde(default)]
+    pub knowledge_settings: crate::knowledge::KnowledgeSettings,
+}
+
+impl AppSettings {
+    /// The subset of settings the built-in workflow agents use
+    pub fn agent_environment(&self) -> crate::agents::AgentEnvironment {
+        crate::agents::AgentEnvironment {
+            openai_api_key: self.openai_api_key.clone(),
+            openai_organization_id: self.openai_organization_id.clone(),
+            openai_model: self.openai_model.clone(),
+            offline_mode: self.offline_mode,
+            stt_provider: self.stt_provider.clone(),
+            tts_provider: self.tts_provider.clone(),
+            voice_model: self.voice_settings.voice_model.clone(),
+            response_voice: self.voice_settings.response_voice.clone(),
+            allowed_roots: self.allowed_roots.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -131,6 +151,7 @@ impl Default for AppStateManager {
                         sandbox_plugins: true,
                         max_file_size_mb: 100,
                     },
+                    knowledge_settings: crate::knowledge::KnowledgeSettings::default(),
                 },
             }),
         }
@@ -164,6 +185,8 @@ pub async fn update_settings(
         }
     } // Lock is released here
     
+    let agent_environment = settings.agent_environment();
+    
     // Save individual settings to database
     match crate::database::with_database(|db| {
         Box::pin(async move {
@@ -175,6 +198,7 @@ pub async fn update_settings(
             db.set_setting("tts_provider", serde_json::json!(settings.tts_provider)).await?;
             db.set_setting("stt_provider", serde_json::json!(settings.stt_provider)).await?;
             db.set_setting("theme", serde_json::json!(settings.theme)).await?;
+            db.set_setting("knowledge_settings", serde_json::to_value(&settings.knowledge_settings)?).await?;
             
             // Log the action
             let audit_entry = crate::database::AuditLogEntry {
@@ -191,7 +215,29 @@ pub async fn update_settings(
             Ok(())
         })
     }).await {
-        Ok(_) => Ok(ApiResponse::success(())),
+        Ok(_) => {
+            // Apply knowledge settings to the running manager
+            let knowledge_settings = settings.knowledge_settings.clone();
+            let api_key = settings.openai_api_key.clone();
+            if let Err(e) = crate::knowledge::with_knowledge_manager(|manager| {
+                Box::pin(async move {
+                    manager.configure(knowledge_settings, api_key).await
+                })
+            }).await {
+                return Ok(ApiResponse::error(format!("Failed to apply knowledge settings: {}", e)));
+            }
+            
+            // And to the workflow agents
+            if let Err(e) = crate::agents::with_agent_system(|system| {
+                Box::pin(async move {
+                    system.configure(agent_environment).await;
+                    Ok(())
+                })
+            }).await {
+                return Ok(ApiResponse::error(format!("Failed to apply agent settings: {}", e)));
+            }
+            Ok(ApiResponse::success(()))
+        }
         Err(e) => Ok(ApiResponse::error(format!("Failed to save settings: {}", e))),
     }
 }
@@ -350,6 +396,7 @@ pub async fn get_settings(state: State<'_, AppStateManager>) -> Result<serde_jso
         "terminal_settings": settings.terminal_settings,
         "voice_settings": settings.voice_settings,
         "security_settings": settings.security_settings,
+        "knowledge_settings": settings.knowledge_settings,
     }))
 }
 
@@ -889,10 +936,14 @@ pub async fn list_agent_workflows() -> Result<ApiResponse<Vec<crate::agents::Age
 }
 
 #[tauri::command]
-pub async fn execute_agent_workflow(workflow_id: String, input: serde_json::Value) -> Result<ApiResponse<serde_json::Value>, String> {
+pub async fn execute_agent_workflow(app: tauri::AppHandle, workflow_id: String, input: serde_json::Value) -> Result<ApiResponse<serde_json::Value>, String> {
+    use tauri::Emitter;
+    
     match crate::agents::with_agent_system(|system| {
         Box::pin(async move {
-            system.execute_workflow(&workflow_id, input).await
+            system.execute_workflow(&workflow_id, input, |event| {
+                let _ = app.emit(event.event_name(), event);
+            }).await
         })
     }).await {
         Ok(result) => Ok(ApiResponse::success(result)),
@@ -900,6 +951,62 @@ pub async fn execute_agent_workflow(workflow_id: String, input: serde_json::Valu
     }
 }
 
+#[tauri::command]
+pub async fn list_agent_workflow_runs(workflow_id: Option<String>, limit: Option<usize>) -> Result<ApiResponse<Vec<crate::agents::WorkflowRunSummary>>, String> {
+    match crate::agents::with_agent_system(|system| {
+        Box::pin(async move {
+            system.list_runs(workflow_id.as_deref(), limit.unwrap_or(50)).await
+        })
+    }).await {
+        Ok(runs) => Ok(ApiResponse::success(runs)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to list workflow runs: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn get_agent_workflow_run(run_id: String) -> Result<ApiResponse<crate::agents::WorkflowRun>, String> {
+    match crate::agents::with_agent_system(|system| {
+        Box::pin(async move {
+            system.get_run(&run_id).await
+        })
+    }).await {
+        Ok(run) => Ok(ApiResponse::success(run)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to get workflow run: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn export_workflow(workflow_id: String, format: String) -> Result<ApiResponse<String>, String> {
+    match crate::agents::with_agent_system(|system| {
+        Box::pin(async move {
+            let format = crate::agent_files::WorkflowFileFormat::parse(&format)?;
+            crate::agent_files::export_workflow(system, &workflow_id, format).await
+        })
+    }).await {
+        Ok(text) => Ok(ApiResponse::success(text)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to export workflow: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn import_workflow(text: String) -> Result<ApiResponse<crate::agents::AgentWorkflow>, String> {
+    // Plugins a workflow names must be loaded; with no plugin system every such reference is missing
+    let installed_plugins = crate::plugin_system::with_plugin_system(|system| {
+        Box::pin(async move {
+            Ok(system.list_plugins().await.into_iter().map(|p| p.id).collect::<Vec<_>>())
+        })
+    }).await.unwrap_or_default();
+    
+    match crate::agents::with_agent_system(|system| {
+        Box::pin(async move {
+            crate::agent_files::import_workflow(system, &text, &installed_plugins).await
+        })
+    }).await {
+        Ok(workflow) => Ok(ApiResponse::success(workflow)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to import workflow: {}", e))),
+    }
+}
+
 #[tauri::command]
 pub async fn get_available_agents() -> Result<ApiResponse<Vec<(String, String, crate::agents::AgentType)>>, String> {
     match crate::agents::with_agent_system(|system| {
@@ -1048,6 +1155,19 @@ pub async fn read_knowledge_content(item_id: String) -> Result<ApiResponse<Strin
     }
 }
 
+#[tauri::command]
+pub async fn preview_dataset(item_id: String, rows: Option<usize>) -> Result<ApiResponse<crate::knowledge_datasets::DatasetPreview>, String> {
+    let rows = rows.unwrap_or(crate::knowledge_datasets::DEFAULT_PREVIEW_ROWS);
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.preview_dataset(&item_id, rows).await
+        })
+    }).await {
+        Ok(preview) => Ok(ApiResponse::success(preview)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to preview dataset: {}", e))),
+    }
+}
+
 #[tauri::command]
 pub async fn create_knowledge_folder(name: String, parent_path: Option<String>) -> Result<ApiResponse<crate::knowledge::KnowledgeItem>, String> {
     match crate::knowledge::with_knowledge_manager(|manager| {
@@ -1062,11 +1182,11 @@ pub async fn create_knowledge_folder(name: String, parent_path: Option<String>)
 }
 
 #[tauri::command]
-pub async fn upload_knowledge_file(file_name: String, content: Vec<u8>, parent_path: Option<String>) -> Result<ApiResponse<crate::knowledge::KnowledgeItem>, String> {
+pub async fn upload_knowledge_file(file_name: String, content: Vec<u8>, parent_path: Option<String>, overwrite: Option<bool>) -> Result<ApiResponse<crate::knowledge::KnowledgeUpload>, String> {
     match crate::knowledge::with_knowledge_manager(|manager| {
         Box::pin(async move {
             let parent = parent_path.as_ref().map(|p| std::path::Path::new(p));
-            manager.upload_file(parent, &file_name, content).await
+            manager.upload_file_checked(parent, &file_name, content, overwrite.unwrap_or(false)).await
         })
     }).await {
         Ok(file) => Ok(ApiResponse::success(file)),
@@ -1074,6 +1194,57 @@ pub async fn upload_knowledge_file(file_name: String, content: Vec<u8>, parent_p
     }
 }
 
+#[tauri::command]
+pub async fn import_knowledge(app: tauri::AppHandle, source: String, options: Option<crate::knowledge_import::ImportOptions>) -> Result<ApiResponse<crate::knowledge_import::ImportSummary>, String> {
+    use tauri::Emitter;
+    
+    let manager = crate::knowledge::shared_knowledge_manager();
+    let options = options.unwrap_or_default();
+    match crate::knowledge_import::import_knowledge(manager, std::path::Path::new(&source), &options, |progress| {
+        let _ = app.emit("knowledge-import-progress", progress);
+    }).await {
+        Ok(summary) => Ok(ApiResponse::success(summary)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to import knowledge: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn export_knowledge(app: tauri::AppHandle, path: String, options: Option<crate::knowledge_bundle::ExportOptions>) -> Result<ApiResponse<crate::knowledge_bundle::ExportSummary>, String> {
+    use tauri::Emitter;
+    
+    let manager = crate::knowledge::shared_knowledge_manager();
+    let options = options.unwrap_or_default();
+    match crate::knowledge_bundle::export_bundle(manager, std::path::Path::new(&path), &options, |progress| {
+        let _ = app.emit("knowledge-export-progress", progress);
+    }).await {
+        Ok(summary) => Ok(ApiResponse::success(summary)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to export knowledge: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn verify_knowledge_bundle(path: String) -> Result<ApiResponse<crate::knowledge_bundle::BundleVerification>, String> {
+    match tokio::task::spawn_blocking(move || crate::knowledge_bundle::verify_bundle(std::path::Path::new(&path))).await {
+        Ok(Ok(verification)) => Ok(ApiResponse::success(verification)),
+        Ok(Err(e)) => Ok(ApiResponse::error(format!("Failed to verify bundle: {}", e))),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to verify bundle: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn import_knowledge_bundle(app: tauri::AppHandle, path: String, options: Option<crate::knowledge_bundle::BundleImportOptions>) -> Result<ApiResponse<crate::knowledge_bundle::BundleImportSummary>, String> {
+    use tauri::Emitter;
+    
+    let manager = crate::knowledge::shared_knowledge_manager();
+    let options = options.unwrap_or_default();
+    match crate::knowledge_bundle::import_bundle(manager, std::path::Path::new(&path), &options, |progress| {
+        let _ = app.emit("knowledge-bundle-import-progress", progress);
+    }).await {
+        Ok(summary) => Ok(ApiResponse::success(summary)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to import bundle: {}", e))),
+    }
+}
+
 #[tauri::command]
 pub async fn delete_knowledge_item(item_id: String) -> Result<ApiResponse<()>, String> {
     match crate::knowledge::with_knowledge_manager(|manager| {
@@ -1086,6 +1257,235 @@ pub async fn delete_knowledge_item(item_id: String) -> Result<ApiResponse<()>, S
     }
 }
 
+#[tauri::command]
+pub async fn move_knowledge_item(item_id: String, new_parent_path: Option<String>) -> Result<ApiResponse<crate::knowledge::KnowledgeItem>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            let parent = new_parent_path.as_ref().map(|p| std::path::Path::new(p));
+            manager.move_item(&item_id, parent).await
+        })
+    }).await {
+        Ok(item) => Ok(ApiResponse::success(item)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to move item: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn rename_knowledge_item(item_id: String, new_name: String) -> Result<ApiResponse<crate::knowledge::KnowledgeItem>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.rename_item(&item_id, &new_name).await
+        })
+    }).await {
+        Ok(item) => Ok(ApiResponse::success(item)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to rename item: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn copy_knowledge_item(item_id: String, new_parent_path: Option<String>, reembed: Option<bool>) -> Result<ApiResponse<crate::knowledge::KnowledgeItem>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            let parent = new_parent_path.as_ref().map(|p| std::path::Path::new(p));
+            manager.copy_item(&item_id, parent, reembed.unwrap_or(false)).await
+        })
+    }).await {
+        Ok(item) => Ok(ApiResponse::success(item)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to copy item: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn find_knowledge_duplicates(threshold: Option<f32>) -> Result<ApiResponse<crate::knowledge_dedupe::DuplicateReport>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.find_duplicates(threshold.unwrap_or(0.8)).await
+        })
+    }).await {
+        Ok(report) => Ok(ApiResponse::success(report)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to find duplicates: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn merge_duplicates(keep_id: String, duplicate_ids: Vec<String>) -> Result<ApiResponse<crate::knowledge::KnowledgeItem>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.merge_duplicates(&keep_id, &duplicate_ids).await
+        })
+    }).await {
+        Ok(item) => Ok(ApiResponse::success(item)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to merge duplicates: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn list_knowledge_versions(item_id: String) -> Result<ApiResponse<Vec<crate::knowledge_versions::KnowledgeVersion>>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.list_versions(&item_id).await
+        })
+    }).await {
+        Ok(versions) => Ok(ApiResponse::success(versions)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to list versions: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn diff_knowledge_versions(item_id: String, from_version: Option<String>, to_version: Option<String>) -> Result<ApiResponse<String>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.diff_versions(&item_id, from_version.as_deref(), to_version.as_deref()).await
+        })
+    }).await {
+        Ok(diff) => Ok(ApiResponse::success(diff)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to diff versions: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn restore_knowledge_version(item_id: String, version_id: String) -> Result<ApiResponse<crate::knowledge::KnowledgeItem>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.restore_version(&item_id, &version_id).await
+        })
+    }).await {
+        Ok(item) => Ok(ApiResponse::success(item)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to restore version: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn get_knowledge_links(item_id: String) -> Result<ApiResponse<Vec<crate::knowledge_links::KnowledgeLink>>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.outgoing_links(&item_id).await
+        })
+    }).await {
+        Ok(links) => Ok(ApiResponse::success(links)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to get links: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn get_knowledge_backlinks(item_id: String) -> Result<ApiResponse<Vec<crate::knowledge_links::KnowledgeLink>>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.backlinks(&item_id).await
+        })
+    }).await {
+        Ok(links) => Ok(ApiResponse::success(links)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to get backlinks: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn get_broken_knowledge_links() -> Result<ApiResponse<Vec<crate::knowledge_links::KnowledgeLink>>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.broken_links().await
+        })
+    }).await {
+        Ok(links) => Ok(ApiResponse::success(links)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to find broken links: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn get_knowledge_graph(item_id: String, depth: Option<usize>) -> Result<ApiResponse<crate::knowledge_links::KnowledgeGraph>, String> {
+    let depth = depth.unwrap_or(1);
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.knowledge_graph(&item_id, depth).await
+        })
+    }).await {
+        Ok(graph) => Ok(ApiResponse::success(graph)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to build knowledge graph: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn enrich_knowledge_items(app: tauri::AppHandle, item_ids: Option<Vec<String>>) -> Result<ApiResponse<crate::knowledge_enrichment::EnrichmentSummary>, String> {
+    use tauri::Emitter;
+    
+    let manager = crate::knowledge::shared_knowledge_manager();
+    match crate::knowledge_enrichment::enrich_items(manager, item_ids, |progress| {
+        let _ = app.emit("knowledge-enrichment-progress", progress);
+    }).await {
+        Ok(summary) => Ok(ApiResponse::success(summary)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to enrich items: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn list_knowledge_suggestions() -> Result<ApiResponse<Vec<crate::knowledge_enrichment::EnrichmentSuggestion>>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.list_suggestions().await
+        })
+    }).await {
+        Ok(suggestions) => Ok(ApiResponse::success(suggestions)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to list suggestions: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn accept_knowledge_suggestion(item_id: String, tags: Option<Vec<String>>, include_description: Option<bool>) -> Result<ApiResponse<crate::knowledge::KnowledgeItem>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.accept_suggestion(&item_id, tags, include_description.unwrap_or(true)).await
+        })
+    }).await {
+        Ok(item) => Ok(ApiResponse::success(item)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to accept suggestion: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn dismiss_knowledge_suggestion(item_id: String) -> Result<ApiResponse<()>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.dismiss_suggestion(&item_id).await
+        })
+    }).await {
+        Ok(_) => Ok(ApiResponse::success(())),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to dismiss suggestion: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn transcribe_knowledge_item(app: tauri::AppHandle, item_id: String) -> Result<ApiResponse<crate::knowledge::KnowledgeItem>, String> {
+    use crate::knowledge_transcripts::{TranscriptionProgress, TranscriptionStage};
+    use tauri::Emitter;
+    
+    let report = |progress: &TranscriptionProgress| {
+        let _ = app.emit("knowledge-transcription-progress", progress);
+    };
+    
+    // Decoding and transcribing can take minutes, so the knowledge lock is only held to
+    // read the item and to store the transcript
+    let result = async {
+        let job = crate::knowledge::with_knowledge_manager(|manager| {
+            Box::pin(async move { manager.prepare_transcription(&item_id).await })
+        }).await?;
+        let (media, transcript) = job.run(report).await?;
+        
+        let stage = |stage| TranscriptionProgress { item_id: media.id.clone(), stage };
+        report(&stage(TranscriptionStage::Indexing));
+        let done = stage(TranscriptionStage::Done);
+        let sidecar = crate::knowledge::with_knowledge_manager(|manager| {
+            Box::pin(async move { manager.save_transcript(&media, &transcript).await })
+        }).await?;
+        report(&done);
+        anyhow::Ok(sidecar)
+    }.await;
+    
+    match result {
+        Ok(transcript) => Ok(ApiResponse::success(transcript)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to transcribe item: {}", e))),
+    }
+}
+
 #[tauri::command]
 pub async fn update_knowledge_tags(item_id: String, tags: Vec<String>) -> Result<ApiResponse<()>, String> {
     match crate::knowledge::with_knowledge_manager(|manager| {
@@ -1124,11 +1524,7 @@ pub async fn toggle_knowledge_private(item_id: String) -> Result<ApiResponse<boo
 
 #[tauri::command]
 pub async fn vectorize_knowledge_item(item_id: String) -> Result<ApiResponse<()>, String> {
-    match crate::knowledge::with_knowledge_manager(|manager| {
-        Box::pin(async move {
-            manager.vectorize_item(&item_id).await
-        })
-    }).await {
+    match crate::knowledge::vectorize_item(crate::knowledge::shared_knowledge_manager(), &item_id).await {
         Ok(_) => Ok(ApiResponse::success(())),
         Err(e) => Ok(ApiResponse::error(format!("Failed to vectorize item: {}", e))),
     }
@@ -1146,6 +1542,81 @@ pub async fn search_similar_knowledge(item_id: String, limit: usize) -> Result<A
     }
 }
 
+#[tauri::command]
+pub async fn search_knowledge(query: String, filters: Option<crate::knowledge::KnowledgeSearchFilters>, limit: Option<usize>) -> Result<ApiResponse<Vec<crate::knowledge::KnowledgeSearchResult>>, String> {
+    let manager = crate::knowledge::shared_knowledge_manager();
+    let filters = filters.unwrap_or_default();
+    match crate::knowledge::search(manager, &query, &filters, limit.unwrap_or(20)).await {
+        Ok(results) => Ok(ApiResponse::success(results)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to search knowledge: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn list_knowledge_collections() -> Result<ApiResponse<Vec<crate::knowledge_collections::Collection>>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.list_collections().await
+        })
+    }).await {
+        Ok(collections) => Ok(ApiResponse::success(collections)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to list collections: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn create_knowledge_collection(name: String, query: String) -> Result<ApiResponse<crate::knowledge_collections::Collection>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.create_collection(&name, &query).await
+        })
+    }).await {
+        Ok(collection) => Ok(ApiResponse::success(collection)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to create collection: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn update_knowledge_collection(collection_id: String, name: Option<String>, query: Option<String>) -> Result<ApiResponse<crate::knowledge_collections::Collection>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.update_collection(&collection_id, name.as_deref(), query.as_deref()).await
+        })
+    }).await {
+        Ok(collection) => Ok(ApiResponse::success(collection)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to update collection: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn delete_knowledge_collection(collection_id: String) -> Result<ApiResponse<()>, String> {
+    match crate::knowledge::with_knowledge_manager(|manager| {
+        Box::pin(async move {
+            manager.delete_collection(&collection_id).await
+        })
+    }).await {
+        Ok(_) => Ok(ApiResponse::success(())),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to delete collection: {}", e))),
+    }
+}
+
+#[tauri::command]
+pub async fn list_collection_items(collection_id: String) -> Result<ApiResponse<Vec<crate::knowledge::KnowledgeItem>>, String> {
+    match crate::knowledge::list_collection_items(crate::knowledge::shared_knowledge_manager(), &collection_id).await {
+        Ok(items) => Ok(ApiResponse::success(items)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to list collection items: {}", e))),
+    }
+}
+
+// Evaluate a query without saving it, e.g. while a collection is being edited
+#[tauri::command]
+pub async fn query_knowledge_items(query: String) -> Result<ApiResponse<Vec<crate::knowledge::KnowledgeItem>>, String> {
+    match crate::knowledge::query_items(crate::knowledge::shared_knowledge_manager(), &query).await {
+        Ok(items) => Ok(ApiResponse::success(items)),
+        Err(e) => Ok(ApiResponse::error(format!("Failed to query knowledge: {}", e))),
+    }
+}
+
 #[tauri::command]
 pub async fn get_knowledge_storage_info() -> Result<ApiResponse<crate::knowledge::StorageInfo>, String> {
     match crate::knowledge::with_knowledge_manager(|manager| {
@@ -7,6 +7,7 @@ pub struct ApiConfig {
     pub openai_chat_endpoint: String,
     pub openai_stt_endpoint: String,
     pub openai_tts_endpoint: String,
+    pub openai_embeddings_endpoint: String,
     pub ollama_base_url: String,
     pub localbrain_docs_base_url: String,
 }
@@ -22,6 +23,8 @@ impl Default for ApiConfig {
                 .unwrap_or_else(|_| "/v1/audio/transcriptions".to_string()),
             openai_tts_endpoint: env::var("OPENAI_TTS_ENDPOINT")
                 .unwrap_or_else(|_| "/v1/audio/speech".to_string()),
+            openai_embeddings_endpoint: env::var("OPENAI_EMBEDDINGS_ENDPOINT")
+                .unwrap_or_else(|_| "/v1/embeddings".to_string()),
             ollama_base_url: env::var("OLLAMA_BASE_URL")
                 .unwrap_or_else(|_| "http://localhost:11434".to_string()),
             localbrain_docs_base_url: env::var("LOCALBRAIN_DOCS_URL")
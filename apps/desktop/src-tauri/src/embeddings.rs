@@ -0,0 +1,181 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Dimension of the built-in hashing embedder.
+pub const HASHING_DIM: usize = 384;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingProvider {
+    Local,  // Feature-hashing embedder, no model download required
+    Ollama, // Local Ollama server
+    Openai, // OpenAI embeddings API (leaves the machine)
+}
+
+#[derive(Debug, Clone)]
+pub struct Embedder {
+    provider: EmbeddingProvider,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl Embedder {
+    pub fn new(provider: EmbeddingProvider, model: Option<String>, api_key: Option<String>) -> Self {
+        let model = model.unwrap_or_else(|| match provider {
+            EmbeddingProvider::Local => format!("hashing-{}", HASHING_DIM),
+            EmbeddingProvider::Ollama => "nomic-embed-text".to_string(),
+            EmbeddingProvider::Openai => "text-embedding-3-small".to_string(),
+        });
+
+        Self {
+            provider,
+            model,
+            api_key,
+        }
+    }
+
+    /// Identifier stored next to persisted vectors so that switching models invalidates them
+    pub fn model_id(&self) -> String {
+        let provider = match self.provider {
+            EmbeddingProvider::Local => "local",
+            EmbeddingProvider::Ollama => "ollama",
+            EmbeddingProvider::Openai => "openai",
+        };
+        format!("{}:{}", provider, self.model)
+    }
+
+    /// Whether embedding text with this provider keeps it on the machine
+    pub fn is_local(&self) -> bool {
+        !matches!(self.provider, EmbeddingProvider::Openai)
+    }
+
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        match self.provider {
+            EmbeddingProvider::Local => Ok(texts.iter().map(|t| hash_embed(t)).collect()),
+            EmbeddingProvider::Ollama => {
+                let client = crate::ollama::OllamaClient::new(None);
+                let mut vectors = Vec::with_capacity(texts.len());
+                for text in texts {
+                    vectors.push(client.embed(&self.model, text).await?);
+                }
+                Ok(vectors)
+            }
+            EmbeddingProvider::Openai => self.embed_openai(texts).await,
+        }
+    }
+
+    async fn embed_openai(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let api_key = match self.api_key.as_ref() {
+            Some(key) => key.clone(),
+            None => crate::config::get_openai_api_key()
+                .ok_or_else(|| anyhow!("OpenAI API key not configured"))?,
+        };
+
+        let url = format!("{}{}", crate::config::CONFIG.api.openai_base_url, crate::config::CONFIG.api.openai_embeddings_endpoint);
+        let response = reqwest::Client::new()
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", api_key))
+            .json(&serde_json::json!({
+                "model": self.model,
+                "input": texts,
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("OpenAI embeddings error: {}", error_text));
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        let entries = data["data"].as_array()
+            .ok_or_else(|| anyhow!("Invalid response format from embeddings API"))?;
+
+        entries.iter()
+            .map(|entry| {
+                entry["embedding"].as_array()
+                    .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+                    .ok_or_else(|| anyhow!("Missing embedding in response"))
+            })
+            .collect()
+    }
+}
+
+/// Lowercased alphanumeric tokens, shared by the embedder and keyword search
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Deterministic bag-of-words embedding using the hashing trick over unigrams and bigrams
+pub fn hash_embed(text: &str) -> Vec<f32> {
+    let mut vector = vec![0f32; HASHING_DIM];
+    let tokens = tokenize(text);
+
+    let mut add = |feature: &str, weight: f32| {
+        let hash = fnv1a(feature.as_bytes());
+        let index = (hash % HASHING_DIM as u64) as usize;
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[index] += sign * weight;
+    };
+
+    for token in &tokens {
+        add(token, 1.0);
+    }
+    for pair in tokens.windows(2) {
+        add(&format!("{} {}", pair[0], pair[1]), 0.5);
+    }
+
+    normalize(&mut vector);
+    vector
+}
+
+pub fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for value in vector.iter_mut() {
+            *value /= norm;
+        }
+    }
+}
+
+/// Split text into overlapping chunks of at most `max_chars`, preferring whitespace boundaries
+pub fn chunk_text(text: &str, max_chars: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = (start + max_chars).min(chars.len());
+        if end < chars.len() {
+            if let Some(ws) = chars[start..end].iter().rposition(|c| c.is_whitespace()) {
+                if ws > max_chars / 2 {
+                    end = start + ws;
+                }
+            }
+        }
+
+        let chunk: String = chars[start..end].iter().collect();
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_string());
+        }
+
+        if end >= chars.len() {
+            break;
+        }
+        start = end.saturating_sub(overlap).max(start + 1);
+    }
+
+    chunks
+}
+
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -1,14 +1,50 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use std::path::{Path, PathBuf};
 use chrono::{DateTime, Utc};
 use std::fs;
-use std::io::Read;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::embeddings::{self, Embedder, EmbeddingProvider};
+use crate::knowledge_bundle::{BundleEntry, ConflictPolicy};
+use crate::knowledge_collections::{self, Collection, EvalContext};
+use crate::knowledge_crypto::{self, KnowledgeCipher};
+use crate::knowledge_datasets::{self, DatasetFormat, DatasetPreview};
+use crate::knowledge_dedupe::{self, DuplicateGroup, DuplicateReport};
+use crate::knowledge_enrichment::{Enricher, EnrichmentJob, EnrichmentSettings, EnrichmentSuggestion};
+use crate::knowledge_import::{self, ImportOutcome, Provenance};
+use crate::knowledge_index::{self, KnowledgeIndex, StoredLink, INDEX_DIR_NAME};
+use crate::knowledge_links::{self, GraphEdge, GraphNode, KnowledgeGraph, KnowledgeLink, LinkKind};
+use crate::knowledge_transcripts::{self, Transcriber, Transcript, TranscriptRecord, TranscriptionJob, TranscriptionSettings};
+use crate::knowledge_versions::{self, KnowledgeVersion, VersionRetention, VersionStore};
+use crate::vector_index::{self, HnswIndex, HnswParams, VectorHit};
+
+// Embedding chunk sizes, in characters
+const CHUNK_CHARS: usize = 1200;
+const CHUNK_OVERLAP: usize = 200;
+// Files larger than this are not read for full-text indexing
+const MAX_INDEXED_TEXT_BYTES: u64 = 4 * 1024 * 1024;
+// Reciprocal rank fusion constant
+const RRF_K: f32 = 60.0;
+// Device names Windows refuses as file names, with or without an extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+// Chunks fetched from the ANN index per requested item
+const CHUNKS_PER_ITEM: usize = 8;
+const VECTOR_INDEX_FILE: &str = "vectors.hnsw";
+// Short-lived decoded media for local transcription, below the index directory
+const SCRATCH_DIR: &str = "scratch";
+// Quiet period after an item change before collections are reported as changed
+const CHANGE_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(250);
+// Kept in the app data dir next to the database key, outside the knowledge folder
+const KNOWLEDGE_KEY_FILE: &str = ".localbrain_knowledge_key";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ItemType {
     Folder,
@@ -20,6 +56,33 @@ pub enum ItemType {
     Model,
 }
 
+impl ItemType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemType::Folder => "folder",
+            ItemType::Document => "document",
+            ItemType::Image => "image",
+            ItemType::Video => "video",
+            ItemType::Audio => "audio",
+            ItemType::Dataset => "dataset",
+            ItemType::Model => "model",
+        }
+    }
+    
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "folder" => Some(ItemType::Folder),
+            "document" => Some(ItemType::Document),
+            "image" => Some(ItemType::Image),
+            "video" => Some(ItemType::Video),
+            "audio" => Some(ItemType::Audio),
+            "dataset" => Some(ItemType::Dataset),
+            "model" => Some(ItemType::Model),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KnowledgeItem {
     pub id: String,
@@ -38,19 +101,137 @@ pub struct KnowledgeItem {
     pub path: PathBuf,
     pub children: Option<Vec<KnowledgeItem>>,
     pub content: Option<String>,
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+}
+
+/// An uploaded item, plus an existing item with the same bytes when there is one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeUpload {
+    #[serde(flatten)]
+    pub item: KnowledgeItem,
+    pub duplicate_of: Option<KnowledgeItem>,
+}
+
+/// An item's text split into chunks, embedded outside the manager lock
+struct VectorizeJob {
+    item: KnowledgeItem,
+    text: String,
+    chunks: Vec<String>,
+    embedder: Embedder,
+}
+
+impl VectorizeJob {
+    async fn run(&self) -> Result<Vec<Vec<f32>>> {
+        self.embedder.embed(&self.chunks).await
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageInfo {
-    pub used_bytes: u64,
-    pub total_bytes: u64,
+    pub used_bytes: u64,              // Files plus index data
+    pub total_bytes: u64,             // Quota, capped by what the volume can still hold
     pub percentage: f32,
+    pub quota_bytes: Option<u64>,
+    pub disk_free_bytes: u64,
+    pub disk_total_bytes: u64,
+    pub index_bytes: u64,             // Everything under the index directory
+    pub vector_index_bytes: u64,
+    pub text_cache_bytes: u64,        // Extracted text held for full-text search
+    pub versions_bytes: u64,          // Previous file contents kept for history
+    pub by_folder: HashMap<String, u64>,
+    pub by_type: HashMap<ItemType, u64>,
+}
+
+/// Running file-size totals, updated as items are written and deleted
+#[derive(Debug, Clone, Default)]
+struct UsageTotals {
+    files_bytes: u64,
+    by_folder: HashMap<String, u64>,
+    by_type: HashMap<ItemType, u64>,
+}
+
+impl UsageTotals {
+    fn add(&mut self, folder: String, item_type: ItemType, bytes: u64) {
+        self.files_bytes += bytes;
+        *self.by_folder.entry(folder).or_insert(0) += bytes;
+        *self.by_type.entry(item_type).or_insert(0) += bytes;
+    }
+    
+    fn subtract(&mut self, folder: String, item_type: ItemType, bytes: u64) {
+        self.files_bytes = self.files_bytes.saturating_sub(bytes);
+        if let Some(total) = self.by_folder.get_mut(&folder) {
+            *total = total.saturating_sub(bytes);
+        }
+        if let Some(total) = self.by_type.get_mut(&item_type) {
+            *total = total.saturating_sub(bytes);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KnowledgeSettings {
+    pub embedding_provider: EmbeddingProvider,
+    pub embedding_model: Option<String>,
+    pub vector_index: HnswParams,
+    pub quota_bytes: Option<u64>, // None means limited only by the disk
+    pub version_retention: VersionRetention,
+    pub enrichment: EnrichmentSettings,
+    pub transcription: TranscriptionSettings,
+}
+
+impl Default for KnowledgeSettings {
+    fn default() -> Self {
+        Self {
+            embedding_provider: EmbeddingProvider::Local,
+            embedding_model: None,
+            vector_index: HnswParams::default(),
+            quota_bytes: None,
+            version_retention: VersionRetention::default(),
+            enrichment: EnrichmentSettings::default(),
+            transcription: TranscriptionSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KnowledgeSearchFilters {
+    pub tags: Vec<String>,
+    pub item_types: Vec<ItemType>,
+    pub folder: Option<String>,
+    pub starred: Option<bool>,
+    pub modified_after: Option<DateTime<Utc>>,
+    pub modified_before: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeSearchResult {
+    pub item: KnowledgeItem,
+    pub score: f32,
+    pub keyword_rank: Option<usize>,
+    pub vector_rank: Option<usize>,
+    pub snippet: Option<String>,
+    pub transcript_of: Option<String>, // Media item id when the hit is a transcript
+    pub timestamp: Option<f64>,        // Seconds into that media where the match is spoken
 }
 
 pub struct KnowledgeManager {
     knowledge_dir: PathBuf,
     items_cache: Arc<RwLock<HashMap<String, KnowledgeItem>>>,
-    vectorized_items: Arc<RwLock<HashMap<String, Vec<f32>>>>, // Simple embedding storage
+    index: KnowledgeIndex,
+    embedder: Arc<RwLock<Embedder>>,
+    vector_index: Arc<RwLock<Option<HnswIndex>>>, // Loaded on first use
+    vector_params: Arc<RwLock<HnswParams>>,
+    quota_bytes: Arc<RwLock<Option<u64>>>,
+    usage: Arc<RwLock<Option<UsageTotals>>>, // Computed on first request
+    versions: VersionStore,
+    version_retention: Arc<RwLock<VersionRetention>>,
+    key_path: PathBuf,
+    cipher: once_cell::sync::OnceCell<KnowledgeCipher>, // Key loaded when first needed
+    enricher: Arc<RwLock<Enricher>>,
+    transcriber: Arc<RwLock<Transcriber>>,
 }
 
 impl KnowledgeManager {
@@ -73,13 +254,206 @@ impl KnowledgeManager {
             std::fs::create_dir_all(&folder_path)?;
         }
         
+        // Every path handed out or accepted is checked against the canonical root
+        let knowledge_dir = knowledge_dir.canonicalize()?;
+        let index = KnowledgeIndex::open(&knowledge_dir)?;
+        let versions = VersionStore::open(&knowledge_dir)?;
+        let key_path = knowledge_dir.parent().unwrap_or(&knowledge_dir).join(KNOWLEDGE_KEY_FILE);
+        let defaults = KnowledgeSettings::default();
+        
+        // Whatever a crash left mid-transcription is stale
+        let _ = fs::remove_dir_all(knowledge_dir.join(INDEX_DIR_NAME).join(SCRATCH_DIR));
+        
         Ok(Self {
             knowledge_dir,
             items_cache: Arc::new(RwLock::new(HashMap::new())),
-            vectorized_items: Arc::new(RwLock::new(HashMap::new())),
+            index,
+            embedder: Arc::new(RwLock::new(Embedder::new(defaults.embedding_provider, defaults.embedding_model, None))),
+            vector_index: Arc::new(RwLock::new(None)),
+            vector_params: Arc::new(RwLock::new(defaults.vector_index)),
+            quota_bytes: Arc::new(RwLock::new(defaults.quota_bytes)),
+            usage: Arc::new(RwLock::new(None)),
+            versions,
+            version_retention: Arc::new(RwLock::new(defaults.version_retention)),
+            key_path,
+            cipher: once_cell::sync::OnceCell::new(),
+            enricher: Arc::new(RwLock::new(Enricher::new(defaults.enrichment, None))),
+            transcriber: Arc::new(RwLock::new(Transcriber::new(defaults.transcription, None))),
         })
     }
     
+    fn cipher(&self) -> Result<&KnowledgeCipher> {
+        self.cipher.get_or_try_init(|| KnowledgeCipher::load_or_create(&self.key_path))
+    }
+    
+    /// Read a file, decrypting it if it belongs to a private item
+    fn read_plain(&self, path: &Path) -> Result<Vec<u8>> {
+        let bytes = fs::read(path)?;
+        if knowledge_crypto::is_sealed(&bytes) {
+            self.cipher()?.open(&bytes)
+        } else {
+            Ok(bytes)
+        }
+    }
+    
+    /// Record which embedding model produced the stored vectors
+    pub async fn record_embedding_model(&self) -> Result<()> {
+        if self.index.get_meta("embedding_model").await?.is_none() {
+            let model_id = self.embedder.read().await.model_id();
+            self.index.set_meta("embedding_model", &model_id).await?;
+        }
+        Ok(())
+    }
+    
+    /// Apply knowledge settings. Switching the embedding model invalidates stored vectors.
+    pub async fn configure(&self, settings: KnowledgeSettings, api_key: Option<String>) -> Result<()> {
+        *self.enricher.write().await = Enricher::new(settings.enrichment, api_key.clone());
+        *self.transcriber.write().await = Transcriber::new(settings.transcription, api_key.clone());
+        let embedder = Embedder::new(settings.embedding_provider, settings.embedding_model, api_key);
+        let model_id = embedder.model_id();
+        *self.quota_bytes.write().await = settings.quota_bytes;
+        *self.version_retention.write().await = settings.version_retention;
+        
+        // Graph shape changes need a rebuild on next use; ef_search applies immediately
+        let params = settings.vector_index;
+        let previous = std::mem::replace(&mut *self.vector_params.write().await, params);
+        {
+            let mut vector_index = self.vector_index.write().await;
+            if previous.m != params.m || previous.ef_construction != params.ef_construction {
+                *vector_index = None;
+            } else if let Some(ann) = vector_index.as_mut() {
+                ann.set_ef_search(params.ef_search);
+            }
+        }
+        
+        match self.index.get_meta("embedding_model").await? {
+            Some(current) if current == model_id => {}
+            Some(_) => {
+                self.index.clear_embeddings().await?;
+                *self.vector_index.write().await = Some(HnswIndex::new(&model_id, params));
+                let _ = fs::remove_file(self.vector_index_path());
+                let _ = fs::remove_file(vector_index::log_path(&self.vector_index_path()));
+                for item in self.items_cache.write().await.values_mut() {
+                    item.vectorized = false;
+                    item.embedding_count = None;
+                }
+                self.index.set_meta("embedding_model", &model_id).await?;
+            }
+            None => self.index.set_meta("embedding_model", &model_id).await?,
+        }
+        
+        *self.embedder.write().await = embedder;
+        Ok(())
+    }
+    
+    fn vector_index_path(&self) -> PathBuf {
+        self.knowledge_dir.join(INDEX_DIR_NAME).join(VECTOR_INDEX_FILE)
+    }
+    
+    /// Access the ANN index, loading it from disk on first use. A missing, corrupt or
+    /// stale index file is rebuilt from the embeddings stored in the knowledge index.
+    async fn ann(&self) -> Result<tokio::sync::RwLockWriteGuard<'_, Option<HnswIndex>>> {
+        let mut guard = self.vector_index.write().await;
+        if guard.is_some() {
+            return Ok(guard);
+        }
+        
+        let model_id = self.embedder.read().await.model_id();
+        let params = *self.vector_params.read().await;
+        let path = self.vector_index_path();
+        
+        let loaded = match HnswIndex::load(&path) {
+            Ok(ann) if ann.is_compatible(&model_id, &params) => Some(ann),
+            Ok(_) => None,
+            Err(e) => {
+                if path.exists() {
+                    eprintln!("Vector index unreadable, rebuilding: {}", e);
+                }
+                None
+            }
+        };
+        
+        let ann = match loaded {
+            Some(mut ann) => {
+                ann.set_ef_search(params.ef_search);
+                self.reconcile_ann(&mut ann).await?;
+                ann
+            }
+            None => {
+                let mut ann = HnswIndex::new(&model_id, params);
+                for (item_id, vectors) in self.index.load_embeddings().await? {
+                    for (chunk, vector) in vectors.into_iter().enumerate() {
+                        ann.insert(&item_id, chunk as u32, vector)?;
+                    }
+                }
+                ann.save(&path)?;
+                ann
+            }
+        };
+        
+        *guard = Some(ann);
+        Ok(guard)
+    }
+    
+    /// Shared access to the ANN index for searches, loading it first if needed
+    async fn ann_read(&self) -> Result<tokio::sync::RwLockReadGuard<'_, Option<HnswIndex>>> {
+        let guard = self.vector_index.read().await;
+        if guard.is_some() {
+            return Ok(guard);
+        }
+        drop(guard);
+        Ok(self.ann().await?.downgrade())
+    }
+    
+    /// The database is written before the index file, so a crash in between leaves the two
+    /// apart. Bring a freshly loaded index back in line with the stored embeddings.
+    async fn reconcile_ann(&self, ann: &mut HnswIndex) -> Result<()> {
+        let stored = self.index.embedding_counts().await?;
+        let mut repaired = 0;
+        for (item_id, chunks) in ann.item_chunks() {
+            if stored.get(&item_id) != Some(&chunks) {
+                ann.remove_item(&item_id);
+                repaired += 1;
+            }
+        }
+        for item_id in stored.keys() {
+            if ann.contains_item(item_id) {
+                continue;
+            }
+            for (chunk, blob) in self.index.embedding_blobs(item_id).await?.iter().enumerate() {
+                ann.insert(item_id, chunk as u32, knowledge_index::decode_vector(blob))?;
+            }
+            repaired += 1;
+        }
+        
+        if repaired > 0 {
+            eprintln!("Vector index out of date, repaired {} items", repaired);
+            if ann.should_compact() {
+                ann.compact()?;
+            }
+            ann.save(&self.vector_index_path())?;
+        }
+        Ok(())
+    }
+    
+    /// Drop items from the ANN index and persist the change
+    async fn remove_vectors(&self, item_ids: &[String]) -> Result<()> {
+        let mut guard = self.ann().await?;
+        if let Some(ann) = guard.as_mut() {
+            let mut changed = false;
+            for id in item_ids {
+                changed |= ann.remove_item(id);
+            }
+            if changed {
+                if ann.should_compact() {
+                    ann.compact()?;
+                }
+                ann.save(&self.vector_index_path())?;
+            }
+        }
+        Ok(())
+    }
+    
     pub async fn scan_directory(&self, path: &Path) -> Result<Vec<KnowledgeItem>> {
         let mut items = Vec::new();
         
@@ -122,8 +496,24 @@ impl KnowledgeManager {
                 path: file_path.clone(),
                 children: None,
                 content: None,
+                provenance: None,
             };
             
+            // Restore the persisted id and metadata for this path
+            let (stored, changed) = self.index.upsert_item(&item).await?;
+            item = stored;
+            if changed {
+                self.index_content(&item).await?;
+                if metadata.is_file() {
+                    // Sealed items keep no hash, which would confirm guesses at their plaintext
+                    let hash = match item.private {
+                        true => None,
+                        false => Some(knowledge_dedupe::hash_file(&file_path)?),
+                    };
+                    self.index.set_content_hash(&item.id, hash.as_deref()).await?;
+                }
+            }
+            
             // Recursively scan subdirectories
             if metadata.is_dir() {
                 item.children = Some(Box::pin(self.scan_directory(&file_path)).await?);
@@ -151,7 +541,7 @@ impl KnowledgeManager {
             "jpg" | "jpeg" | "png" | "gif" | "svg" | "webp" => ItemType::Image,
             "mp4" | "avi" | "mov" | "mkv" | "webm" => ItemType::Video,
             "mp3" | "wav" | "flac" | "ogg" | "m4a" => ItemType::Audio,
-            "csv" | "json" | "xml" | "parquet" => ItemType::Dataset,
+            "csv" | "tsv" | "json" | "jsonl" | "ndjson" | "xml" | "parquet" => ItemType::Dataset,
             "onnx" | "pt" | "pth" | "h5" | "pb" => ItemType::Model,
             _ => ItemType::Document,
         }
@@ -190,46 +580,153 @@ impl KnowledgeManager {
     }
     
     pub async fn get_items(&self) -> Result<Vec<KnowledgeItem>> {
-        self.scan_directory(&self.knowledge_dir).await
+        let items = self.scan_directory(&self.knowledge_dir).await?;
+        
+        // The scan already saw every file, so refresh the usage totals from it
+        let mut totals = UsageTotals::default();
+        self.collect_usage(&items, &mut totals);
+        *self.usage.write().await = Some(totals);
+        
+        // Forget items that were removed outside the app
+        let removed = self.index.prune_missing().await?;
+        if !removed.is_empty() {
+            let mut cache = self.items_cache.write().await;
+            for id in &removed {
+                cache.remove(id);
+            }
+            drop(cache);
+            self.remove_vectors(&removed).await?;
+        }
+        
+        Ok(items)
     }
     
     pub async fn get_item(&self, item_id: &str) -> Option<KnowledgeItem> {
-        let cache = self.items_cache.read().await;
-        cache.get(item_id).cloned()
+        self.lookup_item(item_id).await.ok()
+    }
+    
+    /// Find an item in the cache, falling back to the persistent index
+    async fn lookup_item(&self, item_id: &str) -> Result<KnowledgeItem> {
+        if let Some(item) = self.items_cache.read().await.get(item_id) {
+            return Ok(item.clone());
+        }
+        
+        self.index.get_item(item_id).await?
+            .ok_or_else(|| anyhow!("Item not found"))
+    }
+    
+    /// Persist metadata changes and refresh the cached copy
+    async fn store_item(&self, item: KnowledgeItem) -> Result<()> {
+        self.index.update_metadata(&item).await?;
+        self.items_cache.write().await.insert(item.id.clone(), item);
+        Ok(())
+    }
+    
+    /// Extract plain text from documents and datasets small enough to index
+    fn extract_text(&self, item: &KnowledgeItem) -> Option<String> {
+        if !matches!(item.item_type, ItemType::Document | ItemType::Dataset) {
+            return None;
+        }
+        
+        let path = self.confine_existing(&item.path).ok()?;
+        let metadata = fs::symlink_metadata(&path).ok()?;
+        if !metadata.is_file() || metadata.len() > MAX_INDEXED_TEXT_BYTES {
+            return None;
+        }
+        
+        let text = String::from_utf8(self.read_plain(&path).ok()?).ok()?;
+        let is_html = path.extension()
+            .map(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"))
+            .unwrap_or(false);
+        
+        Some(if is_html { knowledge_import::html_to_text(&text) } else { text })
+    }
+    
+    async fn index_content(&self, item: &KnowledgeItem) -> Result<()> {
+        let content = self.extract_text(item).unwrap_or_default();
+        
+        // Private text is stored encrypted; only the name and tags stay searchable
+        if item.private {
+            let sealed = match content.is_empty() {
+                true => None,
+                false => Some(self.cipher()?.seal(content.as_bytes())?),
+            };
+            self.index.set_content(item, "").await?;
+            self.index.set_sealed_text(&item.id, sealed.as_deref()).await?;
+            self.index.set_links(&item.id, &[]).await?;
+            return self.index.set_minhash(&item.id, None).await;
+        }
+        
+        self.index.set_content(item, &content).await?;
+        self.index.set_sealed_text(&item.id, None).await?;
+        self.index_links(item, &content).await?;
+        self.index.set_minhash(&item.id, knowledge_dedupe::minhash(&content).as_deref()).await
+    }
+    
+    async fn index_links(&self, item: &KnowledgeItem, content: &str) -> Result<()> {
+        let links = match item.item_type {
+            ItemType::Document => knowledge_links::parse_links(content),
+            _ => Vec::new(),
+        };
+        self.index.set_links(&item.id, &links).await
+    }
+    
+    /// Parse links for documents indexed before links were tracked
+    pub async fn backfill_links(&self) -> Result<()> {
+        if self.index.get_meta("links_indexed").await?.is_some() {
+            return Ok(());
+        }
+        
+        for (item_id, content) in self.index.all_content().await? {
+            if let Some(item) = self.index.get_item(&item_id).await? {
+                self.index_links(&item, &content).await?;
+            }
+        }
+        
+        self.index.set_meta("links_indexed", "1").await
     }
     
     pub async fn read_item_content(&self, item_id: &str) -> Result<String> {
-        let cache = self.items_cache.read().await;
-        let item = cache.get(item_id)
-            .ok_or_else(|| anyhow!("Item not found"))?;
+        let item = self.lookup_item(item_id).await?;
+        let path = self.confine_existing(&item.path)?;
         
         match item.item_type {
             ItemType::Document => {
-                let mut file = fs::File::open(&item.path)?;
-                let mut content = String::new();
-                file.read_to_string(&mut content)?;
-                Ok(content)
+                String::from_utf8(self.read_plain(&path)?)
+                    .map_err(|_| anyhow!("{} is not valid UTF-8 text", item.name))
             }
             _ => Err(anyhow!("Cannot read content for this item type")),
         }
     }
     
-    pub async fn create_folder(&self, parent_path: Option<&Path>, name: &str) -> Result<KnowledgeItem> {
-        let folder_path = if let Some(parent) = parent_path {
-            parent.join(name)
-        } else {
-            self.knowledge_dir.join(name)
-        };
+    /// Inferred schema, column statistics and the first `rows` rows of a dataset
+    pub async fn preview_dataset(&self, item_id: &str, rows: usize) -> Result<DatasetPreview> {
+        let item = self.lookup_item(item_id).await?;
+        let path = self.confine_existing(&item.path)?;
+        let format = DatasetFormat::from_path(&path)
+            .ok_or_else(|| anyhow!("{} is not a CSV, TSV, JSON, JSONL or Parquet file", item.name))?;
         
-        if folder_path.exists() {
-            return Err(anyhow!("Folder already exists"));
+        // Private datasets are decrypted in memory, never to a temporary file
+        if item.private {
+            if fs::metadata(&path)?.len() > knowledge_datasets::MAX_DATASET_BYTES {
+                return Err(anyhow!("{} is too large to preview while private", item.name));
+            }
+            knowledge_datasets::preview_dataset(format, bytes::Bytes::from(self.read_plain(&path)?), rows)
+        } else {
+            knowledge_datasets::preview_dataset(format, fs::File::open(&path)?, rows)
         }
+    }
+    
+    pub async fn create_folder(&self, parent_path: Option<&Path>, name: &str) -> Result<KnowledgeItem> {
+        Self::validate_name(name)?;
+        let parent = self.resolve_parent(parent_path)?;
+        let folder_path = Self::unique_path(&parent, name, true);
         
-        fs::create_dir_all(&folder_path)?;
+        fs::create_dir(&folder_path)?;
         
         let item = KnowledgeItem {
             id: uuid::Uuid::new_v4().to_string(),
-            name: name.to_string(),
+            name: folder_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
             item_type: ItemType::Folder,
             size: 0,
             modified: Utc::now(),
@@ -243,44 +740,92 @@ impl KnowledgeManager {
             path: folder_path,
             children: Some(Vec::new()),
             content: None,
+            provenance: None,
         };
         
+        let (mut item, _) = self.index.upsert_item(&item).await?;
+        item.children = Some(Vec::new());
+        
         let mut cache = self.items_cache.write().await;
         cache.insert(item.id.clone(), item.clone());
         
         Ok(item)
     }
     
-    pub async fn upload_file(&self, parent_path: Option<&Path>, file_name: &str, content: Vec<u8>) -> Result<KnowledgeItem> {
-        let file_path = if let Some(parent) = parent_path {
-            parent.join(file_name)
-        } else {
-            self.knowledge_dir.join(file_name)
+    /// Write a file into the knowledge base. An existing file with the same name is replaced
+    /// when `overwrite` is set, otherwise the new file gets a numbered name.
+    pub async fn upload_file(&self, parent_path: Option<&Path>, file_name: &str, content: Vec<u8>, overwrite: bool) -> Result<KnowledgeItem> {
+        self.write_file(parent_path, file_name, content, overwrite, false).await
+    }
+    
+    /// Upload a file, reporting an existing item with identical bytes so the user can
+    /// decide whether to keep both
+    pub async fn upload_file_checked(&self, parent_path: Option<&Path>, file_name: &str, content: Vec<u8>, overwrite: bool) -> Result<KnowledgeUpload> {
+        let existing = self.index.find_by_hash(&knowledge_dedupe::content_hash(&content)).await?;
+        let item = self.upload_file(parent_path, file_name, content, overwrite).await?;
+        let duplicate_of = existing.filter(|existing| existing.id != item.id);
+        Ok(KnowledgeUpload { item, duplicate_of })
+    }
+    
+    /// Upload with `private` sealing a new file before it reaches disk. Overwriting keeps
+    /// the privacy of the item being replaced.
+    async fn write_file(&self, parent_path: Option<&Path>, file_name: &str, content: Vec<u8>, overwrite: bool, private: bool) -> Result<KnowledgeItem> {
+        Self::validate_name(file_name)?;
+        let parent = self.resolve_parent(parent_path)?;
+        
+        let target = parent.join(file_name);
+        let file_path = match fs::symlink_metadata(&target) {
+            Ok(metadata) if overwrite && metadata.is_file() => target,
+            Ok(_) if overwrite => {
+                return Err(anyhow!("Cannot overwrite {}: not a regular file", file_name));
+            }
+            _ => Self::unique_path(&parent, file_name, false),
         };
         
-        fs::write(&file_path, &content)?;
+        // Overwriting a private item keeps it encrypted
+        let private = self.index.get_item_by_path(&file_path).await?
+            .map(|existing| existing.private)
+            .unwrap_or(private);
+        let stored = if private { self.cipher()?.seal(&content)? } else { content.clone() };
         
-        let metadata = fs::metadata(&file_path)?;
+        let replaced = fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+        let kept = if file_path.exists() { self.history_growth(replaced).await } else { 0 };
+        self.ensure_capacity(stored.len() as u64 + kept, replaced).await?;
+        
+        let previous = if file_path.exists() { Some(self.read_plain(&file_path)?) } else { None };
         let item_type = Self::determine_file_type(&file_path);
         
         let item = KnowledgeItem {
             id: uuid::Uuid::new_v4().to_string(),
-            name: file_name.to_string(),
+            name: file_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
             item_type: item_type.clone(),
-            size: metadata.len(),
+            size: stored.len() as u64,
             modified: Utc::now(),
             author: "User".to_string(),
             tags: Self::auto_tag(&file_path, &item_type),
             description: None,
             vectorized: false,
             embedding_count: None,
-            private: false,
+            private,
             starred: false,
             path: file_path,
             children: None,
             content: None,
+            provenance: None,
         };
         
+        // Overwriting an existing path keeps its id and metadata. What is about to be
+        // overwritten goes into history first, so a failed write cannot lose it.
+        let (item, _) = self.index.upsert_item(&item).await?;
+        if let Some(previous) = previous {
+            self.record_version(&item, &previous, "overwrite").await?;
+        }
+        
+        fs::write(&item.path, &stored)?;
+        self.track_usage(&item.path, &item_type, replaced, stored.len() as u64).await;
+        self.set_plain_hash(&item, &content).await?;
+        self.index_content(&item).await?;
+        
         let mut cache = self.items_cache.write().await;
         cache.insert(item.id.clone(), item.clone());
         
@@ -288,140 +833,1691 @@ impl KnowledgeManager {
     }
     
     pub async fn delete_item(&self, item_id: &str) -> Result<()> {
-        let mut cache = self.items_cache.write().await;
-        let item = cache.remove(item_id)
-            .ok_or_else(|| anyhow!("Item not found"))?;
+        let item = self.lookup_item(item_id).await?;
+        let path = self.confine_existing(&item.path)?;
         
-        if item.path.exists() {
-            if item.path.is_dir() {
-                fs::remove_dir_all(&item.path)?;
+        if let Ok(metadata) = fs::symlink_metadata(&path) {
+            for (file, size) in Self::files_under(&path) {
+                let item_type = Self::determine_file_type(&file);
+                self.track_usage(&file, &item_type, size, 0).await;
+            }
+            
+            // Symlinks are removed themselves, never followed
+            if metadata.is_dir() {
+                fs::remove_dir_all(&path)?;
             } else {
-                fs::remove_file(&item.path)?;
+                fs::remove_file(&path)?;
             }
         }
         
-        Ok(())
-    }
-    
-    pub async fn update_item_tags(&self, item_id: &str, tags: Vec<String>) -> Result<()> {
-        let mut cache = self.items_cache.write().await;
-        let item = cache.get_mut(item_id)
-            .ok_or_else(|| anyhow!("Item not found"))?;
+        // Drop the item and everything beneath it from the index
+        let mut removed = self.index.remove_path(&item.path).await?;
+        removed.push(item.id.clone());
         
-        item.tags = tags;
-        Ok(())
-    }
-    
-    pub async fn toggle_star(&self, item_id: &str) -> Result<bool> {
         let mut cache = self.items_cache.write().await;
-        let item = cache.get_mut(item_id)
-            .ok_or_else(|| anyhow!("Item not found"))?;
+        for id in &removed {
+            cache.remove(id);
+        }
+        drop(cache);
         
-        item.starred = !item.starred;
-        Ok(item.starred)
+        // History of deleted items goes with them
+        self.versions.gc(&self.index.version_hashes().await?)?;
+        self.remove_vectors(&removed).await
     }
     
-    pub async fn toggle_private(&self, item_id: &str) -> Result<bool> {
-        let mut cache = self.items_cache.write().await;
-        let item = cache.get_mut(item_id)
-            .ok_or_else(|| anyhow!("Item not found"))?;
+    /// Move an item into another folder, keeping its id, metadata and vectors
+    pub async fn move_item(&self, item_id: &str, new_parent: Option<&Path>) -> Result<KnowledgeItem> {
+        let item = self.lookup_item(item_id).await?;
+        let source = self.confine_existing(&item.path)?;
+        let parent = self.resolve_parent(new_parent)?;
+        
+        if parent.starts_with(&source) {
+            return Err(anyhow!("Cannot move a folder into itself"));
+        }
+        if source.parent() == Some(parent.as_path()) {
+            return Ok(item);
+        }
         
-        item.private = !item.private;
-        Ok(item.private)
+        let target = Self::unique_path(&parent, &item.name, item.item_type == ItemType::Folder);
+        self.relocate(&source, &target).await
     }
     
-    pub async fn vectorize_item(&self, item_id: &str) -> Result<()> {
-        let cache = self.items_cache.read().await;
-        let item = cache.get(item_id)
-            .ok_or_else(|| anyhow!("Item not found"))?;
+    /// Rename an item in place, keeping its id, metadata and vectors
+    pub async fn rename_item(&self, item_id: &str, new_name: &str) -> Result<KnowledgeItem> {
+        Self::validate_name(new_name)?;
+        let item = self.lookup_item(item_id).await?;
+        let source = self.confine_existing(&item.path)?;
         
-        if !matches!(item.item_type, ItemType::Document | ItemType::Dataset) {
-            return Err(anyhow!("Can only vectorize documents and datasets"));
+        let target = source.with_file_name(new_name);
+        if target == source {
+            return Ok(item);
+        }
+        if fs::symlink_metadata(&target).is_ok() {
+            return Err(anyhow!("An item named {} already exists", new_name));
         }
         
-        // In a real implementation, this would use an embedding model
-        // For now, we'll create mock embeddings
-        let mock_embeddings: Vec<f32> = (0..768).map(|i| (i as f32 * 0.1).sin()).collect();
+        self.relocate(&source, &target).await
+    }
+    
+    async fn relocate(&self, source: &Path, target: &Path) -> Result<KnowledgeItem> {
+        let files = Self::files_under(source);
         
-        let mut vectorized = self.vectorized_items.write().await;
-        vectorized.insert(item_id.to_string(), mock_embeddings);
+        fs::rename(source, target)?;
+        self.index.move_path(source, target).await?;
         
-        drop(cache);
-        let mut cache = self.items_cache.write().await;
-        if let Some(item) = cache.get_mut(item_id) {
-            item.vectorized = true;
-            item.embedding_count = Some(42); // Mock embedding count
+        for (file, size) in files {
+            let moved = match file.strip_prefix(source) {
+                Ok(relative) if !relative.as_os_str().is_empty() => target.join(relative),
+                _ => target.to_path_buf(),
+            };
+            self.track_usage(&file, &Self::determine_file_type(&file), size, 0).await;
+            self.track_usage(&moved, &Self::determine_file_type(&moved), 0, size).await;
         }
         
-        Ok(())
+        // Forget cached entries under the old path, then rescan both folders
+        self.items_cache.write().await.retain(|_, cached| !cached.path.starts_with(source));
+        self.refresh_folders(source.parent(), target.parent()).await?;
+        
+        self.item_at(target).await
     }
     
-    pub async fn search_similar(&self, item_id: &str, limit: usize) -> Result<Vec<(String, f32)>> {
-        let vectorized = self.vectorized_items.read().await;
-        let query_embedding = vectorized.get(item_id)
-            .ok_or_else(|| anyhow!("Item not vectorized"))?;
+    /// Copy an item (recursively for folders) into another folder. Metadata and extracted
+    /// text are carried over; vectors are copied too unless `reembed` asks for fresh ones.
+    pub async fn copy_item(&self, item_id: &str, new_parent: Option<&Path>, reembed: bool) -> Result<KnowledgeItem> {
+        let item = self.lookup_item(item_id).await?;
+        let source = self.confine_existing(&item.path)?;
+        let parent = self.resolve_parent(new_parent)?;
         
-        // Simple cosine similarity search
-        let mut similarities: Vec<(String, f32)> = Vec::new();
+        if parent.starts_with(&source) {
+            return Err(anyhow!("Cannot copy a folder into itself"));
+        }
         
-        for (other_id, other_embedding) in vectorized.iter() {
-            if other_id == item_id {
-                continue;
+        let total: u64 = Self::files_under(&source).iter().map(|(_, size)| size).sum();
+        self.ensure_capacity(total, 0).await?;
+        
+        let target = Self::unique_path(&parent, &item.name, item.item_type == ItemType::Folder);
+        let mut vector_copies = Vec::new();
+        let mut to_embed = Vec::new();
+        
+        let entries = walkdir::WalkDir::new(&source)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|e| e.ok())
+            .filter(|e| !e.path_is_symlink());
+        
+        for entry in entries {
+            let destination = match entry.path().strip_prefix(&source) {
+                Ok(relative) if !relative.as_os_str().is_empty() => target.join(relative),
+                _ => target.clone(),
+            };
+            
+            if entry.file_type().is_dir() {
+                fs::create_dir(&destination)?;
+            } else {
+                let size = fs::copy(entry.path(), &destination)?;
+                self.track_usage(&destination, &Self::determine_file_type(&destination), 0, size).await;
             }
             
-            let similarity = Self::cosine_similarity(query_embedding, other_embedding);
-            similarities.push((other_id.clone(), similarity));
+            // Entries never scanned have no metadata to carry; the next scan picks them up
+            let original = match self.index.get_item_by_path(entry.path()).await? {
+                Some(original) => original,
+                None => continue,
+            };
+            
+            let metadata = fs::metadata(&destination)?;
+            let copy = KnowledgeItem {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: destination.file_name().unwrap_or_default().to_string_lossy().to_string(),
+                size: metadata.len(),
+                modified: metadata.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now()),
+                path: destination,
+                ..original.clone()
+            };
+            self.index.copy_item(&original.id, &copy, !reembed).await?;
+            
+            if original.vectorized {
+                if reembed {
+                    to_embed.push(copy.id);
+                } else {
+                    vector_copies.push((original.id, copy.id));
+                }
+            }
         }
         
-        similarities.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        similarities.truncate(limit);
+        if !vector_copies.is_empty() {
+            let mut guard = self.ann().await?;
+            if let Some(ann) = guard.as_mut() {
+                for (from, to) in &vector_copies {
+                    for (chunk, vector) in ann.item_vectors(from).into_iter().enumerate() {
+                        ann.insert(to, chunk as u32, vector)?;
+                    }
+                }
+                ann.save(&self.vector_index_path())?;
+            }
+        }
         
-        Ok(similarities)
+        self.refresh_folders(Some(&parent), None).await?;
+        
+        for id in to_embed {
+            if self.may_embed(&self.lookup_item(&id).await?).await {
+                self.vectorize_item(&id).await?;
+            }
+        }
+        
+        self.item_at(&target).await
     }
     
-    fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-        let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-        let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    /// Snapshot replaced content for an item and apply the retention limits
+    async fn record_version(&self, item: &KnowledgeItem, content: &[u8], reason: &str) -> Result<()> {
+        let retention = self.version_retention.read().await.clone();
+        if retention.max_versions == 0 {
+            return Ok(());
+        }
         
-        if norm_a == 0.0 || norm_b == 0.0 {
-            0.0
+        let item_id = item.id.as_str();
+        let stored = if item.private { self.cipher()?.seal(content)? } else { content.to_vec() };
+        let version = KnowledgeVersion {
+            id: uuid::Uuid::new_v4().to_string(),
+            item_id: item_id.to_string(),
+            content_hash: self.versions.put(&stored)?,
+            size: content.len() as u64,
+            created_at: Utc::now(),
+            reason: reason.to_string(),
+        };
+        self.index.add_version(&version).await?;
+        
+        let versions = self.index.list_versions(item_id).await?;
+        let expired = knowledge_versions::expired(&versions, &retention, Utc::now());
+        if !expired.is_empty() {
+            self.index.delete_versions(&expired).await?;
+            self.versions.gc(&self.index.version_hashes().await?)?;
+        }
+        
+        Ok(())
+    }
+    
+    pub async fn list_versions(&self, item_id: &str) -> Result<Vec<KnowledgeVersion>> {
+        self.lookup_item(item_id).await?;
+        self.index.list_versions(item_id).await
+    }
+    
+    /// Content of a version, decrypted if it was stored for a private item
+    fn version_content(&self, version: &KnowledgeVersion) -> Result<Vec<u8>> {
+        let bytes = self.versions.get(&version.content_hash)?;
+        if knowledge_crypto::is_sealed(&bytes) {
+            self.cipher()?.open(&bytes)
         } else {
-            dot_product / (norm_a * norm_b)
+            Ok(bytes)
         }
     }
     
-    pub async fn get_storage_info(&self) -> Result<StorageInfo> {
-        let mut total_size = 0u64;
+    async fn find_version(&self, item_id: &str, version_id: &str) -> Result<KnowledgeVersion> {
+        self.index.list_versions(item_id).await?
+            .into_iter()
+            .find(|v| v.id == version_id)
+            .ok_or_else(|| anyhow!("Version {} not found", version_id))
+    }
+    
+    /// Unified diff between two versions of a text document; `None` means the current file
+    pub async fn diff_versions(&self, item_id: &str, from: Option<&str>, to: Option<&str>) -> Result<String> {
+        let item = self.lookup_item(item_id).await?;
+        let path = self.confine_existing(&item.path)?;
         
-        for entry in walkdir::WalkDir::new(&self.knowledge_dir).into_iter().filter_map(|e| e.ok()) {
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_file() {
-                    total_size += metadata.len();
+        let mut texts = Vec::new();
+        for version_id in [from, to] {
+            let (label, bytes) = match version_id {
+                Some(id) => {
+                    let version = self.find_version(item_id, id).await?;
+                    (format!("{} ({})", item.name, version.created_at.to_rfc3339()), self.version_content(&version)?)
                 }
-            }
+                None => (format!("{} (current)", item.name), self.read_plain(&path)?),
+            };
+            let text = String::from_utf8(bytes)
+                .map_err(|_| anyhow!("Only text documents can be diffed"))?;
+            texts.push((label, text));
         }
         
-        // Mock 10GB total storage
-        let total_bytes = 10 * 1024 * 1024 * 1024;
-        let percentage = (total_size as f32 / total_bytes as f32) * 100.0;
-        
-        Ok(StorageInfo {
-            used_bytes: total_size,
-            total_bytes,
-            percentage,
-        })
+        Ok(knowledge_versions::unified_diff(&texts[0].1, &texts[1].1, &texts[0].0, &texts[1].0))
     }
-}
-
-// Global knowledge manager instance
-use once_cell::sync::Lazy;
-
-static KNOWLEDGE_MANAGER: Lazy<Arc<tokio::sync::Mutex<Option<KnowledgeManager>>>> = 
+    
+    /// Put an earlier version back in place. The content it replaces becomes a version itself.
+    pub async fn restore_version(&self, item_id: &str, version_id: &str) -> Result<KnowledgeItem> {
+        let item = self.lookup_item(item_id).await?;
+        let path = self.confine_existing(&item.path)?;
+        let version = self.find_version(item_id, version_id).await?;
+        let content = self.version_content(&version)?;
+        let stored = if item.private { self.cipher()?.seal(&content)? } else { content.clone() };
+        
+        let current_len = fs::metadata(&path)?.len();
+        let current = self.read_plain(&path)?;
+        let kept = self.history_growth(current_len).await;
+        self.ensure_capacity(stored.len() as u64 + kept, current_len).await?;
+        self.record_version(&item, &current, "restore").await?;
+        
+        fs::write(&path, &stored)?;
+        self.track_usage(&path, &item.item_type, current_len, stored.len() as u64).await;
+        
+        let mut updated = item.clone();
+        updated.size = stored.len() as u64;
+        updated.modified = Utc::now();
+        let (updated, _) = self.index.upsert_item(&updated).await?;
+        self.set_plain_hash(&updated, &content).await?;
+        self.index_content(&updated).await?;
+        self.items_cache.write().await.insert(updated.id.clone(), updated.clone());
+        
+        if updated.vectorized && self.may_embed(&updated).await {
+            self.vectorize_item(item_id).await?;
+        }
+        
+        self.lookup_item(item_id).await
+    }
+    
+    /// Resolve where an import lands, creating the container folder named after the source
+    pub fn prepare_import_target(&self, folder: Option<&Path>, container: Option<&str>) -> Result<PathBuf> {
+        let parent = self.resolve_parent(folder)?;
+        let container = match container {
+            Some(name) => name,
+            None => return Ok(parent),
+        };
+        
+        Self::validate_name(container)?;
+        let target = parent.join(container);
+        match fs::symlink_metadata(&target) {
+            Ok(metadata) if metadata.is_dir() => {}
+            Ok(_) => return Err(anyhow!("{} exists and is not a folder", target.display())),
+            Err(_) => fs::create_dir(&target)?,
+        }
+        
+        Ok(target)
+    }
+    
+    /// Store one imported file below `target_dir`, unless identical bytes are already stored
+    pub async fn import_entry(&self, target_dir: &Path, relative: &Path, content: Vec<u8>, provenance: Provenance) -> Result<ImportOutcome> {
+        let hash = knowledge_dedupe::content_hash(&content);
+        if let Some(existing) = self.index.find_by_hash(&hash).await? {
+            return Ok(ImportOutcome::Duplicate(existing));
+        }
+        
+        let (parent, file_name) = match Self::import_parent(target_dir, relative)? {
+            Ok(split) => split,
+            Err(reason) => return Ok(ImportOutcome::Skipped(reason)),
+        };
+        
+        self.ensure_capacity(content.len() as u64, 0).await?;
+        let file_path = Self::unique_path(&parent, &file_name, false);
+        fs::write(&file_path, &content)?;
+        
+        let metadata = fs::metadata(&file_path)?;
+        let item_type = Self::determine_file_type(&file_path);
+        self.track_usage(&file_path, &item_type, 0, metadata.len()).await;
+        
+        let item = KnowledgeItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: file_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            item_type: item_type.clone(),
+            size: metadata.len(),
+            modified: metadata.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now()),
+            author: "Import".to_string(),
+            tags: Self::auto_tag(&file_path, &item_type),
+            description: None,
+            vectorized: false,
+            embedding_count: None,
+            private: false,
+            starred: false,
+            path: file_path,
+            children: None,
+            content: None,
+            provenance: Some(provenance),
+        };
+        
+        let (item, _) = self.index.upsert_item(&item).await?;
+        self.index.set_content_hash(&item.id, Some(&hash)).await?;
+        self.index_content(&item).await?;
+        self.items_cache.write().await.insert(item.id.clone(), item.clone());
+        
+        Ok(ImportOutcome::Imported(item))
+    }
+    
+    /// Create the folders of an untrusted relative path below `target_dir`. Returns the
+    /// parent folder and file name, or why the path was refused.
+    fn import_parent(target_dir: &Path, relative: &Path) -> Result<std::result::Result<(PathBuf, String), String>> {
+        // Source paths are untrusted: every component must be a plain, visible name
+        let components: Vec<String> = relative.components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        let (file_name, folders) = match components.split_last() {
+            Some(split) => split,
+            None => return Ok(Err("Empty path".to_string())),
+        };
+        for name in &components {
+            if let Err(e) = Self::validate_name(name) {
+                return Ok(Err(e.to_string()));
+            }
+        }
+        
+        let mut parent = target_dir.to_path_buf();
+        for folder in folders {
+            parent.push(folder);
+            match fs::symlink_metadata(&parent) {
+                Ok(metadata) if metadata.is_dir() => {}
+                Ok(_) => return Ok(Err(format!("{} is not a folder", folder))),
+                Err(_) => fs::create_dir(&parent)?,
+            }
+        }
+        
+        Ok(Ok((parent, file_name.clone())))
+    }
+    
+    /// Files under `folder`, or in the whole knowledge base
+    pub async fn list_files(&self, folder: Option<&Path>) -> Result<Vec<KnowledgeItem>> {
+        let root = self.resolve_parent(folder)?;
+        let mut items: Vec<KnowledgeItem> = self.index.all_items().await?
+            .into_iter()
+            .filter(|item| item.path.starts_with(&root) && item.path.is_file())
+            .collect();
+        items.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(items)
+    }
+    
+    /// Path of an item relative to the knowledge root
+    pub fn relative_path(&self, path: &Path) -> Option<PathBuf> {
+        path.strip_prefix(&self.knowledge_dir).ok().map(Path::to_path_buf)
+    }
+    
+    /// The bytes of a file, decrypted if the item is private
+    pub async fn read_item_bytes(&self, item_id: &str) -> Result<Vec<u8>> {
+        let item = self.lookup_item(item_id).await?;
+        let path = self.confine_existing(&item.path)?;
+        self.read_plain(&path)
+    }
+    
+    /// Stored chunk vectors of an item, decrypted if the item is private
+    pub async fn item_embeddings(&self, item_id: &str) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::new();
+        for blob in self.index.embedding_blobs(item_id).await? {
+            let blob = if knowledge_crypto::is_sealed(&blob) { self.cipher()?.open(&blob)? } else { blob };
+            vectors.push(knowledge_index::decode_vector(&blob));
+        }
+        Ok(vectors)
+    }
+    
+    pub async fn embedding_model_id(&self) -> String {
+        self.embedder.read().await.model_id()
+    }
+    
+    /// Write a file from a backup bundle and reapply its metadata. An existing file at
+    /// the same path is skipped, replaced or kept next to the new one, per `conflict`.
+    pub async fn restore_entry(&self, target_dir: &Path, entry: &BundleEntry, content: Vec<u8>, conflict: ConflictPolicy) -> Result<ImportOutcome> {
+        let (parent, file_name) = match Self::import_parent(target_dir, Path::new(&entry.path))? {
+            Ok(split) => split,
+            Err(reason) => return Ok(ImportOutcome::Skipped(reason)),
+        };
+        
+        let overwrite = match (fs::symlink_metadata(parent.join(&file_name)), conflict) {
+            (Err(_), _) => false,
+            (Ok(_), ConflictPolicy::Skip) => return Ok(ImportOutcome::Skipped("Already exists".to_string())),
+            (Ok(metadata), ConflictPolicy::Overwrite) if !metadata.is_file() => {
+                return Ok(ImportOutcome::Skipped("Exists and is not a regular file".to_string()));
+            }
+            (Ok(_), ConflictPolicy::Overwrite) => true,
+            (Ok(_), ConflictPolicy::Rename) => false,
+        };
+        
+        // Private entries are sealed before their content first reaches disk
+        if overwrite && entry.private {
+            if let Some(existing) = self.index.get_item_by_path(&parent.join(&file_name)).await? {
+                self.set_private(&existing.id, true).await?;
+            }
+        }
+        let mut item = self.write_file(Some(&parent), &file_name, content, overwrite, entry.private).await?;
+        item.tags = entry.tags.clone();
+        item.description = entry.description.clone();
+        item.starred = entry.starred;
+        self.index.set_provenance(&item.id, entry.provenance.as_ref()).await?;
+        item.provenance = entry.provenance.clone();
+        
+        // The file keeps its original timestamp so a rescan does not see it as changed
+        fs::File::options().write(true).open(&item.path)?
+            .set_modified(entry.modified.into())?;
+        item.author = entry.author.clone();
+        item.modified = entry.modified;
+        self.index.set_origin(&item.id, &item.author, item.modified).await?;
+        self.store_item(item.clone()).await?;
+        
+        Ok(ImportOutcome::Imported(self.lookup_item(&item.id).await?))
+    }
+    
+    /// Store vectors produced by the same embedding model elsewhere, instead of re-embedding
+    pub async fn restore_embeddings(&self, item_id: &str, vectors: &[Vec<f32>]) -> Result<()> {
+        let mut item = self.lookup_item(item_id).await?;
+        
+        if item.private {
+            let mut blobs = Vec::new();
+            for vector in vectors {
+                blobs.push(self.cipher()?.seal(&knowledge_index::encode_vector(vector))?);
+            }
+            self.index.save_embedding_blobs(item_id, &blobs).await?;
+        } else {
+            self.index.save_embeddings(item_id, vectors).await?;
+            
+            let mut guard = self.ann().await?;
+            if let Some(ann) = guard.as_mut() {
+                ann.remove_item(item_id);
+                for (chunk, vector) in vectors.iter().enumerate() {
+                    ann.insert(item_id, chunk as u32, vector.clone())?;
+                }
+                ann.save(&self.vector_index_path())?;
+            }
+        }
+        
+        item.vectorized = true;
+        item.embedding_count = Some(vectors.len() as u32);
+        self.store_item(item).await
+    }
+    
+    /// Group files with identical bytes, and files whose text has an estimated
+    /// Jaccard similarity of at least `threshold`
+    pub async fn find_duplicates(&self, threshold: f32) -> Result<DuplicateReport> {
+        let fingerprints = self.index.fingerprints().await?;
+        
+        let mut by_hash: HashMap<&str, Vec<&str>> = HashMap::new();
+        for fingerprint in &fingerprints {
+            if let Some(hash) = &fingerprint.content_hash {
+                by_hash.entry(hash).or_default().push(&fingerprint.item_id);
+            }
+        }
+        
+        let mut exact = Vec::new();
+        for ids in by_hash.values().filter(|ids| ids.len() > 1) {
+            let items = self.lookup_items(ids.iter().copied()).await;
+            if items.len() > 1 {
+                exact.push(DuplicateGroup { items, similarity: 1.0 });
+            }
+        }
+        
+        // Identical files are already reported above, so only link files that differ
+        let with_text: Vec<_> = fingerprints.iter().filter(|f| f.minhash.is_some()).collect();
+        let signatures: Vec<Vec<u32>> = with_text.iter().filter_map(|f| f.minhash.clone()).collect();
+        let pairs: Vec<_> = knowledge_dedupe::similar_pairs(&signatures, threshold)
+            .into_iter()
+            .filter(|&(a, b, _)| with_text[a].content_hash.is_none() || with_text[a].content_hash != with_text[b].content_hash)
+            .collect();
+        
+        let mut near = Vec::new();
+        for (members, similarity) in knowledge_dedupe::group_pairs(signatures.len(), &pairs) {
+            let items = self.lookup_items(members.iter().map(|&i| with_text[i].item_id.as_str())).await;
+            if items.len() > 1 {
+                near.push(DuplicateGroup { items, similarity });
+            }
+        }
+        near.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal));
+        
+        Ok(DuplicateReport { exact, near })
+    }
+    
+    async fn lookup_items<'a>(&self, ids: impl Iterator<Item = &'a str>) -> Vec<KnowledgeItem> {
+        let mut items = Vec::new();
+        for id in ids {
+            if let Ok(item) = self.lookup_item(id).await {
+                items.push(item);
+            }
+        }
+        items
+    }
+    
+    /// Keep one item, fold the tags, star and privacy of the others into it, then delete them
+    pub async fn merge_duplicates(&self, keep_id: &str, duplicate_ids: &[String]) -> Result<KnowledgeItem> {
+        let mut keep = self.lookup_item(keep_id).await?;
+        
+        let mut duplicates = Vec::new();
+        for id in duplicate_ids.iter().filter(|id| id.as_str() != keep_id) {
+            duplicates.push(self.lookup_item(id).await?);
+        }
+        
+        for duplicate in &duplicates {
+            for tag in &duplicate.tags {
+                if !keep.tags.contains(tag) {
+                    keep.tags.push(tag.clone());
+                }
+            }
+            keep.starred |= duplicate.starred;
+            if keep.description.is_none() {
+                keep.description = duplicate.description.clone();
+            }
+        }
+        self.store_item(keep.clone()).await?;
+        
+        // Private content must not become readable by merging it into a public copy
+        if !keep.private && duplicates.iter().any(|d| d.private) {
+            self.set_private(keep_id, true).await?;
+        }
+        
+        for duplicate in &duplicates {
+            self.delete_item(&duplicate.id).await?;
+        }
+        
+        self.lookup_item(keep_id).await
+    }
+    
+    /// Rescan folders whose contents changed and update their cached children
+    pub async fn refresh_folders(&self, first: Option<&Path>, second: Option<&Path>) -> Result<()> {
+        let mut folders = Vec::new();
+        folders.extend(first);
+        folders.extend(second.filter(|dir| Some(*dir) != first));
+        
+        // The parent of the root is not ours to scan
+        for dir in folders.into_iter().filter(|dir| dir.starts_with(&self.knowledge_dir)) {
+            let children = self.scan_directory(dir).await?;
+            if let Some(folder) = self.items_cache.write().await.values_mut().find(|i| i.path == dir) {
+                folder.children = Some(children);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn item_at(&self, path: &Path) -> Result<KnowledgeItem> {
+        let stored = self.index.get_item_by_path(path).await?
+            .ok_or_else(|| anyhow!("Item not found at {}", path.display()))?;
+        self.lookup_item(&stored.id).await
+    }
+    
+    /// Reject names that are empty, hidden, reserved, or could address another directory
+    fn validate_name(name: &str) -> Result<()> {
+        if name.is_empty() || name.len() > 255 {
+            return Err(anyhow!("Name must be between 1 and 255 bytes"));
+        }
+        if name.starts_with('.') {
+            return Err(anyhow!("Hidden names are not allowed: {}", name));
+        }
+        if name.ends_with(' ') || name.ends_with('.') || name.starts_with(' ') {
+            return Err(anyhow!("Names cannot start or end with spaces or end with dots"));
+        }
+        if let Some(c) = name.chars().find(|c| c.is_control() || matches!(c, '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*')) {
+            return Err(anyhow!("Invalid character {:?} in name", c));
+        }
+        
+        let stem = name.split('.').next().unwrap_or(name).trim_end();
+        if RESERVED_NAMES.iter().any(|reserved| stem.eq_ignore_ascii_case(reserved)) {
+            return Err(anyhow!("Reserved name: {}", name));
+        }
+        
+        Ok(())
+    }
+    
+    /// Resolve a parent directory, given relative to the knowledge root or as an absolute
+    /// path inside it, and make sure it stays within the root after resolving symlinks
+    fn resolve_parent(&self, parent_path: Option<&Path>) -> Result<PathBuf> {
+        let parent = match parent_path {
+            Some(parent) => self.knowledge_dir.join(parent),
+            None => return Ok(self.knowledge_dir.clone()),
+        };
+        
+        let resolved = parent.canonicalize()
+            .map_err(|_| anyhow!("Parent folder does not exist: {}", parent.display()))?;
+        self.ensure_inside(&resolved, true)?;
+        if !resolved.is_dir() {
+            return Err(anyhow!("Parent is not a folder: {}", parent.display()));
+        }
+        
+        Ok(resolved)
+    }
+    
+    /// Resolve the directory of an existing item while leaving the final component
+    /// untouched, so a symlink inside the knowledge base refers to the link itself
+    fn confine_existing(&self, path: &Path) -> Result<PathBuf> {
+        let (parent, name) = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => (parent, name),
+            _ => return Err(anyhow!("Invalid item path: {}", path.display())),
+        };
+        
+        let parent = parent.canonicalize()
+            .map_err(|_| anyhow!("Item location does not exist: {}", path.display()))?;
+        self.ensure_inside(&parent, true)?;
+        
+        let resolved = parent.join(name);
+        self.ensure_inside(&resolved, false)?;
+        Ok(resolved)
+    }
+    
+    fn ensure_inside(&self, path: &Path, allow_root: bool) -> Result<()> {
+        let relative = path.strip_prefix(&self.knowledge_dir)
+            .map_err(|_| anyhow!("Path is outside the knowledge base: {}", path.display()))?;
+        
+        if relative.as_os_str().is_empty() && !allow_root {
+            return Err(anyhow!("Refusing to operate on the knowledge root"));
+        }
+        if relative.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.')) {
+            return Err(anyhow!("Path is inside a hidden folder: {}", path.display()));
+        }
+        
+        Ok(())
+    }
+    
+    /// First free path for `name` in `dir`, appending " (1)", " (2)", ... before the extension
+    fn unique_path(dir: &Path, name: &str, is_folder: bool) -> PathBuf {
+        let candidate = dir.join(name);
+        if fs::symlink_metadata(&candidate).is_err() {
+            return candidate;
+        }
+        
+        let (stem, extension) = match name.rfind('.') {
+            Some(dot) if !is_folder && dot > 0 => (&name[..dot], &name[dot..]),
+            _ => (name, ""),
+        };
+        
+        (1..)
+            .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+            .find(|path| fs::symlink_metadata(path).is_err())
+            .unwrap_or(candidate)
+    }
+    
+    pub async fn update_item_tags(&self, item_id: &str, tags: Vec<String>) -> Result<()> {
+        let mut item = self.lookup_item(item_id).await?;
+        item.tags = tags;
+        self.store_item(item).await
+    }
+    
+    /// Notified after items are added, removed, moved or have their metadata changed
+    pub fn subscribe_changes(&self) -> tokio::sync::broadcast::Receiver<()> {
+        self.index.subscribe()
+    }
+    
+    /// Ask the enrichment model for a summary and tags. The result stays pending until accepted.
+    pub async fn enrich_item(&self, item_id: &str) -> Result<EnrichmentSuggestion> {
+        let suggestion = self.prepare_enrichment(item_id).await?.run().await?;
+        self.save_suggestion(&suggestion).await?;
+        Ok(suggestion)
+    }
+    
+    /// Collect an item's text for enrichment. The returned job calls the model without
+    /// the manager, and its result is stored by `save_suggestion`.
+    pub async fn prepare_enrichment(&self, item_id: &str) -> Result<EnrichmentJob> {
+        let item = self.lookup_item(item_id).await?;
+        let text = self.extract_text(&item)
+            .filter(|text| !text.trim().is_empty())
+            .ok_or_else(|| anyhow!("No text to summarize in {}", item.name))?;
+        let enricher = self.enricher.read().await.clone();
+        Ok(EnrichmentJob { item, text, enricher })
+    }
+    
+    pub async fn save_suggestion(&self, suggestion: &EnrichmentSuggestion) -> Result<()> {
+        self.index.save_suggestion(suggestion).await
+    }
+    
+    pub async fn enrichment_candidates(&self) -> Result<Vec<String>> {
+        self.index.enrichment_candidates().await
+    }
+    
+    pub async fn list_suggestions(&self) -> Result<Vec<EnrichmentSuggestion>> {
+        self.index.list_suggestions().await
+    }
+    
+    /// Apply a pending suggestion. `tags` narrows the suggested tags to the ones the user kept.
+    pub async fn accept_suggestion(&self, item_id: &str, tags: Option<Vec<String>>, include_description: bool) -> Result<KnowledgeItem> {
+        let mut item = self.lookup_item(item_id).await?;
+        let suggestion = self.index.get_suggestion(item_id).await?
+            .ok_or_else(|| anyhow!("No pending suggestion for {}", item.name))?;
+        
+        let accepted: Vec<String> = match tags {
+            Some(tags) => suggestion.tags.iter().filter(|t| tags.contains(t)).cloned().collect(),
+            None => suggestion.tags.clone(),
+        };
+        for tag in accepted {
+            if !item.tags.contains(&tag) {
+                item.tags.push(tag);
+            }
+        }
+        if include_description {
+            if let Some(description) = suggestion.description {
+                item.description = Some(description);
+            }
+        }
+        
+        self.store_item(item.clone()).await?;
+        self.index.delete_suggestion(item_id).await?;
+        Ok(item)
+    }
+    
+    pub async fn dismiss_suggestion(&self, item_id: &str) -> Result<()> {
+        self.index.dismiss_suggestion(item_id).await
+    }
+    
+    /// Collect what is needed to transcribe an audio or video item. The returned job
+    /// extracts and transcribes the audio without the manager, and its result is stored
+    /// as a sidecar document by `save_transcript`.
+    pub async fn prepare_transcription(&self, item_id: &str) -> Result<TranscriptionJob> {
+        let item = self.lookup_item(item_id).await?;
+        if !matches!(item.item_type, ItemType::Audio | ItemType::Video) {
+            return Err(anyhow!("Can only transcribe audio and video items"));
+        }
+        let path = self.confine_existing(&item.path)?;
+        let decrypted = if item.private { Some(self.read_plain(&path)?) } else { None };
+        
+        Ok(TranscriptionJob {
+            media: item,
+            path,
+            decrypted,
+            transcriber: self.transcriber.read().await.clone(),
+            scratch_dir: self.knowledge_dir.join(INDEX_DIR_NAME).join(SCRATCH_DIR),
+        })
+    }
+    
+    /// Write a transcript as a Markdown document linked to its media item, replacing the
+    /// one from an earlier run. The sidecar inherits the media's privacy and is embedded.
+    pub async fn save_transcript(&self, media: &KnowledgeItem, transcript: &Transcript) -> Result<KnowledgeItem> {
+        let text = knowledge_transcripts::render_transcript(&media.name, transcript);
+        
+        let existing = match self.index.transcript_for(&media.id).await? {
+            Some(record) => self.lookup_item(&record.transcript_id).await.ok()
+                .filter(|sidecar| sidecar.path.is_file()),
+            None => None,
+        };
+        // The sidecar of a private item is sealed before the transcript is first written
+        let sidecar = match existing {
+            Some(sidecar) => {
+                if media.private && !sidecar.private {
+                    self.set_private(&sidecar.id, true).await?;
+                }
+                let parent = sidecar.path.parent().map(Path::to_path_buf);
+                self.upload_file(parent.as_deref(), &sidecar.name, text.into_bytes(), true).await?
+            }
+            None => {
+                let stem = media.path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+                let parent = media.path.parent().map(Path::to_path_buf);
+                let name = format!("{}.transcript.md", stem);
+                self.write_file(parent.as_deref(), &name, text.into_bytes(), false, media.private).await?
+            }
+        };
+        
+        let mut tags = sidecar.tags.clone();
+        if !tags.iter().any(|t| t == "transcript") {
+            tags.push("transcript".to_string());
+            self.update_item_tags(&sidecar.id, tags).await?;
+        }
+        
+        self.index.set_transcript(&TranscriptRecord {
+            media_id: media.id.clone(),
+            transcript_id: sidecar.id.clone(),
+            language: transcript.language.clone(),
+            model: transcript.model.clone(),
+            created_at: Utc::now(),
+        }).await?;
+        
+        // The transcript is kept even if embedding fails; it can be vectorized later
+        let sidecar = self.lookup_item(&sidecar.id).await?;
+        if self.may_embed(&sidecar).await {
+            if let Err(e) = self.vectorize_item(&sidecar.id).await {
+                eprintln!("Failed to vectorize transcript {}: {}", sidecar.name, e);
+            }
+        }
+        
+        self.lookup_item(&sidecar.id).await
+    }
+    
+    pub async fn toggle_star(&self, item_id: &str) -> Result<bool> {
+        let mut item = self.lookup_item(item_id).await?;
+        item.starred = !item.starred;
+        let starred = item.starred;
+        self.store_item(item).await?;
+        Ok(starred)
+    }
+    
+    pub async fn toggle_private(&self, item_id: &str) -> Result<bool> {
+        let private = !self.lookup_item(item_id).await?.private;
+        self.set_private(item_id, private).await?;
+        Ok(private)
+    }
+    
+    /// Encrypt or decrypt an item's file, extracted text, embeddings and version history.
+    /// Marking a folder applies to every file beneath it.
+    pub async fn set_private(&self, item_id: &str, private: bool) -> Result<()> {
+        let item = self.lookup_item(item_id).await?;
+        let path = self.confine_existing(&item.path)?;
+        
+        let mut targets = Vec::new();
+        if item.item_type == ItemType::Folder {
+            for (file, _) in Self::files_under(&path) {
+                if let Some(child) = self.index.get_item_by_path(&file).await? {
+                    targets.push(self.lookup_item(&child.id).await?);
+                }
+            }
+        }
+        targets.push(item);
+        
+        for target in targets {
+            if target.private != private {
+                self.apply_privacy(target, private).await?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn apply_privacy(&self, mut item: KnowledgeItem, private: bool) -> Result<()> {
+        item.private = private;
+        
+        if item.item_type != ItemType::Folder {
+            let path = self.confine_existing(&item.path)?;
+            let current = fs::read(&path)?;
+            let updated = match (private, knowledge_crypto::is_sealed(&current)) {
+                (true, false) => self.cipher()?.seal(&current)?,
+                (false, true) => self.cipher()?.open(&current)?,
+                _ => current.clone(),
+            };
+            
+            // Write beside the original and swap, so a crash never leaves half a file
+            let tmp_path = path.with_file_name(format!(".{}.tmp", item.name));
+            fs::write(&tmp_path, &updated)?;
+            fs::rename(&tmp_path, &path)?;
+            self.track_usage(&path, &item.item_type, current.len() as u64, updated.len() as u64).await;
+            
+            let metadata = fs::metadata(&path)?;
+            item.size = metadata.len();
+            item.modified = metadata.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+            self.index.upsert_item(&item).await?;
+            self.set_plain_hash(&item, &updated).await?;
+            
+            self.reseal_versions(&item, private).await?;
+        }
+        
+        // The flag is persisted first so an ANN rebuild never reads sealed vectors
+        self.store_item(item.clone()).await?;
+        self.reseal_embeddings(&item.id, private).await?;
+        self.index_content(&item).await?;
+        
+        if private {
+            self.index.purge_deleted_text().await?;
+        }
+        Ok(())
+    }
+    
+    /// Store the duplicate-detection hash, which sealed items must not carry
+    async fn set_plain_hash(&self, item: &KnowledgeItem, content: &[u8]) -> Result<()> {
+        let hash = (!item.private).then(|| knowledge_dedupe::content_hash(content));
+        self.index.set_content_hash(&item.id, hash.as_deref()).await
+    }
+    
+    async fn reseal_versions(&self, item: &KnowledgeItem, private: bool) -> Result<()> {
+        let versions = self.index.list_versions(&item.id).await?;
+        if versions.is_empty() {
+            return Ok(());
+        }
+        
+        for version in versions {
+            let content = self.version_content(&version)?;
+            let stored = if private { self.cipher()?.seal(&content)? } else { content };
+            let hash = self.versions.put(&stored)?;
+            self.index.set_version_hash(&version.id, &hash).await?;
+        }
+        
+        self.versions.gc(&self.index.version_hashes().await?)
+    }
+    
+    /// Seal stored vectors and drop them from the ANN index, or the reverse
+    async fn reseal_embeddings(&self, item_id: &str, private: bool) -> Result<()> {
+        let blobs = self.index.embedding_blobs(item_id).await?;
+        if blobs.is_empty() {
+            return Ok(());
+        }
+        
+        let mut updated = Vec::new();
+        for blob in blobs {
+            let sealed = knowledge_crypto::is_sealed(&blob);
+            updated.push(match (private, sealed) {
+                (true, false) => self.cipher()?.seal(&blob)?,
+                (false, true) => self.cipher()?.open(&blob)?,
+                _ => blob,
+            });
+        }
+        self.index.save_embedding_blobs(item_id, &updated).await?;
+        
+        if private {
+            // Tombstoned nodes are still written to disk, so rebuild the graph without them
+            let mut guard = self.ann().await?;
+            if let Some(ann) = guard.as_mut() {
+                ann.remove_item(item_id);
+                if ann.has_tombstones() {
+                    ann.compact()?;
+                    ann.save(&self.vector_index_path())?;
+                }
+            }
+            return Ok(());
+        }
+        
+        let mut guard = self.ann().await?;
+        if let Some(ann) = guard.as_mut() {
+            ann.remove_item(item_id);
+            for (chunk, blob) in updated.iter().enumerate() {
+                ann.insert(item_id, chunk as u32, knowledge_index::decode_vector(blob))?;
+            }
+            ann.save(&self.vector_index_path())?;
+        }
+        Ok(())
+    }
+    
+    async fn embedder(&self) -> Embedder {
+        self.embedder.read().await.clone()
+    }
+    
+    /// Private content may only be embedded by a provider that keeps it on this machine
+    async fn may_embed(&self, item: &KnowledgeItem) -> bool {
+        !item.private || self.embedder.read().await.is_local()
+    }
+    
+    pub async fn vectorize_item(&self, item_id: &str) -> Result<()> {
+        let job = self.prepare_vectorize(item_id).await?;
+        let vectors = job.run().await?;
+        self.save_vectors(job, vectors).await
+    }
+    
+    /// Extract and chunk an item's text. The returned job embeds it without the manager,
+    /// and its vectors are stored by `save_vectors`.
+    async fn prepare_vectorize(&self, item_id: &str) -> Result<VectorizeJob> {
+        let item = self.lookup_item(item_id).await?;
+        
+        if !matches!(item.item_type, ItemType::Document | ItemType::Dataset) {
+            return Err(anyhow!("Can only vectorize documents and datasets"));
+        }
+        
+        if !self.may_embed(&item).await {
+            return Err(anyhow!("Private items can only be vectorized with a local embedding model"));
+        }
+        
+        self.confine_existing(&item.path)?;
+        let text = self.extract_text(&item)
+            .ok_or_else(|| anyhow!("No indexable text in {}", item.name))?;
+        let chunks = embeddings::chunk_text(&text, CHUNK_CHARS, CHUNK_OVERLAP);
+        if chunks.is_empty() {
+            return Err(anyhow!("Item has no text to vectorize"));
+        }
+        
+        let embedder = self.embedder().await;
+        Ok(VectorizeJob { item, text, chunks, embedder })
+    }
+    
+    async fn save_vectors(&self, job: VectorizeJob, vectors: Vec<Vec<f32>>) -> Result<()> {
+        // The job ran unlocked, so the model or the item may have changed meanwhile
+        if job.embedder.model_id() != self.embedder.read().await.model_id() {
+            return Err(anyhow!("Embedding model changed while vectorizing {}", job.item.name));
+        }
+        let item_id = job.item.id.as_str();
+        let mut item = self.lookup_item(item_id).await?;
+        let text = job.text;
+        
+        if item.private {
+            // Sealed vectors stay out of the ANN index until the item is made public again
+            let mut blobs = Vec::new();
+            for vector in &vectors {
+                blobs.push(self.cipher()?.seal(&knowledge_index::encode_vector(vector))?);
+            }
+            self.index.save_embedding_blobs(item_id, &blobs).await?;
+            self.index_content(&item).await?;
+        } else {
+            self.index.save_embeddings(item_id, &vectors).await?;
+            self.index.set_content(&item, &text).await?;
+            
+            let mut guard = self.ann().await?;
+            if let Some(ann) = guard.as_mut() {
+                ann.remove_item(item_id);
+                for (chunk, vector) in vectors.iter().enumerate() {
+                    ann.insert(item_id, chunk as u32, vector.clone())?;
+                }
+                if ann.should_compact() {
+                    ann.compact()?;
+                }
+                ann.save(&self.vector_index_path())?;
+            }
+        }
+        
+        item.vectorized = true;
+        item.embedding_count = Some(vectors.len() as u32);
+        self.store_item(item).await
+    }
+    
+    fn to_link(link: StoredLink) -> KnowledgeLink {
+        KnowledgeLink {
+            source_id: link.source_id,
+            kind: link.kind,
+            target: link.target,
+            target_id: link.target_id,
+        }
+    }
+    
+    /// Links and hashtags found in an item's text, with their targets resolved
+    pub async fn outgoing_links(&self, item_id: &str) -> Result<Vec<KnowledgeLink>> {
+        self.lookup_item(item_id).await?;
+        Ok(self.index.links(Some(item_id)).await?.into_iter().map(Self::to_link).collect())
+    }
+    
+    /// Links from other documents that resolve to this item
+    pub async fn backlinks(&self, item_id: &str) -> Result<Vec<KnowledgeLink>> {
+        self.lookup_item(item_id).await?;
+        Ok(self.index.links_to(item_id).await?.into_iter().map(Self::to_link).collect())
+    }
+    
+    /// Document links whose target is not in the knowledge base
+    pub async fn broken_links(&self) -> Result<Vec<KnowledgeLink>> {
+        Ok(self.index.unresolved_links().await?.into_iter().map(Self::to_link).collect())
+    }
+    
+    /// Items within `depth` links of an item (either direction), plus their tags
+    pub async fn knowledge_graph(&self, item_id: &str, depth: usize) -> Result<KnowledgeGraph> {
+        let depth = depth.min(knowledge_links::MAX_GRAPH_DEPTH);
+        let root = self.lookup_item(item_id).await?;
+        
+        let mut nodes = vec![GraphNode {
+            id: root.id.clone(),
+            label: root.name.clone(),
+            item: Some(root.clone()),
+            depth: 0,
+        }];
+        let mut seen_nodes: HashSet<String> = [root.id.clone()].into_iter().collect();
+        let mut edges = Vec::new();
+        let mut seen_edges = HashSet::new();
+        
+        let mut frontier = vec![root];
+        for level in 0..=depth {
+            let mut next = Vec::new();
+            for item in frontier {
+                let mut neighbours: Vec<(String, GraphEdge)> = Vec::new();
+                for link in self.outgoing_links(&item.id).await? {
+                    if let Some(target) = link.target_id {
+                        neighbours.push((target.clone(), GraphEdge { source: item.id.clone(), target, kind: link.kind }));
+                    }
+                }
+                for link in self.backlinks(&item.id).await? {
+                    neighbours.push((link.source_id.clone(), GraphEdge { source: link.source_id, target: item.id.clone(), kind: link.kind }));
+                }
+                
+                for (other, edge) in neighbours {
+                    // The outermost ring only gets edges between nodes already in the graph
+                    if !seen_nodes.contains(&other) {
+                        if level == depth {
+                            continue;
+                        }
+                        let other_item = match self.lookup_item(&other).await {
+                            Ok(other_item) => other_item,
+                            Err(_) => continue,
+                        };
+                        seen_nodes.insert(other.clone());
+                        nodes.push(GraphNode {
+                            id: other.clone(),
+                            label: other_item.name.clone(),
+                            item: Some(other_item.clone()),
+                            depth: level + 1,
+                        });
+                        next.push(other_item);
+                    }
+                    if seen_edges.insert((edge.source.clone(), edge.target.clone(), edge.kind)) {
+                        edges.push(edge);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        
+        // Tags are leaves: metadata tags and hashtags from the text
+        let items: Vec<(String, usize)> = nodes.iter().map(|n| (n.id.clone(), n.depth)).collect();
+        for (id, item_depth) in items {
+            let mut tags: Vec<String> = self.lookup_item(&id).await?.tags.iter().map(|t| t.to_lowercase()).collect();
+            tags.extend(self.index.links(Some(&id)).await?.into_iter().filter(|l| l.kind == LinkKind::Tag).map(|l| l.target));
+            
+            for tag in tags {
+                let tag_id = format!("tag:{}", tag);
+                if seen_nodes.insert(tag_id.clone()) {
+                    nodes.push(GraphNode { id: tag_id.clone(), label: tag, item: None, depth: item_depth + 1 });
+                }
+                if seen_edges.insert((id.clone(), tag_id.clone(), LinkKind::Tag)) {
+                    edges.push(GraphEdge { source: id.clone(), target: tag_id, kind: LinkKind::Tag });
+                }
+            }
+        }
+        
+        Ok(KnowledgeGraph { nodes, edges })
+    }
+    
+    fn mean_vector(vectors: &[Vec<f32>]) -> Option<Vec<f32>> {
+        let dim = vectors.first()?.len();
+        let mut mean = vec![0f32; dim];
+        for vector in vectors.iter().filter(|v| v.len() == dim) {
+            for (m, v) in mean.iter_mut().zip(vector) {
+                *m += v;
+            }
+        }
+        embeddings::normalize(&mut mean);
+        Some(mean)
+    }
+    
+    /// Free-text search combining BM25 keyword ranking with vector similarity
+    /// through reciprocal rank fusion.
+    pub async fn search(&self, query: &str, filters: &KnowledgeSearchFilters, limit: usize) -> Result<Vec<KnowledgeSearchResult>> {
+        let query_vector = Self::embed_query(&self.embedder().await, query).await;
+        self.search_with_vector(query, query_vector.as_deref(), filters, limit).await
+    }
+    
+    /// Embed a search query. Without an embedder (e.g. Ollama not running) there is no
+    /// vector and the search falls back to keywords only.
+    async fn embed_query(embedder: &Embedder, query: &str) -> Option<Vec<f32>> {
+        if Self::unique_terms(query).is_empty() {
+            return None;
+        }
+        match embedder.embed(&[query.to_string()]).await {
+            Ok(vectors) => vectors.into_iter().next(),
+            Err(e) => {
+                eprintln!("Query embedding failed, searching by keyword only: {}", e);
+                None
+            }
+        }
+    }
+    
+    async fn search_with_vector(
+        &self,
+        query: &str,
+        query_vector: Option<&[f32]>,
+        filters: &KnowledgeSearchFilters,
+        limit: usize,
+    ) -> Result<Vec<KnowledgeSearchResult>> {
+        let terms = Self::unique_terms(query);
+        if terms.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        
+        // Keyword leg: any term may match, BM25 rewards documents matching more of them
+        let match_expr = terms.iter()
+            .map(|t| format!("\"{}\"", t))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        
+        // Filters apply before ranking, and the candidate pool widens until each leg has
+        // `limit` matching items or has nothing more to give
+        let mut candidates = (limit * 4).max(50);
+        let (keyword_hits, vector_hits, mut items) = loop {
+            let keyword_hits = self.index.keyword_search(&match_expr, candidates).await?;
+            let keyword_done = keyword_hits.len() < candidates;
+            
+            let (vector_hits, vector_done) = match query_vector {
+                Some(query_vector) => {
+                    let guard = self.ann_read().await?;
+                    let hits = guard.as_ref()
+                        .map(|ann| ann.search(query_vector, candidates * CHUNKS_PER_ITEM))
+                        .unwrap_or_default();
+                    let exhausted = hits.len() < candidates * CHUNKS_PER_ITEM;
+                    let hits = Self::best_per_item(hits);
+                    let done = exhausted || hits.iter().any(|(_, similarity)| *similarity <= 0.0);
+                    (hits, done)
+                }
+                None => (Vec::new(), true),
+            };
+            
+            let mut items: HashMap<String, KnowledgeItem> = HashMap::new();
+            let ids = keyword_hits.iter().map(|hit| &hit.item_id).chain(vector_hits.iter().map(|(id, _)| id));
+            for item_id in ids {
+                if items.contains_key(item_id) {
+                    continue;
+                }
+                if let Ok(item) = self.lookup_item(item_id).await {
+                    if self.matches_filters(&item, filters) {
+                        items.insert(item_id.clone(), item);
+                    }
+                }
+            }
+            let keyword_hits: Vec<_> = keyword_hits.into_iter()
+                .filter(|hit| items.contains_key(&hit.item_id))
+                .collect();
+            let vector_hits: Vec<_> = vector_hits.into_iter()
+                .filter(|(id, similarity)| *similarity > 0.0 && items.contains_key(id))
+                .collect();
+            
+            let enough = |found: usize, done: bool| done || found >= limit;
+            if enough(keyword_hits.len(), keyword_done) && enough(vector_hits.len(), vector_done) {
+                break (keyword_hits, vector_hits, items);
+            }
+            candidates *= 4;
+        };
+        
+        // Reciprocal rank fusion: (score, keyword rank, vector rank) per item
+        let mut fused: HashMap<String, (f32, Option<usize>, Option<usize>)> = HashMap::new();
+        let mut snippets: HashMap<String, String> = HashMap::new();
+        
+        for (rank, hit) in keyword_hits.into_iter().enumerate() {
+            if !hit.snippet.is_empty() {
+                snippets.insert(hit.item_id.clone(), hit.snippet);
+            }
+            let entry = fused.entry(hit.item_id).or_insert((0.0, None, None));
+            entry.0 += 1.0 / (RRF_K + rank as f32 + 1.0);
+            entry.1 = Some(rank + 1);
+        }
+        
+        for (rank, (item_id, _)) in vector_hits.into_iter().enumerate() {
+            let entry = fused.entry(item_id).or_insert((0.0, None, None));
+            entry.0 += 1.0 / (RRF_K + rank as f32 + 1.0);
+            entry.2 = Some(rank + 1);
+        }
+        
+        let mut results = Vec::new();
+        for (item_id, (score, keyword_rank, vector_rank)) in fused {
+            let Some(item) = items.remove(&item_id) else { continue };
+            
+            let snippet = match snippets.remove(&item_id) {
+                Some(snippet) => Some(snippet),
+                None => self.index.get_content(&item_id).await?
+                    .and_then(|content| highlight_snippet(&content, &terms, 24)),
+            };
+            
+            // Transcript hits point at the moment in the recording that matched
+            let transcript_of = self.index.transcript_source(&item_id).await?;
+            let timestamp = match transcript_of {
+                Some(_) => self.index.get_content(&item_id).await?
+                    .and_then(|content| knowledge_transcripts::locate(&content, &terms)),
+                None => None,
+            };
+            
+            results.push(KnowledgeSearchResult {
+                item,
+                score,
+                keyword_rank,
+                vector_rank,
+                snippet,
+                transcript_of,
+                timestamp,
+            });
+        }
+        
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(limit);
+        
+        Ok(results)
+    }
+    
+    fn unique_terms(query: &str) -> Vec<String> {
+        let mut terms = Vec::new();
+        for token in embeddings::tokenize(query) {
+            if !terms.contains(&token) {
+                terms.push(token);
+            }
+        }
+        terms
+    }
+    
+    fn matches_filters(&self, item: &KnowledgeItem, filters: &KnowledgeSearchFilters) -> bool {
+        if !filters.tags.iter().all(|tag| item.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))) {
+            return false;
+        }
+        if !filters.item_types.is_empty() && !filters.item_types.contains(&item.item_type) {
+            return false;
+        }
+        if let Some(folder) = &filters.folder {
+            let folder_path = self.knowledge_dir.join(folder);
+            if !item.path.starts_with(&folder_path) {
+                return false;
+            }
+        }
+        if let Some(starred) = filters.starred {
+            if item.starred != starred {
+                return false;
+            }
+        }
+        if let Some(after) = filters.modified_after {
+            if item.modified < after {
+                return false;
+            }
+        }
+        if let Some(before) = filters.modified_before {
+            if item.modified > before {
+                return false;
+            }
+        }
+        true
+    }
+    
+    pub async fn search_similar(&self, item_id: &str, limit: usize) -> Result<Vec<(String, f32)>> {
+        let guard = self.ann_read().await?;
+        let ann = guard.as_ref().ok_or_else(|| anyhow!("Vector index unavailable"))?;
+        
+        let query_embedding = Self::mean_vector(&ann.item_vectors(item_id))
+            .ok_or_else(|| anyhow!("Item not vectorized"))?;
+        
+        let hits = ann.search(&query_embedding, (limit + 1) * CHUNKS_PER_ITEM);
+        let mut similarities = Self::best_per_item(hits);
+        similarities.retain(|(other_id, _)| other_id != item_id);
+        similarities.truncate(limit);
+        
+        Ok(similarities)
+    }
+    
+    /// Collapse chunk hits to one score per item (its best chunk), most similar first
+    fn best_per_item(hits: Vec<VectorHit>) -> Vec<(String, f32)> {
+        let mut best: HashMap<String, f32> = HashMap::new();
+        for hit in hits {
+            let score = best.entry(hit.item_id).or_insert(f32::MIN);
+            *score = score.max(hit.similarity);
+        }
+        
+        let mut ranked: Vec<(String, f32)> = best.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranked
+    }
+    
+    /// Save a query as a collection. The query is checked before it is stored.
+    pub async fn create_collection(&self, name: &str, query: &str) -> Result<Collection> {
+        if name.trim().is_empty() {
+            return Err(anyhow!("Collection name cannot be empty"));
+        }
+        knowledge_collections::parse_query(query)?;
+        
+        let now = Utc::now();
+        let collection = Collection {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.trim().to_string(),
+            query: query.trim().to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        self.index.save_collection(&collection).await?;
+        Ok(collection)
+    }
+    
+    pub async fn update_collection(&self, collection_id: &str, name: Option<&str>, query: Option<&str>) -> Result<Collection> {
+        let mut collection = self.index.get_collection(collection_id).await?
+            .ok_or_else(|| anyhow!("Collection not found"))?;
+        
+        if let Some(name) = name {
+            if name.trim().is_empty() {
+                return Err(anyhow!("Collection name cannot be empty"));
+            }
+            collection.name = name.trim().to_string();
+        }
+        if let Some(query) = query {
+            knowledge_collections::parse_query(query)?;
+            collection.query = query.trim().to_string();
+        }
+        collection.updated_at = Utc::now();
+        
+        self.index.save_collection(&collection).await?;
+        Ok(collection)
+    }
+    
+    pub async fn delete_collection(&self, collection_id: &str) -> Result<()> {
+        if !self.index.delete_collection(collection_id).await? {
+            return Err(anyhow!("Collection not found"));
+        }
+        Ok(())
+    }
+    
+    pub async fn list_collections(&self) -> Result<Vec<Collection>> {
+        self.index.list_collections().await
+    }
+    
+    /// Items currently matching a collection's query
+    pub async fn list_collection_items(&self, collection_id: &str) -> Result<Vec<KnowledgeItem>> {
+        let query = self.collection_query(collection_id).await?;
+        self.query_items(&query).await
+    }
+    
+    async fn collection_query(&self, collection_id: &str) -> Result<String> {
+        let collection = self.index.get_collection(collection_id).await?
+            .ok_or_else(|| anyhow!("Collection not found"))?;
+        Ok(collection.query)
+    }
+    
+    /// Evaluate a collection query against the stored metadata. Semantic matches come
+    /// first, most similar first; otherwise the most recently modified come first.
+    pub async fn query_items(&self, query: &str) -> Result<Vec<KnowledgeItem>> {
+        let vectors = Self::embed_semantic_queries(&self.embedder().await, query).await?;
+        self.evaluate_query(query, &vectors).await
+    }
+    
+    /// Embed the text of every similar: term in a collection query
+    async fn embed_semantic_queries(embedder: &Embedder, query: &str) -> Result<HashMap<String, Vec<f32>>> {
+        let expr = knowledge_collections::parse_query(query)?;
+        let mut vectors = HashMap::new();
+        for (text, _) in expr.semantic_queries() {
+            let vector = embedder.embed(std::slice::from_ref(&text)).await?
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            vectors.insert(text, vector);
+        }
+        Ok(vectors)
+    }
+    
+    async fn evaluate_query(&self, query: &str, vectors: &HashMap<String, Vec<f32>>) -> Result<Vec<KnowledgeItem>> {
+        let expr = knowledge_collections::parse_query(query)?;
+        
+        let mut similarity = HashMap::new();
+        for (text, threshold) in expr.semantic_queries() {
+            let Some(vector) = vectors.get(&text) else { continue };
+            
+            // Thresholds need every chunk above them, not just the nearest few
+            let guard = self.ann_read().await?;
+            let hits = guard.as_ref()
+                .map(|ann| ann.search_within(vector, threshold))
+                .unwrap_or_default();
+            similarity.insert(text, Self::best_per_item(hits).into_iter().collect::<HashMap<_, _>>());
+        }
+        
+        let context = EvalContext {
+            now: Utc::now(),
+            root: &self.knowledge_dir,
+            similarity,
+        };
+        
+        let mut items: Vec<KnowledgeItem> = self.index.all_items().await?
+            .into_iter()
+            .filter(|item| expr.matches(item, &context))
+            .collect();
+        
+        let best = |item: &KnowledgeItem| context.similarity.values()
+            .filter_map(|scores| scores.get(&item.id).copied())
+            .fold(f32::MIN, f32::max);
+        items.sort_by(|a, b| {
+            best(b).partial_cmp(&best(a))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.modified.cmp(&a.modified))
+        });
+        
+        Ok(items)
+    }
+    
+    pub async fn get_storage_info(&self) -> Result<StorageInfo> {
+        let totals = self.usage_totals().await;
+        let index_bytes = self.index_bytes();
+        let versions_bytes = self.versions.size();
+        let used_bytes = totals.files_bytes + index_bytes + versions_bytes;
+        
+        let disk_free_bytes = fs2::available_space(&self.knowledge_dir)?;
+        let disk_total_bytes = fs2::total_space(&self.knowledge_dir)?;
+        let quota_bytes = *self.quota_bytes.read().await;
+        
+        let capacity = used_bytes + disk_free_bytes;
+        let total_bytes = quota_bytes.map(|q| q.min(capacity)).unwrap_or(capacity);
+        let percentage = if total_bytes == 0 {
+            0.0
+        } else {
+            (used_bytes as f32 / total_bytes as f32) * 100.0
+        };
+        
+        Ok(StorageInfo {
+            used_bytes,
+            total_bytes,
+            percentage,
+            quota_bytes,
+            disk_free_bytes,
+            disk_total_bytes,
+            index_bytes,
+            vector_index_bytes: [self.vector_index_path(), vector_index::log_path(&self.vector_index_path())]
+                .iter()
+                .map(|path| fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+                .sum(),
+            text_cache_bytes: self.index.text_bytes().await?,
+            versions_bytes,
+            by_folder: totals.by_folder,
+            by_type: totals.by_type,
+        })
+    }
+    
+    /// Bytes version history grows by when `replaced` bytes are overwritten
+    async fn history_growth(&self, replaced: u64) -> u64 {
+        match self.version_retention.read().await.max_versions {
+            0 => 0,
+            _ => replaced,
+        }
+    }
+    
+    /// Refuse writes that would exceed the quota or the free space left on the volume
+    async fn ensure_capacity(&self, incoming: u64, replaced: u64) -> Result<()> {
+        let growth = incoming.saturating_sub(replaced);
+        if growth == 0 {
+            return Ok(());
+        }
+        
+        let available = fs2::available_space(&self.knowledge_dir)?;
+        if growth > available {
+            return Err(anyhow!("Not enough disk space: {} bytes needed, {} available", growth, available));
+        }
+        
+        if let Some(quota) = *self.quota_bytes.read().await {
+            let used = self.usage_totals().await.files_bytes + self.index_bytes() + self.versions.size();
+            if used + growth > quota {
+                return Err(anyhow!(
+                    "Storage quota exceeded: {} of {} bytes used, {} more needed",
+                    used, quota, growth
+                ));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Current usage totals, walking the knowledge directory only the first time
+    async fn usage_totals(&self) -> UsageTotals {
+        let mut usage = self.usage.write().await;
+        if usage.is_none() {
+            let mut totals = UsageTotals::default();
+            for (path, size) in Self::files_under(&self.knowledge_dir) {
+                let item_type = Self::determine_file_type(&path);
+                totals.add(self.folder_key(&path), item_type, size);
+            }
+            *usage = Some(totals);
+        }
+        usage.clone().unwrap_or_default()
+    }
+    
+    /// Adjust cached totals after a file of `removed` bytes was replaced by `added` bytes
+    async fn track_usage(&self, path: &Path, item_type: &ItemType, removed: u64, added: u64) {
+        if let Some(totals) = self.usage.write().await.as_mut() {
+            totals.subtract(self.folder_key(path), item_type.clone(), removed);
+            totals.add(self.folder_key(path), item_type.clone(), added);
+        }
+    }
+    
+    fn collect_usage(&self, items: &[KnowledgeItem], totals: &mut UsageTotals) {
+        for item in items {
+            match &item.children {
+                Some(children) => self.collect_usage(children, totals),
+                None if item.item_type != ItemType::Folder => {
+                    totals.add(self.folder_key(&item.path), item.item_type.clone(), item.size);
+                }
+                None => {}
+            }
+        }
+    }
+    
+    /// Top-level folder an item is accounted under; files at the root use "/"
+    fn folder_key(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.knowledge_dir).unwrap_or(path);
+        let mut components = relative.components();
+        match (components.next(), components.next()) {
+            (Some(first), Some(_)) => first.as_os_str().to_string_lossy().to_string(),
+            _ => "/".to_string(),
+        }
+    }
+    
+    /// Sizes of all visible files at or below `path`
+    fn files_under(path: &Path) -> Vec<(PathBuf, u64)> {
+        walkdir::WalkDir::new(path)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let metadata = e.metadata().ok()?;
+                metadata.is_file().then(|| (e.into_path(), metadata.len()))
+            })
+            .collect()
+    }
+    
+    fn index_bytes(&self) -> u64 {
+        walkdir::WalkDir::new(self.knowledge_dir.join(INDEX_DIR_NAME))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum()
+    }
+}
+
+/// Build a short excerpt around the first occurrence of any term, wrapping matches in `<mark>`
+fn highlight_snippet(content: &str, terms: &[String], max_words: usize) -> Option<String> {
+    let mut spans = Vec::new();
+    let mut word_start = None;
+    for (index, c) in content.char_indices() {
+        match (c.is_alphanumeric(), word_start) {
+            (true, None) => word_start = Some(index),
+            (false, Some(start)) => {
+                spans.push((start, index));
+                word_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = word_start {
+        spans.push((start, content.len()));
+    }
+    
+    let is_match = |&(start, end): &(usize, usize)| terms.contains(&content[start..end].to_lowercase());
+    let first = spans.iter().position(is_match)?;
+    let from = first.saturating_sub(max_words / 3);
+    let to = (from + max_words).min(spans.len());
+    
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    for (i, span) in spans[from..to].iter().enumerate() {
+        if i > 0 {
+            snippet.push_str(&content[spans[from + i - 1].1..span.0]);
+        }
+        let word = &content[span.0..span.1];
+        if is_match(span) {
+            snippet.push_str("<mark>");
+            snippet.push_str(word);
+            snippet.push_str("</mark>");
+        } else {
+            snippet.push_str(word);
+        }
+    }
+    if to < spans.len() {
+        snippet.push('…');
+    }
+    
+    Some(snippet)
+}
+
+// Global knowledge manager instance
+use once_cell::sync::Lazy;
+
+/// The manager behind the lock that serializes knowledge base access
+pub type SharedKnowledgeManager = tokio::sync::Mutex<Option<KnowledgeManager>>;
+
+static KNOWLEDGE_MANAGER: Lazy<Arc<SharedKnowledgeManager>> = 
     Lazy::new(|| Arc::new(tokio::sync::Mutex::new(None)));
 
 pub async fn initialize_knowledge_manager(knowledge_dir: PathBuf) -> Result<()> {
     let manager = KnowledgeManager::new(knowledge_dir)?;
+    manager.record_embedding_model().await?;
+    manager.backfill_links().await?;
     *KNOWLEDGE_MANAGER.lock().await = Some(manager);
     Ok(())
 }
@@ -435,4 +2531,349 @@ where
         Some(manager) => f(manager).await,
         None => Err(anyhow!("Knowledge manager not initialized")),
     }
-}
\ No newline at end of file
+}
+
+/// Call `on_change` after items are added, removed or changed, once per burst of changes
+pub async fn watch_changes<F: Fn()>(on_change: F) -> Result<()> {
+    use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+    
+    let mut changes = with_knowledge_manager(|manager| {
+        Box::pin(async move { Ok(manager.subscribe_changes()) })
+    }).await?;
+    
+    loop {
+        if let Err(RecvError::Closed) = changes.recv().await {
+            return Ok(());
+        }
+        // Imports and refreshes change many items at once
+        tokio::time::sleep(CHANGE_DEBOUNCE).await;
+        while !matches!(changes.try_recv(), Err(TryRecvError::Empty | TryRecvError::Closed)) {}
+        on_change();
+    }
+}
+
+/// Search holding the manager only around the index lookups, not while the query is embedded
+pub async fn search(manager: &SharedKnowledgeManager, query: &str, filters: &KnowledgeSearchFilters, limit: usize) -> Result<Vec<KnowledgeSearchResult>> {
+    let embedder = lock_manager(manager).await?.embedder().await;
+    let query_vector = KnowledgeManager::embed_query(&embedder, query).await;
+    lock_manager(manager).await?.search_with_vector(query, query_vector.as_deref(), filters, limit).await
+}
+
+/// Evaluate a collection query, embedding its similar: terms without holding the manager
+pub async fn query_items(manager: &SharedKnowledgeManager, query: &str) -> Result<Vec<KnowledgeItem>> {
+    let embedder = lock_manager(manager).await?.embedder().await;
+    let vectors = KnowledgeManager::embed_semantic_queries(&embedder, query).await?;
+    lock_manager(manager).await?.evaluate_query(query, &vectors).await
+}
+
+pub async fn list_collection_items(manager: &SharedKnowledgeManager, collection_id: &str) -> Result<Vec<KnowledgeItem>> {
+    let query = lock_manager(manager).await?.collection_query(collection_id).await?;
+    query_items(manager, &query).await
+}
+
+/// Vectorize an item, holding the manager only to read the item and store its vectors
+pub async fn vectorize_item(manager: &SharedKnowledgeManager, item_id: &str) -> Result<()> {
+    let job = lock_manager(manager).await?.prepare_vectorize(item_id).await?;
+    let vectors = job.run().await?;
+    lock_manager(manager).await?.save_vectors(job, vectors).await
+}
+
+/// The global manager, for jobs that take the lock once per step with `lock_manager`
+pub fn shared_knowledge_manager() -> &'static SharedKnowledgeManager {
+    &KNOWLEDGE_MANAGER
+}
+
+/// Lock the manager for one step of a long job. Drop the guard before any slow work
+/// (model calls, reading sources) so other commands are not blocked meanwhile.
+pub async fn lock_manager(shared: &SharedKnowledgeManager) -> Result<tokio::sync::MappedMutexGuard<'_, KnowledgeManager>> {
+    tokio::sync::MutexGuard::try_map(shared.lock().await, |manager| manager.as_mut())
+        .map_err(|_| anyhow!("Knowledge manager not initialized"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_hybrid_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+        
+        let rust = manager.upload_file(Some(&docs), "rust.md", b"Rust ownership and borrowing rules keep memory safe".to_vec(), false).await.unwrap();
+        let soup = manager.upload_file(Some(&docs), "soup.md", b"A recipe for tomato soup with fresh basil".to_vec(), false).await.unwrap();
+        manager.vectorize_item(&rust.id).await.unwrap();
+        manager.vectorize_item(&soup.id).await.unwrap();
+        
+        let results = manager.search("memory ownership", &KnowledgeSearchFilters::default(), 10).await.unwrap();
+        assert_eq!(results[0].item.id, rust.id);
+        assert_eq!(results[0].keyword_rank, Some(1));
+        assert!(results[0].snippet.as_deref().unwrap().contains("<mark>"));
+        
+        let starred_only = KnowledgeSearchFilters { starred: Some(true), ..Default::default() };
+        assert!(manager.search("memory ownership", &starred_only, 10).await.unwrap().is_empty());
+        
+        manager.toggle_star(&rust.id).await.unwrap();
+        let results = manager.search("memory ownership", &starred_only, 10).await.unwrap();
+        assert_eq!(results.len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_search_without_embedder_uses_keywords() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+        
+        let rust = manager.upload_file(Some(&docs), "rust.md", b"Rust ownership and borrowing rules keep memory safe".to_vec(), false).await.unwrap();
+        manager.vectorize_item(&rust.id).await.unwrap();
+        
+        // A model no Ollama server has, so embedding fails whether or not one is running
+        *manager.embedder.write().await = Embedder::new(EmbeddingProvider::Ollama, Some("localbrain-missing-model".to_string()), None);
+        let results = manager.search("memory ownership", &KnowledgeSearchFilters::default(), 10).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].item.id, rust.id);
+        assert_eq!(results[0].vector_rank, None);
+    }
+    
+    #[tokio::test]
+    async fn test_shared_manager_embeds_outside_the_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let docs = dir.path().join("documents");
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let rust = manager.upload_file(Some(&docs), "rust.md", b"Rust ownership and borrowing rules keep memory safe".to_vec(), false).await.unwrap();
+        let shared: SharedKnowledgeManager = tokio::sync::Mutex::new(Some(manager));
+        
+        vectorize_item(&shared, &rust.id).await.unwrap();
+        assert!(lock_manager(&shared).await.unwrap().lookup_item(&rust.id).await.unwrap().vectorized);
+        
+        let results = search(&shared, "memory ownership", &KnowledgeSearchFilters::default(), 10).await.unwrap();
+        assert_eq!(results[0].item.id, rust.id);
+        assert!(results[0].vector_rank.is_some());
+        
+        let items = query_items(&shared, "similar:\"memory ownership\">0.1").await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert!(shared.try_lock().is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_filters_apply_before_the_candidate_cut() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+        
+        // Sixty better keyword matches push the starred note past the first candidate pool
+        for i in 0..60 {
+            manager.upload_file(Some(&docs), &format!("draft {}.md", i), b"Budget budget budget review".to_vec(), false).await.unwrap();
+        }
+        let note = manager.upload_file(Some(&docs), "note.md", b"Budget review for the offsite, plus travel, catering and venue notes".to_vec(), false).await.unwrap();
+        manager.toggle_star(&note.id).await.unwrap();
+        
+        let starred_only = KnowledgeSearchFilters { starred: Some(true), ..Default::default() };
+        let results = manager.search("budget", &starred_only, 5).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].item.id, note.id);
+        assert_eq!(results[0].keyword_rank, Some(1));
+    }
+    
+    #[tokio::test]
+    async fn test_private_items_leave_no_plaintext() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+        let content = b"The zebrafish regenerates its heart after injury".to_vec();
+        
+        let secret = manager.upload_file(Some(&docs), "secret.md", content.clone(), false).await.unwrap();
+        let other = manager.upload_file(Some(&docs), "other.md", b"Tomato soup with basil".to_vec(), false).await.unwrap();
+        manager.vectorize_item(&secret.id).await.unwrap();
+        manager.vectorize_item(&other.id).await.unwrap();
+        
+        manager.set_private(&secret.id, true).await.unwrap();
+        
+        let vectors = fs::read(manager.vector_index_path()).unwrap();
+        assert!(!vectors.windows(secret.id.len()).any(|w| w == secret.id.as_bytes()));
+        let log = fs::read(vector_index::log_path(&manager.vector_index_path())).unwrap_or_default();
+        assert!(!log.windows(secret.id.len()).any(|w| w == secret.id.as_bytes()));
+        let db = fs::read(dir.path().join(INDEX_DIR_NAME).join("knowledge.db")).unwrap();
+        assert!(!db.windows(9).any(|w| w == b"zebrafish"));
+        let hash = knowledge_dedupe::content_hash(&content);
+        assert!(manager.index.find_by_hash(&hash).await.unwrap().is_none());
+        
+        manager.set_private(&secret.id, false).await.unwrap();
+        assert_eq!(manager.index.find_by_hash(&hash).await.unwrap().unwrap().id, secret.id);
+    }
+    
+    #[tokio::test]
+    async fn test_vector_index_is_checked_against_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let docs = dir.path().join("documents");
+        let (first, second) = {
+            let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+            let first = manager.upload_file(Some(&docs), "first.md", b"Sourdough needs a lively starter".to_vec(), false).await.unwrap();
+            let second = manager.upload_file(Some(&docs), "second.md", b"Comets have icy nuclei".to_vec(), false).await.unwrap();
+            manager.vectorize_item(&first.id).await.unwrap();
+            
+            // Roll the index files back as if the app crashed before saving them
+            let path = manager.vector_index_path();
+            let log = vector_index::log_path(&path);
+            let saved = (fs::read(&path).unwrap(), fs::read(&log).ok());
+            manager.vectorize_item(&second.id).await.unwrap();
+            fs::write(&path, saved.0).unwrap();
+            match saved.1 {
+                Some(bytes) => fs::write(&log, bytes).unwrap(),
+                None => { let _ = fs::remove_file(&log); }
+            }
+            (first, second)
+        };
+        
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let guard = manager.ann_read().await.unwrap();
+        let ann = guard.as_ref().unwrap();
+        assert!(ann.contains_item(&first.id));
+        assert!(ann.contains_item(&second.id));
+    }
+    
+    #[tokio::test]
+    async fn test_storage_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+        
+        manager.upload_file(Some(&docs), "notes.md", vec![b'a'; 2000], false).await.unwrap();
+        let info = manager.get_storage_info().await.unwrap();
+        assert_eq!(info.by_folder["documents"], 2000);
+        assert_eq!(info.by_type[&ItemType::Document], 2000);
+        assert!(info.index_bytes > 0);
+        
+        let settings = KnowledgeSettings { quota_bytes: Some(info.used_bytes + 50_000), ..Default::default() };
+        manager.configure(settings, None).await.unwrap();
+        
+        manager.upload_file(Some(&docs), "small.csv", vec![b'b'; 1000], false).await.unwrap();
+        let err = manager.upload_file(Some(&docs), "large.md", vec![b'c'; 100_000], false).await.unwrap_err();
+        assert!(err.to_string().contains("quota"));
+        assert!(!docs.join("large.md").exists());
+        
+        let info = manager.get_storage_info().await.unwrap();
+        assert_eq!(info.by_folder["documents"], 3000);
+        assert_eq!(info.by_type[&ItemType::Dataset], 1000);
+    }
+    
+    #[tokio::test]
+    async fn test_overwrite_history_counts_toward_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+        
+        manager.upload_file(Some(&docs), "draft.md", vec![b'a'; 30_000], false).await.unwrap();
+        let used = manager.get_storage_info().await.unwrap().used_bytes;
+        let settings = KnowledgeSettings { quota_bytes: Some(used + 10_000), ..Default::default() };
+        manager.configure(settings.clone(), None).await.unwrap();
+        
+        // The old draft moves into history, so replacing it needs room for both
+        let err = manager.upload_file(Some(&docs), "draft.md", vec![b'b'; 30_000], true).await.unwrap_err();
+        assert!(err.to_string().contains("quota"));
+        assert_eq!(fs::read(docs.join("draft.md")).unwrap(), vec![b'a'; 30_000]);
+        
+        let retention = VersionRetention { max_versions: 0, ..Default::default() };
+        manager.configure(KnowledgeSettings { version_retention: retention, ..settings }, None).await.unwrap();
+        manager.upload_file(Some(&docs), "draft.md", vec![b'b'; 30_000], true).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_traversal_is_rejected() {
+        let root = tempfile::tempdir().unwrap();
+        let knowledge_dir = root.path().join("knowledge");
+        let manager = KnowledgeManager::new(knowledge_dir.clone()).unwrap();
+        let docs = knowledge_dir.join("documents");
+        
+        // Names that would address another directory
+        for name in ["../escape.txt", "..", "a/b.txt", "a\\b.txt", ".ssh", "CON", "lpt1.txt", "bad\0name"] {
+            assert!(manager.upload_file(None, name, b"x".to_vec(), false).await.is_err(), "accepted {:?}", name);
+            assert!(manager.create_folder(None, name).await.is_err(), "accepted {:?}", name);
+        }
+        
+        // Parents outside the root or inside the hidden index directory
+        let outside = root.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        for parent in [Path::new("../outside"), Path::new("documents/../../outside"), outside.as_path(), Path::new(".index")] {
+            assert!(manager.upload_file(Some(parent), "x.txt", b"x".to_vec(), false).await.is_err(), "accepted {:?}", parent);
+            assert!(manager.create_folder(Some(parent), "x").await.is_err(), "accepted {:?}", parent);
+        }
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 0);
+        
+        // A symlink inside the knowledge base that points outside it
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&outside, docs.join("link")).unwrap();
+            assert!(manager.upload_file(Some(&docs.join("link")), "x.txt", b"x".to_vec(), false).await.is_err());
+            assert_eq!(fs::read_dir(&outside).unwrap().count(), 0);
+        }
+        
+        // A tampered item path must not let delete or read escape
+        let victim = outside.join("victim.txt");
+        fs::write(&victim, b"keep me").unwrap();
+        let mut item = manager.upload_file(Some(&docs), "note.md", b"x".to_vec(), false).await.unwrap();
+        item.path = victim.clone();
+        manager.items_cache.write().await.insert(item.id.clone(), item.clone());
+        assert!(manager.delete_item(&item.id).await.is_err());
+        assert!(manager.read_item_content(&item.id).await.is_err());
+        assert!(manager.vectorize_item(&item.id).await.is_err());
+        assert!(manager.extract_text(&item).is_none());
+        assert!(victim.exists());
+    }
+    
+    #[tokio::test]
+    async fn test_name_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+        
+        let first = manager.upload_file(Some(&docs), "report.md", b"one".to_vec(), false).await.unwrap();
+        let second = manager.upload_file(Some(&docs), "report.md", b"two".to_vec(), false).await.unwrap();
+        assert_eq!(second.name, "report (1).md");
+        assert_ne!(first.id, second.id);
+        
+        let replaced = manager.upload_file(Some(&docs), "report.md", b"three".to_vec(), true).await.unwrap();
+        assert_eq!(replaced.id, first.id);
+        assert_eq!(fs::read_to_string(docs.join("report.md")).unwrap(), "three");
+        
+        let folder = manager.create_folder(None, "documents").await.unwrap();
+        assert_eq!(folder.name, "documents (1)");
+    }
+    
+    #[tokio::test]
+    async fn test_move_rename_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+        
+        let project = manager.create_folder(Some(&docs), "project").await.unwrap();
+        let note = manager.upload_file(Some(&project.path), "plan.md", b"Quarterly roadmap for the storage engine".to_vec(), false).await.unwrap();
+        manager.update_item_tags(&note.id, vec!["roadmap".to_string()]).await.unwrap();
+        manager.vectorize_item(&note.id).await.unwrap();
+        
+        // Moving a folder carries its children along, ids intact
+        let moved = manager.move_item(&project.id, Some(Path::new("datasets"))).await.unwrap();
+        assert_eq!(moved.id, project.id);
+        assert_eq!(moved.children.as_ref().unwrap().len(), 1);
+        let note_moved = manager.get_item(&note.id).await.unwrap();
+        assert_eq!(note_moved.path, dir.path().canonicalize().unwrap().join("datasets/project/plan.md"));
+        assert_eq!(note_moved.tags, vec!["roadmap".to_string()]);
+        assert!(manager.move_item(&project.id, Some(Path::new("datasets/project"))).await.is_err());
+        
+        let renamed = manager.rename_item(&note.id, "roadmap.md").await.unwrap();
+        assert_eq!(renamed.id, note.id);
+        assert!(renamed.vectorized);
+        assert!(manager.rename_item(&note.id, "../roadmap.md").await.is_err());
+        
+        let results = manager.search("storage roadmap", &KnowledgeSearchFilters::default(), 5).await.unwrap();
+        assert_eq!(results[0].item.id, note.id);
+        
+        // Copies get fresh ids but keep tags and vectors
+        let copy = manager.copy_item(&note.id, None, false).await.unwrap();
+        assert_ne!(copy.id, note.id);
+        assert_eq!(copy.tags, vec!["roadmap".to_string()]);
+        assert!(copy.vectorized);
+        let similar = manager.search_similar(&note.id, 1).await.unwrap();
+        assert_eq!(similar[0].0, copy.id);
+    }
+}
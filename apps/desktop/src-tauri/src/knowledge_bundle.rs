@@ -0,0 +1,667 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path};
+
+use crate::knowledge::{lock_manager, ItemType, KnowledgeItem, SharedKnowledgeManager};
+use crate::knowledge_collections::Collection;
+use crate::knowledge_dedupe;
+use crate::knowledge_import::{self, ImportOutcome, ImportSkip, Provenance, MAX_ARCHIVE_BYTES, MAX_IMPORT_FILE_BYTES};
+use crate::knowledge_index;
+
+/// Bumped when the bundle layout changes in a way older readers cannot handle
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+const FILES_DIR: &str = "files";
+const VECTORS_DIR: &str = "vectors";
+// Members queued for the archive writer before export waits for it to catch up
+const SINK_QUEUE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BundleFormat {
+    Zip,
+    TarGz,
+}
+
+impl BundleFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(BundleFormat::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(BundleFormat::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    pub folder: Option<String>, // Relative to the knowledge root; the whole base when unset
+    pub include_vectors: bool,
+    pub include_private: bool,  // Private files are written decrypted, so this is opt-in
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    Skip,      // Keep what is already there
+    Overwrite, // Replace it, keeping the old content in version history
+    #[default]
+    Rename,    // Keep both; the restored file gets a numbered name
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BundleImportOptions {
+    pub target_folder: Option<String>, // Relative to the knowledge root
+    pub conflict: ConflictPolicy,
+    pub import_vectors: bool, // Used only when the bundle was embedded with the current model
+}
+
+impl Default for BundleImportOptions {
+    fn default() -> Self {
+        Self {
+            target_folder: None,
+            conflict: ConflictPolicy::default(),
+            import_vectors: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleVectors {
+    pub file: String,
+    pub chunks: u32,
+    pub dimensions: u32,
+    pub sha256: String,
+}
+
+/// One file in a bundle with everything needed to restore its metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleEntry {
+    pub path: String, // Relative to the knowledge root, '/'-separated
+    #[serde(rename = "type")]
+    pub item_type: ItemType,
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+    pub author: String,
+    pub tags: Vec<String>,
+    pub description: Option<String>,
+    pub starred: bool,
+    pub private: bool,
+    pub provenance: Option<Provenance>,
+    pub sha256: String,
+    pub vectors: Option<BundleVectors>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub embedding_model: Option<String>, // Set when vectors are included
+    pub items: Vec<BundleEntry>,
+    #[serde(default)]
+    pub collections: Vec<Collection>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub current: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub items: usize,
+    pub vectors: usize, // Items exported with their embeddings
+    pub collections: usize,
+    pub skipped: Vec<ImportSkip>,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleVerification {
+    pub valid: bool,
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub items: usize,
+    pub missing: Vec<String>,   // Listed in the manifest but absent from the archive
+    pub corrupted: Vec<String>, // Present, but the checksum does not match
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleImportSummary {
+    pub imported: Vec<KnowledgeItem>,
+    pub skipped: Vec<ImportSkip>,
+    pub vectors_restored: usize,
+    pub collections_restored: usize,
+}
+
+enum BundleWriter {
+    Zip(zip::ZipWriter<fs::File>),
+    TarGz(tar::Builder<flate2::write::GzEncoder<fs::File>>),
+}
+
+impl BundleWriter {
+    fn create(path: &Path, format: BundleFormat) -> Result<Self> {
+        let file = fs::File::create(path)?;
+        Ok(match format {
+            BundleFormat::Zip => BundleWriter::Zip(zip::ZipWriter::new(file)),
+            BundleFormat::TarGz => BundleWriter::TarGz(tar::Builder::new(
+                flate2::write::GzEncoder::new(file, flate2::Compression::default()),
+            )),
+        })
+    }
+
+    fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        match self {
+            BundleWriter::Zip(writer) => {
+                let options = zip::write::SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated);
+                writer.start_file(name, options)?;
+                writer.write_all(data)?;
+            }
+            BundleWriter::TarGz(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(Utc::now().timestamp().max(0) as u64);
+                header.set_cksum();
+                builder.append_data(&mut header, name, data)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            BundleWriter::Zip(writer) => {
+                writer.finish()?;
+            }
+            BundleWriter::TarGz(builder) => {
+                builder.into_inner()?.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compresses members on a blocking thread so export never stalls the async runtime
+struct BundleSink {
+    members: tokio::sync::mpsc::Sender<(String, Vec<u8>)>,
+    task: tokio::task::JoinHandle<Result<()>>,
+}
+
+impl BundleSink {
+    fn start(path: &Path, format: BundleFormat) -> Result<Self> {
+        let mut writer = BundleWriter::create(path, format)?;
+        let (members, mut queue) = tokio::sync::mpsc::channel::<(String, Vec<u8>)>(SINK_QUEUE);
+        let task = tokio::task::spawn_blocking(move || {
+            while let Some((name, data)) = queue.blocking_recv() {
+                writer.add(&name, &data)?;
+            }
+            writer.finish()
+        });
+        Ok(Self { members, task })
+    }
+
+    async fn add(&self, name: String, data: Vec<u8>) -> Result<()> {
+        // A closed queue means the writer failed; `finish` reports why
+        self.members.send((name, data)).await
+            .map_err(|_| anyhow!("Bundle writer stopped"))
+    }
+
+    async fn finish(self) -> Result<()> {
+        drop(self.members);
+        self.task.await?
+    }
+}
+
+/// Write files, metadata, collections and optionally vectors to a .zip or .tar.gz bundle.
+/// The archive is written next to `destination` and moved into place once complete. The
+/// manager is locked once per item, so other commands keep working during an export.
+pub async fn export_bundle<F>(manager: &SharedKnowledgeManager, destination: &Path, options: &ExportOptions, on_progress: F) -> Result<ExportSummary>
+where
+    F: Fn(&BundleProgress) + Send + Sync,
+{
+    let format = BundleFormat::from_path(destination)
+        .ok_or_else(|| anyhow!("Bundle must be a .zip or .tar.gz file"))?;
+    let file_name = destination.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let partial = destination.with_file_name(format!("{}.partial", file_name));
+
+    let sink = BundleSink::start(&partial, format)?;
+    let written = write_bundle(manager, &sink, options, &on_progress).await;
+    let result = match (written, sink.finish().await) {
+        (Ok(summary), Ok(())) => Ok(summary),
+        (_, Err(e)) | (Err(e), Ok(())) => Err(e),
+    };
+
+    let mut summary = match result {
+        Ok(summary) => summary,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    fs::rename(&partial, destination)?;
+    summary.path = destination.to_string_lossy().to_string();
+    summary.bytes = fs::metadata(destination)?.len();
+    Ok(summary)
+}
+
+async fn write_bundle<F>(manager: &SharedKnowledgeManager, sink: &BundleSink, options: &ExportOptions, on_progress: &F) -> Result<ExportSummary>
+where
+    F: Fn(&BundleProgress) + Send + Sync,
+{
+    let (items, collections, embedding_model) = {
+        let manager = lock_manager(manager).await?;
+        let items = manager.list_files(options.folder.as_deref().map(Path::new)).await?;
+        let embedding_model = match options.include_vectors {
+            true => Some(manager.embedding_model_id().await),
+            false => None,
+        };
+        (items, manager.list_collections().await?, embedding_model)
+    };
+    let mut manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        embedding_model,
+        items: Vec::new(),
+        collections,
+    };
+    let mut summary = ExportSummary {
+        path: String::new(),
+        items: 0,
+        vectors: 0,
+        collections: manifest.collections.len(),
+        skipped: Vec::new(),
+        bytes: 0,
+    };
+    let mut progress = BundleProgress {
+        processed: 0,
+        total: items.len(),
+        current: String::new(),
+    };
+    // Restore reads members through the same limits, so never write what it would refuse
+    let mut written = 0u64;
+
+    for item in items {
+        let path = match lock_manager(manager).await?.relative_path(&item.path) {
+            Some(relative) => bundle_path(&relative),
+            None => continue,
+        };
+        progress.current = path.clone();
+        on_progress(&progress);
+        progress.processed += 1;
+
+        if item.private && !options.include_private {
+            summary.skipped.push(ImportSkip { path, reason: "Private item".to_string() });
+            continue;
+        }
+        if item.size > MAX_IMPORT_FILE_BYTES {
+            summary.skipped.push(ImportSkip { path, reason: "Too large for a bundle".to_string() });
+            continue;
+        }
+
+        let (content, embeddings) = {
+            let manager = lock_manager(manager).await?;
+            let content = manager.read_item_bytes(&item.id).await?;
+            let embeddings = match options.include_vectors && item.vectorized {
+                true => manager.item_embeddings(&item.id).await?,
+                false => Vec::new(),
+            };
+            (content, embeddings)
+        };
+
+        if written + content.len() as u64 > MAX_ARCHIVE_BYTES {
+            summary.skipped.push(ImportSkip { path, reason: "Bundle size limit reached".to_string() });
+            continue;
+        }
+        let sha256 = knowledge_dedupe::content_hash(&content);
+        let size = content.len() as u64;
+        sink.add(format!("{}/{}", FILES_DIR, path), content).await?;
+        written += size;
+
+        let mut vectors = None;
+        if !embeddings.is_empty() {
+            let data: Vec<u8> = embeddings.iter().flat_map(|v| knowledge_index::encode_vector(v)).collect();
+            if let (Some(first), true) = (embeddings.first(), written + data.len() as u64 <= MAX_ARCHIVE_BYTES) {
+                let file = format!("{}/{}.f32", VECTORS_DIR, manifest.items.len());
+                let vectors_sha256 = knowledge_dedupe::content_hash(&data);
+                written += data.len() as u64;
+                sink.add(file.clone(), data).await?;
+                vectors = Some(BundleVectors {
+                    file,
+                    chunks: embeddings.len() as u32,
+                    dimensions: first.len() as u32,
+                    sha256: vectors_sha256,
+                });
+                summary.vectors += 1;
+            }
+        }
+
+        manifest.items.push(BundleEntry {
+            path,
+            item_type: item.item_type,
+            size,
+            modified: item.modified,
+            author: item.author,
+            tags: item.tags,
+            description: item.description,
+            starred: item.starred,
+            private: item.private,
+            provenance: item.provenance,
+            sha256,
+            vectors,
+        });
+        summary.items += 1;
+    }
+
+    let manifest = serde_json::to_vec_pretty(&manifest)?;
+    if written + manifest.len() as u64 > MAX_ARCHIVE_BYTES {
+        return Err(anyhow!("Bundle would exceed {} bytes; export a smaller folder", MAX_ARCHIVE_BYTES));
+    }
+    sink.add(MANIFEST_NAME.to_string(), manifest).await?;
+    progress.current.clear();
+    on_progress(&progress);
+    Ok(summary)
+}
+
+fn bundle_path(relative: &Path) -> String {
+    relative.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Manifest and members of a bundle, read into memory
+fn read_bundle(path: &Path) -> Result<(BundleManifest, HashMap<String, Vec<u8>>)> {
+    let format = BundleFormat::from_path(path)
+        .ok_or_else(|| anyhow!("Bundle must be a .zip or .tar.gz file"))?;
+    let mut members = HashMap::new();
+    let mut total = 0u64;
+
+    match format {
+        BundleFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(fs::File::open(path)?)?;
+            for index in 0..archive.len() {
+                let file = archive.by_index(index)?;
+                if file.is_dir() || file.is_symlink() {
+                    continue;
+                }
+                let name = match file.enclosed_name() {
+                    Some(name) => bundle_path(&name),
+                    None => continue,
+                };
+                if let Some(bytes) = knowledge_import::read_limited(file, &mut total)? {
+                    members.insert(name, bytes);
+                }
+            }
+        }
+        BundleFormat::TarGz => {
+            let decoder = flate2::read::GzDecoder::new(fs::File::open(path)?);
+            let mut archive = tar::Archive::new(decoder);
+            for entry in archive.entries()? {
+                let entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = bundle_path(&entry.path()?);
+                if let Some(bytes) = knowledge_import::read_limited(entry, &mut total)? {
+                    members.insert(name, bytes);
+                }
+            }
+        }
+    }
+
+    let manifest = members.remove(MANIFEST_NAME)
+        .ok_or_else(|| anyhow!("Not a knowledge bundle: {} is missing", MANIFEST_NAME))?;
+    let manifest: BundleManifest = serde_json::from_slice(&manifest)?;
+    Ok((manifest, members))
+}
+
+fn check_members(manifest: &BundleManifest, members: &HashMap<String, Vec<u8>>) -> BundleVerification {
+    let mut verification = BundleVerification {
+        valid: true,
+        format_version: manifest.format_version,
+        exported_at: manifest.exported_at,
+        items: manifest.items.len(),
+        missing: Vec::new(),
+        corrupted: Vec::new(),
+    };
+
+    let mut expect = |name: String, sha256: &str| match members.get(&name) {
+        None => verification.missing.push(name),
+        Some(bytes) if knowledge_dedupe::content_hash(bytes) != sha256 => verification.corrupted.push(name),
+        Some(_) => {}
+    };
+    for entry in &manifest.items {
+        expect(format!("{}/{}", FILES_DIR, entry.path), &entry.sha256);
+        if let Some(vectors) = &entry.vectors {
+            expect(vectors.file.clone(), &vectors.sha256);
+        }
+    }
+
+    verification.valid = verification.missing.is_empty() && verification.corrupted.is_empty();
+    verification
+}
+
+/// Check every file and vector set in a bundle against the checksums in its manifest
+pub fn verify_bundle(path: &Path) -> Result<BundleVerification> {
+    let (manifest, members) = read_bundle(path)?;
+    Ok(check_members(&manifest, &members))
+}
+
+/// Restore a bundle written by `export_bundle`. Nothing is written unless every
+/// checksum matches. The archive is read and checked without the manager, which is then
+/// locked once per entry.
+pub async fn import_bundle<F>(manager: &SharedKnowledgeManager, source: &Path, options: &BundleImportOptions, on_progress: F) -> Result<BundleImportSummary>
+where
+    F: Fn(&BundleProgress) + Send + Sync,
+{
+    let source = source.to_path_buf();
+    let (manifest, mut members, verification) = tokio::task::spawn_blocking(move || {
+        let (manifest, members) = read_bundle(&source)?;
+        let verification = check_members(&manifest, &members);
+        anyhow::Ok((manifest, members, verification))
+    }).await??;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(anyhow!("Bundle format {} is newer than this version of LocalBrain supports", manifest.format_version));
+    }
+
+    if !verification.valid {
+        return Err(anyhow!(
+            "Bundle failed verification: {} missing and {} corrupted files",
+            verification.missing.len(),
+            verification.corrupted.len()
+        ));
+    }
+
+    let (target, model_id) = {
+        let manager = lock_manager(manager).await?;
+        let target = manager.prepare_import_target(options.target_folder.as_deref().map(Path::new), None)?;
+        (target, manager.embedding_model_id().await)
+    };
+    let restore_vectors = options.import_vectors && manifest.embedding_model.as_deref() == Some(model_id.as_str());
+
+    let mut summary = BundleImportSummary {
+        imported: Vec::new(),
+        skipped: Vec::new(),
+        vectors_restored: 0,
+        collections_restored: 0,
+    };
+    let mut progress = BundleProgress {
+        processed: 0,
+        total: manifest.items.len(),
+        current: String::new(),
+    };
+
+    for entry in &manifest.items {
+        progress.current = entry.path.clone();
+        on_progress(&progress);
+        progress.processed += 1;
+
+        let content = members.remove(&format!("{}/{}", FILES_DIR, entry.path)).unwrap_or_default();
+        let manager = lock_manager(manager).await?;
+        let item = match manager.restore_entry(&target, entry, content, options.conflict).await? {
+            ImportOutcome::Imported(item) | ImportOutcome::Duplicate(item) => item,
+            ImportOutcome::Skipped(reason) => {
+                summary.skipped.push(ImportSkip { path: entry.path.clone(), reason });
+                continue;
+            }
+        };
+
+        if let (true, Some(vectors)) = (restore_vectors, &entry.vectors) {
+            let data = members.remove(&vectors.file).unwrap_or_default();
+            let decoded = knowledge_index::decode_vector(&data);
+            let dimensions = vectors.dimensions as usize;
+            if dimensions > 0 && decoded.len() == dimensions * vectors.chunks as usize {
+                let chunks: Vec<Vec<f32>> = decoded.chunks(dimensions).map(|c| c.to_vec()).collect();
+                manager.restore_embeddings(&item.id, &chunks).await?;
+                summary.vectors_restored += 1;
+            }
+        }
+
+        summary.imported.push(manager.get_item(&item.id).await.unwrap_or(item));
+    }
+
+    {
+        // Collections are matched by name so importing twice does not duplicate them
+        let manager = lock_manager(manager).await?;
+        let existing: Vec<String> = manager.list_collections().await?.into_iter().map(|c| c.name).collect();
+        for collection in &manifest.collections {
+            if !existing.contains(&collection.name)
+                && manager.create_collection(&collection.name, &collection.query).await.is_ok()
+            {
+                summary.collections_restored += 1;
+            }
+        }
+        manager.refresh_folders(Some(&target), target.parent()).await?;
+    }
+
+    progress.current.clear();
+    on_progress(&progress);
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::KnowledgeManager;
+
+    #[tokio::test]
+    async fn test_export_and_restore_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = KnowledgeManager::new(dir.path().join("source")).unwrap();
+        let docs = dir.path().join("source/documents");
+
+        let design = source.upload_file(Some(&docs), "design.md", b"Service boundaries and queues".to_vec(), false).await.unwrap();
+        source.update_item_tags(&design.id, vec!["architecture".to_string()]).await.unwrap();
+        source.toggle_star(&design.id).await.unwrap();
+        source.vectorize_item(&design.id).await.unwrap();
+        let secret = source.upload_file(Some(&docs), "secret.md", b"Private notes".to_vec(), false).await.unwrap();
+        source.toggle_private(&secret.id).await.unwrap();
+        source.create_collection("Starred", "starred").await.unwrap();
+        let source = SharedKnowledgeManager::new(Some(source));
+
+        for name in ["backup.zip", "backup.tar.gz"] {
+            let bundle = dir.path().join(name);
+            let options = ExportOptions { include_vectors: true, ..Default::default() };
+            let summary = export_bundle(&source, &bundle, &options, |_| {
+                // Other commands can use the knowledge base between items
+                assert!(source.try_lock().is_ok());
+            }).await.unwrap();
+            assert_eq!((summary.items, summary.vectors, summary.skipped.len()), (1, 1, 1));
+            assert!(verify_bundle(&bundle).unwrap().valid);
+        }
+
+        let target = SharedKnowledgeManager::new(Some(KnowledgeManager::new(dir.path().join("target")).unwrap()));
+        let bundle = dir.path().join("backup.zip");
+        let summary = import_bundle(&target, &bundle, &BundleImportOptions::default(), |_| {
+            assert!(target.try_lock().is_ok());
+        }).await.unwrap();
+        assert_eq!(summary.imported.len(), 1);
+        assert_eq!((summary.vectors_restored, summary.collections_restored), (1, 1));
+
+        let restored = &summary.imported[0];
+        assert_eq!(restored.path, dir.path().join("target/documents/design.md").canonicalize().unwrap());
+        assert!(restored.starred && restored.vectorized);
+        assert_eq!(restored.tags, vec!["architecture".to_string()]);
+        let original = lock_manager(&source).await.unwrap().get_item(&design.id).await.unwrap();
+        assert_eq!((&restored.author, restored.modified), (&original.author, original.modified));
+        // Restored vectors are searchable without re-embedding
+        assert!(lock_manager(&target).await.unwrap().search_similar(&restored.id, 5).await.is_ok());
+
+        // Conflicts: skipped, or kept next to the existing file
+        let options = BundleImportOptions { conflict: ConflictPolicy::Skip, ..Default::default() };
+        let summary = import_bundle(&target, &bundle, &options, |_| {}).await.unwrap();
+        assert_eq!(summary.skipped[0].reason, "Already exists");
+        let summary = import_bundle(&target, &bundle, &BundleImportOptions::default(), |_| {}).await.unwrap();
+        assert_eq!(summary.imported[0].name, "design (1).md");
+        assert_eq!(summary.collections_restored, 0);
+
+        // A tampered file fails verification and nothing is imported
+        let tampered = dir.path().join("tampered.zip");
+        let mut archive = zip::ZipArchive::new(fs::File::open(&bundle).unwrap()).unwrap();
+        let mut writer = zip::ZipWriter::new(fs::File::create(&tampered).unwrap());
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).unwrap();
+            let mut data = Vec::new();
+            std::io::Read::read_to_end(&mut file, &mut data).unwrap();
+            if file.name() == "files/documents/design.md" {
+                data = b"Edited after export".to_vec();
+            }
+            writer.start_file(file.name(), zip::write::SimpleFileOptions::default()).unwrap();
+            writer.write_all(&data).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let verification = verify_bundle(&tampered).unwrap();
+        assert!(!verification.valid);
+        assert_eq!(verification.corrupted, vec!["files/documents/design.md".to_string()]);
+        assert!(import_bundle(&target, &tampered, &BundleImportOptions::default(), |_| {}).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_private_entries_are_restored_sealed() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = KnowledgeManager::new(dir.path().join("source")).unwrap();
+        let docs = dir.path().join("source/documents");
+        let secret = source.upload_file(Some(&docs), "secret.md", b"Acquisition target list".to_vec(), false).await.unwrap();
+        source.toggle_private(&secret.id).await.unwrap();
+        let source = SharedKnowledgeManager::new(Some(source));
+
+        let bundle = dir.path().join("backup.zip");
+        let options = ExportOptions { include_private: true, ..Default::default() };
+        export_bundle(&source, &bundle, &options, |_| {}).await.unwrap();
+
+        // Restoring over an existing public copy seals it before the content is replaced
+        let target = KnowledgeManager::new(dir.path().join("target")).unwrap();
+        let target_docs = dir.path().join("target/documents");
+        let existing = target.upload_file(Some(&target_docs), "secret.md", b"Old notes".to_vec(), false).await.unwrap();
+        let target = SharedKnowledgeManager::new(Some(target));
+        let options = BundleImportOptions { conflict: ConflictPolicy::Overwrite, ..Default::default() };
+        let summary = import_bundle(&target, &bundle, &options, |_| {}).await.unwrap();
+        let target = lock_manager(&target).await.unwrap();
+
+        let restored = &summary.imported[0];
+        assert_eq!(restored.id, existing.id);
+        assert!(restored.private);
+        assert!(crate::knowledge_crypto::is_sealed(&fs::read(&restored.path).unwrap()));
+        assert_eq!(target.read_item_content(&restored.id).await.unwrap(), "Acquisition target list");
+        assert!(target.search("acquisition", &Default::default(), 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,502 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::knowledge::{ItemType, KnowledgeItem};
+
+// Threshold for `similar:` when the query does not give one
+const DEFAULT_SIMILARITY: f32 = 0.5;
+
+/// A saved query. Only the query is stored, so the contents follow the knowledge base.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+}
+
+impl Comparison {
+    fn holds<T: PartialOrd>(&self, left: T, right: T) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Equal => left == right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateBound {
+    Ago(Duration),   // modified<30d: changed within the last 30 days
+    On(NaiveDate),   // modified>2024-06-01
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Tag(String),
+    Type(ItemType),
+    Folder(String), // Relative to the knowledge root
+    Name(String),
+    Text(String),   // Bare words: name, description or tags
+    Starred,
+    Private,
+    Vectorized,
+    Modified(Comparison, DateBound),
+    Size(Comparison, u64),
+    Similar { query: String, threshold: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Predicate(Predicate),
+}
+
+/// What an expression is evaluated against besides the item itself
+pub struct EvalContext<'a> {
+    pub now: DateTime<Utc>,
+    pub root: &'a Path,
+    pub similarity: HashMap<String, HashMap<String, f32>>, // Semantic query -> item id -> best chunk similarity
+}
+
+impl Expr {
+    pub fn matches(&self, item: &KnowledgeItem, context: &EvalContext) -> bool {
+        match self {
+            Expr::And(left, right) => left.matches(item, context) && right.matches(item, context),
+            Expr::Or(left, right) => left.matches(item, context) || right.matches(item, context),
+            Expr::Not(inner) => !inner.matches(item, context),
+            Expr::Predicate(predicate) => predicate.matches(item, context),
+        }
+    }
+
+    /// Texts of every `similar:` predicate, which need embedding before evaluation, each
+    /// with the lowest threshold it is compared against
+    pub fn semantic_queries(&self) -> Vec<(String, f32)> {
+        let mut queries = Vec::new();
+        self.collect_semantic(&mut queries);
+        queries
+    }
+
+    fn collect_semantic(&self, queries: &mut Vec<(String, f32)>) {
+        match self {
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                left.collect_semantic(queries);
+                right.collect_semantic(queries);
+            }
+            Expr::Not(inner) => inner.collect_semantic(queries),
+            Expr::Predicate(Predicate::Similar { query, threshold }) => {
+                match queries.iter_mut().find(|(text, _)| text == query) {
+                    Some((_, lowest)) => *lowest = lowest.min(*threshold),
+                    None => queries.push((query.clone(), *threshold)),
+                }
+            }
+            Expr::Predicate(_) => {}
+        }
+    }
+}
+
+impl Predicate {
+    fn matches(&self, item: &KnowledgeItem, context: &EvalContext) -> bool {
+        let contains = |haystack: &str, needle: &str| haystack.to_lowercase().contains(&needle.to_lowercase());
+
+        match self {
+            Predicate::Tag(tag) => item.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            Predicate::Type(item_type) => &item.item_type == item_type,
+            Predicate::Folder(folder) => item.path.starts_with(context.root.join(folder.trim_matches('/'))),
+            Predicate::Name(name) => contains(&item.name, name),
+            Predicate::Text(text) => {
+                contains(&item.name, text)
+                    || item.description.as_deref().is_some_and(|d| contains(d, text))
+                    || item.tags.iter().any(|t| contains(t, text))
+            }
+            Predicate::Starred => item.starred,
+            Predicate::Private => item.private,
+            Predicate::Vectorized => item.vectorized,
+            Predicate::Modified(comparison, DateBound::Ago(duration)) => {
+                comparison.holds(context.now - item.modified, *duration)
+            }
+            Predicate::Modified(comparison, DateBound::On(date)) => {
+                comparison.holds(item.modified.date_naive(), *date)
+            }
+            Predicate::Size(comparison, bytes) => comparison.holds(item.size, *bytes),
+            Predicate::Similar { query, threshold } => context.similarity.get(query)
+                .and_then(|scores| scores.get(&item.id))
+                .is_some_and(|similarity| similarity >= threshold),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Term(String),
+}
+
+/// Parse a collection query such as `tag:architecture AND starred AND modified<30d`.
+///
+/// Terms are combined with AND (also implied between adjacent terms), OR, NOT or a
+/// leading '-', and parentheses. Operators are case-insensitive. Supported terms: `tag:`, `type:`, `folder:`, `name:`,
+/// `starred`, `private`, `vectorized`, `modified<30d` (h, d, w, y) or `modified>=2024-06-01`,
+/// `size>10mb` (b, kb, mb, gb), `similar:"text"` with an optional `>0.7` threshold, and
+/// bare or quoted words matched against names, descriptions and tags.
+pub fn parse_query(query: &str) -> Result<Expr> {
+    let tokens = tokenize(query)?;
+    if tokens.is_empty() {
+        return Err(anyhow!("Query is empty"));
+    }
+
+    let mut parser = Parser { tokens, position: 0 };
+    let expr = parser.parse_or()?;
+    match parser.tokens.get(parser.position) {
+        None => Ok(expr),
+        Some(Token::Close) => Err(anyhow!("Unmatched ')'")),
+        Some(token) => Err(anyhow!("Unexpected {:?}", token)),
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                if c == '-' {
+                    chars.next();
+                    tokens.push(Token::Not);
+                }
+
+                let mut term = String::new();
+                let mut in_quotes = false;
+                while let Some(&c) = chars.peek() {
+                    if !in_quotes && (c.is_whitespace() || c == '(' || c == ')') {
+                        break;
+                    }
+                    if c == '"' {
+                        in_quotes = !in_quotes;
+                    }
+                    term.push(c);
+                    chars.next();
+                }
+                if in_quotes {
+                    return Err(anyhow!("Unterminated quote in {}", term));
+                }
+
+                // Operators in any case; quote the word to search for it
+                match term.to_ascii_uppercase().as_str() {
+                    "" => {}
+                    "AND" => tokens.push(Token::And),
+                    "OR" => tokens.push(Token::Or),
+                    "NOT" => tokens.push(Token::Not),
+                    _ => tokens.push(Token::Term(term)),
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.position += 1,
+                Some(Token::Term(_)) | Some(Token::Not) | Some(Token::Open) => {} // Implicit AND
+                _ => break,
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        let token = self.peek().cloned()
+            .ok_or_else(|| anyhow!("Query ends where a term was expected"))?;
+        self.position += 1;
+
+        match token {
+            Token::Not => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Token::Open => {
+                let expr = self.parse_or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(anyhow!("Missing ')'"));
+                }
+                self.position += 1;
+                Ok(expr)
+            }
+            Token::Term(term) => Ok(Expr::Predicate(parse_term(&term)?)),
+            other => Err(anyhow!("Expected a term, found {:?}", other)),
+        }
+    }
+}
+
+fn parse_term(term: &str) -> Result<Predicate> {
+    if term.starts_with('"') {
+        return Ok(Predicate::Text(unquote(term).to_string()));
+    }
+
+    let key_len = term.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(term.len());
+    let (key, rest) = term.split_at(key_len);
+    let key = key.to_lowercase();
+
+    if rest.is_empty() {
+        return Ok(match key.as_str() {
+            "starred" => Predicate::Starred,
+            "private" => Predicate::Private,
+            "vectorized" => Predicate::Vectorized,
+            _ => Predicate::Text(term.to_string()),
+        });
+    }
+
+    if let Some(value) = rest.strip_prefix(':') {
+        let text = unquote(value);
+        if text.is_empty() && key != "similar" {
+            return Err(anyhow!("{} needs a value", key));
+        }
+        return match key.as_str() {
+            "tag" => Ok(Predicate::Tag(text.to_string())),
+            "folder" => Ok(Predicate::Folder(text.to_string())),
+            "name" => Ok(Predicate::Name(text.to_string())),
+            "type" => {
+                let lower = text.to_lowercase();
+                ItemType::parse(&lower)
+                    .or_else(|| lower.strip_suffix('s').and_then(ItemType::parse))
+                    .map(Predicate::Type)
+                    .ok_or_else(|| anyhow!("Unknown item type '{}'", text))
+            }
+            "similar" => parse_similar(value),
+            _ => Err(anyhow!("Unknown field '{}'", key)),
+        };
+    }
+
+    if let Some((comparison, value)) = parse_comparison(rest) {
+        return match key.as_str() {
+            "modified" => Ok(Predicate::Modified(comparison, parse_date_bound(comparison, value)?)),
+            "size" => Ok(Predicate::Size(comparison, parse_size(value)?)),
+            _ => Err(anyhow!("Cannot compare '{}'", key)),
+        };
+    }
+
+    Ok(Predicate::Text(term.to_string()))
+}
+
+fn unquote(value: &str) -> &str {
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value)
+}
+
+fn parse_comparison(text: &str) -> Option<(Comparison, &str)> {
+    [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+        ("=", Comparison::Equal),
+    ]
+    .into_iter()
+    .find_map(|(op, comparison)| text.strip_prefix(op).map(|rest| (comparison, rest)))
+}
+
+/// `"query text">0.7`, or an unquoted single word; the threshold is optional
+fn parse_similar(value: &str) -> Result<Predicate> {
+    let (query, rest) = match value.strip_prefix('"') {
+        Some(quoted) => {
+            let end = quoted.find('"').ok_or_else(|| anyhow!("Unterminated quote in similar:"))?;
+            (&quoted[..end], &quoted[end + 1..])
+        }
+        None => {
+            let end = value.find(['<', '>', '=']).unwrap_or(value.len());
+            (&value[..end], &value[end..])
+        }
+    };
+    if query.trim().is_empty() {
+        return Err(anyhow!("similar needs a query"));
+    }
+
+    let threshold = match parse_comparison(rest) {
+        Some((Comparison::Greater | Comparison::GreaterOrEqual, number)) => number.parse::<f32>()
+            .ok()
+            .filter(|t| (0.0..=1.0).contains(t))
+            .ok_or_else(|| anyhow!("Similarity threshold must be between 0 and 1"))?,
+        Some(_) => return Err(anyhow!("similar only supports > or >= thresholds")),
+        None if rest.is_empty() => DEFAULT_SIMILARITY,
+        None => return Err(anyhow!("Unexpected '{}' after similar query", rest)),
+    };
+
+    Ok(Predicate::Similar { query: query.trim().to_string(), threshold })
+}
+
+fn parse_date_bound(comparison: Comparison, value: &str) -> Result<DateBound> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(DateBound::On(date));
+    }
+
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let invalid = || anyhow!("Invalid date '{}': use 30d or 2024-06-01", value);
+    let number: i64 = number.parse().map_err(|_| invalid())?;
+    let duration = match unit.to_lowercase().as_str() {
+        "h" => Duration::try_hours(number),
+        "d" => Duration::try_days(number),
+        "w" => Duration::try_weeks(number),
+        "y" => number.checked_mul(365).and_then(Duration::try_days),
+        _ => return Err(anyhow!("Unknown time unit '{}': use h, d, w or y", unit)),
+    }
+    .ok_or_else(invalid)?;
+    if comparison == Comparison::Equal {
+        return Err(anyhow!("Use < or > with relative dates"));
+    }
+
+    Ok(DateBound::Ago(duration))
+}
+
+fn parse_size(value: &str) -> Result<u64> {
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().map_err(|_| anyhow!("Invalid size '{}'", value))?;
+    let multiplier = match unit.to_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "kb" => 1024.0,
+        "mb" => 1024.0 * 1024.0,
+        "gb" => 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(anyhow!("Unknown size unit '{}': use b, kb, mb or gb", unit)),
+    };
+    Ok((number * multiplier) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::KnowledgeManager;
+
+    fn term(predicate: Predicate) -> Box<Expr> {
+        Box::new(Expr::Predicate(predicate))
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let expr = parse_query("tag:architecture AND starred AND modified<30d").unwrap();
+        assert_eq!(expr, Expr::And(
+            Box::new(Expr::And(term(Predicate::Tag("architecture".to_string())), term(Predicate::Starred))),
+            term(Predicate::Modified(Comparison::Less, DateBound::Ago(Duration::days(30)))),
+        ));
+
+        // OR binds looser than the implicit AND; '-' negates
+        let expr = parse_query("type:documents (tag:api OR name:\"design doc\") -private").unwrap();
+        assert_eq!(expr, Expr::And(
+            Box::new(Expr::And(
+                term(Predicate::Type(ItemType::Document)),
+                Box::new(Expr::Or(term(Predicate::Tag("api".to_string())), term(Predicate::Name("design doc".to_string())))),
+            )),
+            Box::new(Expr::Not(term(Predicate::Private))),
+        ));
+
+        assert_eq!(
+            parse_query("similar:\"vector databases\">=0.7").unwrap(),
+            Expr::Predicate(Predicate::Similar { query: "vector databases".to_string(), threshold: 0.7 }),
+        );
+        assert_eq!(
+            parse_query("size>1.5mb").unwrap(),
+            Expr::Predicate(Predicate::Size(Comparison::Greater, 1_572_864)),
+        );
+        assert_eq!(
+            parse_query("modified>=2024-06-01").unwrap(),
+            Expr::Predicate(Predicate::Modified(Comparison::GreaterOrEqual, DateBound::On(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()))),
+        );
+
+        // Operators in any case; a quoted operator is a search word
+        assert_eq!(
+            parse_query("starred or private and not vectorized").unwrap(),
+            parse_query("starred OR private AND NOT vectorized").unwrap(),
+        );
+        assert_eq!(parse_query("\"and\"").unwrap(), *term(Predicate::Text("and".to_string())));
+
+        let expr = parse_query("similar:\"rust\">0.8 OR similar:\"rust\">0.6").unwrap();
+        assert_eq!(expr.semantic_queries(), vec![("rust".to_string(), 0.6)]);
+
+        for bad in ["", "tag:", "(starred", "starred)", "owner:me", "modified<30x", "similar:\"x\">2", "name:\"open",
+                    "modified<9999999999999d", "modified<99999999999y"] {
+            assert!(parse_query(bad).is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collection_items_follow_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+
+        let design = manager.upload_file(Some(&docs), "design.md", b"Service boundaries".to_vec(), false).await.unwrap();
+        manager.upload_file(Some(&docs), "notes.md", b"Groceries".to_vec(), false).await.unwrap();
+        manager.update_item_tags(&design.id, vec!["architecture".to_string()]).await.unwrap();
+
+        let collection = manager.create_collection("Starred architecture", "tag:architecture AND starred AND modified<30d").await.unwrap();
+        assert!(manager.list_collection_items(&collection.id).await.unwrap().is_empty());
+
+        let mut changes = manager.subscribe_changes();
+        manager.toggle_star(&design.id).await.unwrap();
+        assert!(changes.try_recv().is_ok());
+        let items = manager.list_collection_items(&collection.id).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, design.id);
+
+        assert!(manager.create_collection("Broken", "tag:architecture AND (").await.is_err());
+        assert_eq!(manager.list_collections().await.unwrap().len(), 1);
+
+        manager.update_collection(&collection.id, None, Some("type:document -starred")).await.unwrap();
+        let items = manager.list_collection_items(&collection.id).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "notes.md");
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::{anyhow, Result};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::path::Path;
+
+/// Prefix of every sealed blob, so sealed and plain content can be told apart
+const MAGIC: &[u8; 8] = b"LBSEAL1\0";
+
+/// AES-256-GCM cipher for private knowledge items
+pub struct KnowledgeCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl KnowledgeCipher {
+    /// Load the key stored at `key_path`, creating it (owner-only) on first use
+    pub fn load_or_create(key_path: &Path) -> Result<Self> {
+        let key_bytes = if key_path.exists() {
+            hex::decode(std::fs::read_to_string(key_path)?.trim())?
+        } else {
+            let mut key_data = [0u8; 32];
+            SystemRandom::new().fill(&mut key_data)
+                .map_err(|_| anyhow!("Failed to generate random key"))?;
+
+            std::fs::write(key_path, hex::encode(key_data))?;
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mut perms = std::fs::metadata(key_path)?.permissions();
+                perms.set_mode(0o600); // Read/write for owner only
+                std::fs::set_permissions(key_path, perms)?;
+            }
+
+            key_data.to_vec()
+        };
+
+        let key = UnboundKey::new(&AES_256_GCM, &key_bytes)
+            .map_err(|_| anyhow!("Invalid knowledge encryption key"))?;
+
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// MAGIC | nonce | ciphertext with tag
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce)
+            .map_err(|_| anyhow!("Failed to generate nonce"))?;
+
+        let mut buffer = plaintext.to_vec();
+        self.key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(MAGIC), &mut buffer)
+            .map_err(|_| anyhow!("Encryption failed"))?;
+
+        let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + buffer.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&buffer);
+        Ok(sealed)
+    }
+
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if !is_sealed(sealed) || sealed.len() < MAGIC.len() + NONCE_LEN {
+            return Err(anyhow!("Content is not encrypted"));
+        }
+
+        let (nonce, ciphertext) = sealed[MAGIC.len()..].split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| anyhow!("Invalid nonce"))?;
+
+        let mut buffer = ciphertext.to_vec();
+        let plaintext = self.key.open_in_place(nonce, Aad::from(MAGIC), &mut buffer)
+            .map_err(|_| anyhow!("Decryption failed: wrong key or corrupted content"))?;
+        Ok(plaintext.to_vec())
+    }
+}
+
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::KnowledgeManager;
+
+    #[tokio::test]
+    async fn test_private_items_are_sealed() {
+        let dir = tempfile::tempdir().unwrap();
+        let knowledge_dir = dir.path().join("knowledge");
+        let manager = KnowledgeManager::new(knowledge_dir.clone()).unwrap();
+        let docs = knowledge_dir.join("documents");
+
+        let secret = "Salary review notes: the offer for the platform lead is confidential";
+        let item = manager.upload_file(Some(&docs), "review.md", secret.as_bytes().to_vec(), false).await.unwrap();
+        manager.vectorize_item(&item.id).await.unwrap();
+
+        assert!(manager.toggle_private(&item.id).await.unwrap());
+        let on_disk = std::fs::read(docs.join("review.md")).unwrap();
+        assert!(is_sealed(&on_disk));
+        assert!(dir.path().join(".localbrain_knowledge_key").exists());
+
+        // Readable through the manager, invisible to content search
+        assert_eq!(manager.read_item_content(&item.id).await.unwrap(), secret);
+        let results = manager.search("confidential", &Default::default(), 10).await.unwrap();
+        assert!(results.is_empty());
+
+        // Overwrites and their history stay sealed
+        manager.upload_file(Some(&docs), "review.md", b"Offer accepted".to_vec(), true).await.unwrap();
+        assert!(is_sealed(&std::fs::read(docs.join("review.md")).unwrap()));
+        let diff = manager.diff_versions(&item.id, Some(&manager.list_versions(&item.id).await.unwrap()[0].id), None).await.unwrap();
+        assert!(diff.contains("+Offer accepted"));
+
+        assert!(!manager.toggle_private(&item.id).await.unwrap());
+        assert_eq!(std::fs::read_to_string(docs.join("review.md")).unwrap(), "Offer accepted");
+        let results = manager.search("offer", &Default::default(), 10).await.unwrap();
+        assert_eq!(results[0].item.id, item.id);
+    }
+}
@@ -0,0 +1,461 @@
+use anyhow::{anyhow, Result};
+use parquet::file::reader::ChunkReader;
+use serde::de::{Deserializer as _, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// One record as (column, value) pairs in file order
+pub type DatasetRow = Vec<(String, Value)>;
+
+pub const DEFAULT_PREVIEW_ROWS: usize = 50;
+pub const MAX_PREVIEW_ROWS: usize = 1000;
+// Distinct values are tracked per column up to this many, then reported as unknown
+const DISTINCT_LIMIT: usize = 10_000;
+// JSON and Parquet files above this size are refused, as are private datasets, which
+// are decrypted in memory
+pub const MAX_DATASET_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatasetFormat {
+    Csv,
+    Tsv,
+    Json,
+    Jsonl,
+    Parquet,
+}
+
+impl DatasetFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "csv" => Some(DatasetFormat::Csv),
+            "tsv" | "tab" => Some(DatasetFormat::Tsv),
+            "json" => Some(DatasetFormat::Json),
+            "jsonl" | "ndjson" => Some(DatasetFormat::Jsonl),
+            "parquet" => Some(DatasetFormat::Parquet),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    Null, // Every value was missing
+    Boolean,
+    Integer,
+    Float,
+    String,
+    Json, // Nested objects or arrays
+}
+
+impl ColumnType {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => ColumnType::Null,
+            Value::Bool(_) => ColumnType::Boolean,
+            Value::Number(n) if n.is_i64() || n.is_u64() => ColumnType::Integer,
+            Value::Number(_) => ColumnType::Float,
+            Value::String(_) => ColumnType::String,
+            Value::Array(_) | Value::Object(_) => ColumnType::Json,
+        }
+    }
+
+    /// Narrowest type holding values of both; integers widen to floats, other mixes to strings
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnType::Null, b) => b,
+            (a, ColumnType::Null) => a,
+            (ColumnType::Integer, ColumnType::Float) | (ColumnType::Float, ColumnType::Integer) => ColumnType::Float,
+            (ColumnType::Json, _) | (_, ColumnType::Json) => ColumnType::Json,
+            _ => ColumnType::String,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub null_count: u64,
+    pub null_ratio: f64,
+    pub distinct_count: Option<u64>, // None when there were too many to count
+    pub min: Option<f64>,            // Numeric columns only
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetPreview {
+    pub format: DatasetFormat,
+    pub row_count: u64,
+    pub columns: Vec<ColumnSchema>,
+    pub rows: Vec<Map<String, Value>>, // The first rows, values typed as inferred
+}
+
+#[derive(Default)]
+struct ColumnProfile {
+    column_type: Option<ColumnType>,
+    present: u64,
+    numeric_count: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    distinct: HashSet<String>,
+    distinct_overflow: bool,
+}
+
+/// Streams rows once, keeping the first `preview_rows` and per-column statistics
+struct Profiler {
+    preview_rows: usize,
+    row_count: u64,
+    columns: Vec<String>,
+    positions: HashMap<String, usize>,
+    profiles: Vec<ColumnProfile>,
+    rows: Vec<Map<String, Value>>,
+}
+
+impl Profiler {
+    fn new(preview_rows: usize) -> Self {
+        Self {
+            preview_rows,
+            row_count: 0,
+            columns: Vec::new(),
+            positions: HashMap::new(),
+            profiles: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn column(&mut self, name: &str) -> usize {
+        if let Some(&position) = self.positions.get(name) {
+            return position;
+        }
+        self.columns.push(name.to_string());
+        self.profiles.push(ColumnProfile::default());
+        self.positions.insert(name.to_string(), self.columns.len() - 1);
+        self.columns.len() - 1
+    }
+
+    fn observe(&mut self, row: DatasetRow) {
+        self.row_count += 1;
+
+        for (name, value) in &row {
+            let position = self.column(name);
+            let profile = &mut self.profiles[position];
+
+            let value_type = ColumnType::of(value);
+            profile.column_type = Some(match profile.column_type {
+                Some(current) => current.merge(value_type),
+                None => value_type,
+            });
+            if value.is_null() {
+                continue;
+            }
+            profile.present += 1;
+
+            if let Some(number) = value.as_f64() {
+                profile.numeric_count += 1;
+                profile.sum += number;
+                profile.min = Some(profile.min.map_or(number, |m| m.min(number)));
+                profile.max = Some(profile.max.map_or(number, |m| m.max(number)));
+            }
+
+            if !profile.distinct_overflow {
+                profile.distinct.insert(value.to_string());
+                if profile.distinct.len() > DISTINCT_LIMIT {
+                    profile.distinct_overflow = true;
+                    profile.distinct.clear();
+                }
+            }
+        }
+
+        if self.rows.len() < self.preview_rows {
+            self.rows.push(row.into_iter().collect());
+        }
+    }
+
+    fn finish(self, format: DatasetFormat, row_count: Option<u64>) -> DatasetPreview {
+        let rows_seen = self.row_count;
+        let columns = self.columns.into_iter()
+            .zip(self.profiles)
+            .map(|(name, profile)| {
+                let column_type = profile.column_type.unwrap_or(ColumnType::Null);
+                let numeric = matches!(column_type, ColumnType::Integer | ColumnType::Float);
+                let null_count = rows_seen - profile.present;
+                ColumnSchema {
+                    name,
+                    column_type,
+                    null_count,
+                    null_ratio: if rows_seen == 0 { 0.0 } else { null_count as f64 / rows_seen as f64 },
+                    distinct_count: (!profile.distinct_overflow).then_some(profile.distinct.len() as u64),
+                    min: profile.min.filter(|_| numeric),
+                    max: profile.max.filter(|_| numeric),
+                    mean: (numeric && profile.numeric_count > 0).then(|| profile.sum / profile.numeric_count as f64),
+                }
+            })
+            .collect();
+
+        DatasetPreview {
+            format,
+            row_count: row_count.unwrap_or(rows_seen),
+            columns,
+            rows: self.rows,
+        }
+    }
+}
+
+/// Read a whole dataset once, returning its inferred schema, statistics and first rows.
+/// `source` is a file or in-memory bytes; Parquet is read from it in place.
+pub fn preview_dataset<R: ChunkReader + 'static>(format: DatasetFormat, source: R, rows: usize) -> Result<DatasetPreview> {
+    let mut profiler = Profiler::new(rows.min(MAX_PREVIEW_ROWS));
+    let row_count = read_source(format, source, &mut |row| profiler.observe(row))?;
+    Ok(profiler.finish(format, row_count))
+}
+
+/// Stream every row of a dataset as a JSON object
+pub fn read_rows<R: ChunkReader + 'static>(format: DatasetFormat, source: R, on_row: &mut dyn FnMut(DatasetRow)) -> Result<()> {
+    read_source(format, source, on_row).map(|_| ())
+}
+
+/// Returns the row count recorded in a Parquet footer
+fn read_source<R: ChunkReader + 'static>(format: DatasetFormat, source: R, on_row: &mut dyn FnMut(DatasetRow)) -> Result<Option<u64>> {
+    if matches!(format, DatasetFormat::Json | DatasetFormat::Parquet) && source.len() > MAX_DATASET_BYTES {
+        return Err(anyhow!("Dataset is larger than {} MB", MAX_DATASET_BYTES / (1024 * 1024)));
+    }
+
+    match format {
+        DatasetFormat::Csv => read_delimited(source.get_read(0)?, b',', on_row)?,
+        DatasetFormat::Tsv => read_delimited(source.get_read(0)?, b'\t', on_row)?,
+        DatasetFormat::Jsonl => read_json_lines(source.get_read(0)?, on_row)?,
+        DatasetFormat::Json => read_json(source.get_read(0)?, on_row)?,
+        DatasetFormat::Parquet => return read_parquet(source, on_row).map(Some),
+    }
+    Ok(None)
+}
+
+fn read_delimited<R: Read>(reader: R, delimiter: u8, on_row: &mut dyn FnMut(DatasetRow)) -> Result<()> {
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(reader);
+
+    let headers: Vec<String> = csv_reader.headers()?
+        .iter()
+        .enumerate()
+        .map(|(i, h)| if h.trim().is_empty() { format!("column_{}", i + 1) } else { h.trim().to_string() })
+        .collect();
+
+    for record in csv_reader.records() {
+        let record = record?;
+        let row = record.iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let name = headers.get(i).cloned().unwrap_or_else(|| format!("column_{}", i + 1));
+                (name, infer_value(field))
+            })
+            .collect();
+        on_row(row);
+    }
+
+    Ok(())
+}
+
+/// Typed value for a text cell: empty is null, then integer, float, boolean, string.
+/// Numbers that would not print back as written, such as "00123", stay text.
+fn infer_value(field: &str) -> Value {
+    let trimmed = field.trim();
+    if trimmed.is_empty() {
+        return Value::Null;
+    }
+    if let Ok(integer) = trimmed.parse::<i64>() {
+        if integer.to_string() == trimmed {
+            return Value::from(integer);
+        }
+        return Value::String(field.to_string());
+    }
+    let digits = trimmed.strip_prefix(['-', '+']).unwrap_or(trimmed);
+    let leading_zero = digits.len() > 1 && digits.starts_with('0') && digits.as_bytes()[1].is_ascii_digit();
+    // All digits but not an i64: too large to survive as a float
+    let integer_text = digits.bytes().all(|b| b.is_ascii_digit());
+    if !leading_zero && !integer_text {
+        if let Ok(float) = trimmed.parse::<f64>() {
+            if float.is_finite() {
+                return Value::from(float);
+            }
+        }
+    }
+    match trimmed.to_lowercase().as_str() {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => Value::String(field.to_string()),
+    }
+}
+
+fn into_row(value: Value) -> DatasetRow {
+    match value {
+        Value::Object(map) => map.into_iter().collect(),
+        other => vec![("value".to_string(), other)],
+    }
+}
+
+fn read_json_lines<R: Read>(reader: R, on_row: &mut dyn FnMut(DatasetRow)) -> Result<()> {
+    for (number, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(&line)
+            .map_err(|e| anyhow!("Invalid JSON on line {}: {}", number + 1, e))?;
+        on_row(into_row(value));
+    }
+    Ok(())
+}
+
+/// A top-level array is one row per element, parsed as it streams in; a single
+/// object is one row
+fn read_json<R: Read>(reader: R, on_row: &mut dyn FnMut(DatasetRow)) -> Result<()> {
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+    deserializer.deserialize_any(JsonRows { on_row })?;
+    deserializer.end()?;
+    Ok(())
+}
+
+struct JsonRows<'a> {
+    on_row: &'a mut dyn FnMut(DatasetRow),
+}
+
+impl JsonRows<'_> {
+    fn row<E>(self, value: Value) -> std::result::Result<(), E> {
+        (self.on_row)(into_row(value));
+        Ok(())
+    }
+}
+
+impl<'de> Visitor<'de> for JsonRows<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a JSON array or object")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        while let Some(value) = seq.next_element::<Value>()? {
+            (self.on_row)(into_row(value));
+        }
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> std::result::Result<(), A::Error> {
+        let value = Value::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+        self.row(value)
+    }
+
+    fn visit_bool<E>(self, value: bool) -> std::result::Result<(), E> { self.row(Value::from(value)) }
+    fn visit_i64<E>(self, value: i64) -> std::result::Result<(), E> { self.row(Value::from(value)) }
+    fn visit_u64<E>(self, value: u64) -> std::result::Result<(), E> { self.row(Value::from(value)) }
+    fn visit_f64<E>(self, value: f64) -> std::result::Result<(), E> { self.row(Value::from(value)) }
+    fn visit_str<E>(self, value: &str) -> std::result::Result<(), E> { self.row(Value::from(value)) }
+    fn visit_unit<E>(self) -> std::result::Result<(), E> { self.row(Value::Null) }
+}
+
+/// Returns the row count recorded in the file footer
+fn read_parquet<R: ChunkReader + 'static>(source: R, on_row: &mut dyn FnMut(DatasetRow)) -> Result<u64> {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let file_reader = SerializedFileReader::new(source)?;
+    let row_count = file_reader.metadata().file_metadata().num_rows().max(0) as u64;
+
+    for row in file_reader.get_row_iter(None)? {
+        let row = row?;
+        on_row(row.get_column_iter().map(|(name, field)| (name.clone(), field.to_json_value())).collect());
+    }
+
+    Ok(row_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_csv_and_json_lines() {
+        let csv = "id,name,score,active\n1,Ada,9.5,true\n2,,7,false\n3,Linus,,true\n";
+        let preview = preview_dataset(DatasetFormat::Csv, bytes::Bytes::from(csv), 2).unwrap();
+        assert_eq!(preview.row_count, 3);
+        assert_eq!(preview.rows.len(), 2);
+
+        let column = |name: &str| preview.columns.iter().find(|c| c.name == name).unwrap();
+        assert_eq!(column("id").column_type, ColumnType::Integer);
+        assert_eq!(column("score").column_type, ColumnType::Float);
+        assert_eq!(column("score").mean, Some(8.25));
+        assert_eq!(column("name").null_count, 1);
+        assert!((column("name").null_ratio - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(column("active").column_type, ColumnType::Boolean);
+        assert_eq!(column("active").distinct_count, Some(2));
+
+        let jsonl = "{\"a\": 1, \"b\": {\"x\": 1}}\n\n{\"a\": \"two\"}\n";
+        let preview = preview_dataset(DatasetFormat::Jsonl, bytes::Bytes::from(jsonl), 10).unwrap();
+        assert_eq!(preview.row_count, 2);
+        assert_eq!(preview.columns[0].column_type, ColumnType::String);
+        assert_eq!(preview.columns[1].column_type, ColumnType::Json);
+        assert_eq!(preview.columns[1].null_count, 1);
+    }
+
+    #[test]
+    fn test_json_arrays_and_number_text() {
+        let json = r#"[{"zip": "00123", "n": 1}, {"zip": "02139", "n": 2}, 7]"#;
+        let preview = preview_dataset(DatasetFormat::Json, bytes::Bytes::from(json), 10).unwrap();
+        assert_eq!(preview.row_count, 3);
+        assert_eq!(preview.rows[2]["value"], Value::from(7));
+
+        assert_eq!(infer_value("00123"), Value::from("00123"));
+        assert_eq!(infer_value("007.5"), Value::from("007.5"));
+        assert_eq!(infer_value("+44"), Value::from("+44"));
+        assert_eq!(infer_value("12345678901234567890"), Value::from("12345678901234567890"));
+        assert_eq!(infer_value("-42"), Value::from(-42));
+        assert_eq!(infer_value("0.25"), Value::from(0.25));
+        assert_eq!(infer_value("1e3"), Value::from(1000.0));
+    }
+
+    #[test]
+    fn test_parquet() {
+        use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+
+        let schema = Arc::new(parse_message_type(
+            "message schema { REQUIRED INT64 id; OPTIONAL BYTE_ARRAY city (UTF8); }"
+        ).unwrap());
+        let mut buffer = Vec::new();
+        let mut writer = SerializedFileWriter::new(&mut buffer, schema, Arc::new(WriterProperties::builder().build())).unwrap();
+        let mut row_group = writer.next_row_group().unwrap();
+
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column.typed::<Int64Type>().write_batch(&[10, 20, 30], None, None).unwrap();
+        column.close().unwrap();
+
+        let mut column = row_group.next_column().unwrap().unwrap();
+        let cities = [ByteArray::from("Lisbon"), ByteArray::from("Porto")];
+        column.typed::<ByteArrayType>().write_batch(&cities, Some(&[1, 0, 1]), None).unwrap();
+        column.close().unwrap();
+
+        row_group.close().unwrap();
+        writer.close().unwrap();
+
+        let preview = preview_dataset(DatasetFormat::Parquet, bytes::Bytes::from(buffer), 5).unwrap();
+        assert_eq!(preview.row_count, 3);
+        assert_eq!(preview.columns[0].column_type, ColumnType::Integer);
+        assert_eq!(preview.columns[0].max, Some(30.0));
+        assert_eq!(preview.columns[1].column_type, ColumnType::String);
+        assert_eq!(preview.columns[1].null_count, 1);
+        assert_eq!(preview.rows[2]["city"], Value::from("Porto"));
+    }
+}
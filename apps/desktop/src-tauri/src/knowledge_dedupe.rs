@@ -0,0 +1,200 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::Path;
+
+use crate::knowledge::KnowledgeItem;
+
+const MINHASH_PERMUTATIONS: usize = 64;
+// 32 bands of 2 rows: pairs above ~0.3 similarity almost always share a bucket
+const LSH_BANDS: usize = 32;
+const SHINGLE_WORDS: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub items: Vec<KnowledgeItem>,
+    pub similarity: f32, // 1.0 for identical bytes, otherwise the weakest estimated link
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub exact: Vec<DuplicateGroup>,
+    pub near: Vec<DuplicateGroup>,
+}
+
+/// Hex SHA-256 of a byte buffer
+pub fn content_hash(bytes: &[u8]) -> String {
+    hex::encode(ring::digest::digest(&ring::digest::SHA256, bytes).as_ref())
+}
+
+/// Hex SHA-256 of a file, streamed so large media doesn't need to fit in memory
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        context.update(&buffer[..read]);
+    }
+    Ok(hex::encode(context.finish().as_ref()))
+}
+
+/// MinHash signature over word shingles, or None for text without words
+pub fn minhash(text: &str) -> Option<Vec<u32>> {
+    let tokens = crate::embeddings::tokenize(text);
+    if tokens.is_empty() {
+        return None;
+    }
+
+    let shingles: HashSet<u64> = if tokens.len() < SHINGLE_WORDS {
+        std::iter::once(crate::embeddings::fnv1a(tokens.join(" ").as_bytes())).collect()
+    } else {
+        tokens.windows(SHINGLE_WORDS)
+            .map(|window| crate::embeddings::fnv1a(window.join(" ").as_bytes()))
+            .collect()
+    };
+
+    let mut signature = vec![u32::MAX; MINHASH_PERMUTATIONS];
+    for shingle in shingles {
+        for (i, slot) in signature.iter_mut().enumerate() {
+            let value = mix(shingle ^ mix(i as u64 + 1)) as u32;
+            if value < *slot {
+                *slot = value;
+            }
+        }
+    }
+
+    Some(signature)
+}
+
+/// Estimated Jaccard similarity of the shingle sets behind two signatures
+pub fn similarity(a: &[u32], b: &[u32]) -> f32 {
+    if a.is_empty() || a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).filter(|(x, y)| x == y).count() as f32 / a.len() as f32
+}
+
+/// Pairs of signatures at or above `threshold`, found through LSH banding
+pub fn similar_pairs(signatures: &[Vec<u32>], threshold: f32) -> Vec<(usize, usize, f32)> {
+    let rows = MINHASH_PERMUTATIONS / LSH_BANDS;
+    let mut buckets: HashMap<(usize, Vec<u32>), Vec<usize>> = HashMap::new();
+    for (index, signature) in signatures.iter().enumerate() {
+        if signature.len() != MINHASH_PERMUTATIONS {
+            continue;
+        }
+        for band in 0..LSH_BANDS {
+            let key = signature[band * rows..(band + 1) * rows].to_vec();
+            buckets.entry((band, key)).or_default().push(index);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut pairs = Vec::new();
+    for members in buckets.values().filter(|m| m.len() > 1) {
+        for (i, &a) in members.iter().enumerate() {
+            for &b in &members[i + 1..] {
+                if !seen.insert((a, b)) {
+                    continue;
+                }
+                let score = similarity(&signatures[a], &signatures[b]);
+                if score >= threshold {
+                    pairs.push((a, b, score));
+                }
+            }
+        }
+    }
+
+    pairs
+}
+
+/// Connected components of the pair graph, each with its weakest edge
+pub fn group_pairs(count: usize, pairs: &[(usize, usize, f32)]) -> Vec<(Vec<usize>, f32)> {
+    let mut parent: Vec<usize> = (0..count).collect();
+    fn find(parent: &mut [usize], mut x: usize) -> usize {
+        while parent[x] != x {
+            parent[x] = parent[parent[x]];
+            x = parent[x];
+        }
+        x
+    }
+
+    for &(a, b, _) in pairs {
+        let (root_a, root_b) = (find(&mut parent, a), find(&mut parent, b));
+        if root_a != root_b {
+            parent[root_b] = root_a;
+        }
+    }
+
+    let mut groups: HashMap<usize, (Vec<usize>, f32)> = HashMap::new();
+    for &(a, b, score) in pairs {
+        let root = find(&mut parent, a);
+        let group = groups.entry(root).or_insert((Vec::new(), 1.0));
+        for member in [a, b] {
+            if !group.0.contains(&member) {
+                group.0.push(member);
+            }
+        }
+        group.1 = group.1.min(score);
+    }
+
+    groups.into_values().collect()
+}
+
+fn mix(mut x: u64) -> u64 {
+    // splitmix64 finalizer
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::KnowledgeManager;
+
+    #[test]
+    fn test_minhash_similarity() {
+        let original = "The quarterly planning meeting covered hiring, the storage migration and the new onboarding guide for support engineers";
+        let edited = "The quarterly planning meeting covered hiring, the storage migration and the revised onboarding guide for support engineers";
+        let other = "Tomato soup needs fresh basil, garlic, olive oil and a long slow simmer before blending";
+
+        let a = minhash(original).unwrap();
+        assert!(similarity(&a, &minhash(edited).unwrap()) > 0.6);
+        assert!(similarity(&a, &minhash(other).unwrap()) < 0.2);
+    }
+
+    #[tokio::test]
+    async fn test_report_and_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+
+        let text = "Release checklist: tag the build, publish the notes, update the download page and announce it in the team channel";
+        let first = manager.upload_file(Some(&docs), "release.md", text.as_bytes().to_vec(), false).await.unwrap();
+        let upload = manager.upload_file_checked(Some(&docs), "release copy.md", text.as_bytes().to_vec(), false).await.unwrap();
+        assert_eq!(upload.duplicate_of.map(|item| item.id), Some(first.id.clone()));
+        let copy = upload.item;
+        let edited = manager.upload_file(Some(&docs), "release v2.md", text.replace("announce it", "post it").into_bytes(), false).await.unwrap();
+        manager.upload_file(Some(&docs), "soup.md", b"Tomato soup with basil".to_vec(), false).await.unwrap();
+
+        let report = manager.find_duplicates(0.5).await.unwrap();
+        assert_eq!(report.exact.len(), 1);
+        assert_eq!(report.exact[0].items.len(), 2);
+        assert_eq!(report.near.len(), 1);
+        assert_eq!(report.near[0].items.len(), 3);
+
+        manager.update_item_tags(&copy.id, vec!["release".to_string()]).await.unwrap();
+        manager.toggle_star(&edited.id).await.unwrap();
+
+        let merged = manager.merge_duplicates(&first.id, &[copy.id.clone(), edited.id.clone()]).await.unwrap();
+        assert!(merged.starred);
+        assert!(merged.tags.contains(&"release".to_string()));
+        assert!(manager.get_item(&copy.id).await.is_none());
+        assert!(!docs.join("release v2.md").exists());
+    }
+}
@@ -0,0 +1,298 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::knowledge::{lock_manager, KnowledgeItem, SharedKnowledgeManager};
+
+// Longer documents are cut before they go into the prompt
+const MAX_PROMPT_CHARS: usize = 8000;
+const MAX_SUGGESTED_TAGS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnrichmentProvider {
+    Ollama, // Local Ollama server
+    Openai, // OpenAI chat API (leaves the machine)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnrichmentSettings {
+    pub provider: EnrichmentProvider,
+    pub model: Option<String>,       // Defaults to the provider's configured default model
+    pub local_model: Option<String>, // Ollama model used for private items
+    pub vocabulary: Vec<String>,     // The only tags that may be suggested
+}
+
+impl Default for EnrichmentSettings {
+    fn default() -> Self {
+        Self {
+            provider: EnrichmentProvider::Ollama,
+            model: None,
+            local_model: None,
+            vocabulary: [
+                "architecture", "api", "design", "research", "meeting-notes", "planning",
+                "how-to", "reference", "specification", "report", "data", "code",
+                "product", "finance", "legal", "personal",
+            ].iter().map(|t| t.to_string()).collect(),
+        }
+    }
+}
+
+/// Pending summary and tags for an item, applied only when the user accepts them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrichmentSuggestion {
+    pub item_id: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrichmentProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub current: String, // Name of the item being summarized
+    pub failed: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrichmentFailure {
+    pub item_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrichmentSummary {
+    pub suggested: Vec<EnrichmentSuggestion>,
+    pub failed: Vec<EnrichmentFailure>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Enricher {
+    settings: EnrichmentSettings,
+    api_key: Option<String>,
+}
+
+impl Enricher {
+    pub fn new(settings: EnrichmentSettings, api_key: Option<String>) -> Self {
+        Self { settings, api_key }
+    }
+
+    /// Provider and model for an item. Private items never go to a cloud model.
+    pub fn model_for(&self, private: bool) -> (EnrichmentProvider, String) {
+        let config = &crate::config::CONFIG;
+        match self.settings.provider {
+            EnrichmentProvider::Openai if !private => (
+                EnrichmentProvider::Openai,
+                self.settings.model.clone().unwrap_or_else(|| config.default_openai_chat_model.clone()),
+            ),
+            EnrichmentProvider::Openai => (
+                EnrichmentProvider::Ollama,
+                self.settings.local_model.clone().unwrap_or_else(|| config.default_ollama_model.clone()),
+            ),
+            EnrichmentProvider::Ollama => (
+                EnrichmentProvider::Ollama,
+                self.settings.model.clone()
+                    .or_else(|| self.settings.local_model.clone())
+                    .unwrap_or_else(|| config.default_ollama_model.clone()),
+            ),
+        }
+    }
+
+    pub async fn suggest(&self, item: &KnowledgeItem, text: &str) -> Result<EnrichmentSuggestion> {
+        let (provider, model) = self.model_for(item.private);
+        let prompt = build_prompt(&item.name, text, &self.settings.vocabulary);
+
+        let reply = match provider {
+            EnrichmentProvider::Ollama => {
+                crate::ollama::OllamaClient::new(None).generate(&model, &prompt, 0.2).await?
+            }
+            EnrichmentProvider::Openai => self.complete_openai(&model, &prompt).await?,
+        };
+
+        let (description, mut tags) = parse_reply(&reply, &self.settings.vocabulary)?;
+        tags.retain(|tag| !item.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+
+        let provider_name = match provider {
+            EnrichmentProvider::Ollama => "ollama",
+            EnrichmentProvider::Openai => "openai",
+        };
+
+        Ok(EnrichmentSuggestion {
+            item_id: item.id.clone(),
+            description,
+            tags,
+            model: format!("{}:{}", provider_name, model),
+            created_at: Utc::now(),
+        })
+    }
+
+    async fn complete_openai(&self, model: &str, prompt: &str) -> Result<String> {
+        let api_key = match self.api_key.as_ref() {
+            Some(key) => key.clone(),
+            None => crate::config::get_openai_api_key()
+                .ok_or_else(|| anyhow!("OpenAI API key not configured"))?,
+        };
+
+        let url = format!("{}{}", crate::config::CONFIG.api.openai_base_url, crate::config::CONFIG.api.openai_chat_endpoint);
+        let response = reqwest::Client::new()
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", api_key))
+            .json(&serde_json::json!({
+                "model": model,
+                "messages": [{ "role": "user", "content": prompt }],
+                "temperature": 0.2,
+                "response_format": { "type": "json_object" },
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("OpenAI chat error: {}", error_text));
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        data["choices"][0]["message"]["content"].as_str()
+            .map(|content| content.to_string())
+            .ok_or_else(|| anyhow!("Invalid response format from chat API"))
+    }
+}
+
+/// An item's text and the enricher to summarize it with, run without holding the manager
+pub struct EnrichmentJob {
+    pub item: KnowledgeItem,
+    pub(crate) text: String,
+    pub(crate) enricher: Enricher,
+}
+
+impl EnrichmentJob {
+    pub async fn run(self) -> Result<EnrichmentSuggestion> {
+        self.enricher.suggest(&self.item, &self.text).await
+    }
+}
+
+pub fn build_prompt(name: &str, text: &str, vocabulary: &[String]) -> String {
+    let excerpt: String = text.chars().take(MAX_PROMPT_CHARS).collect();
+    format!(
+        "You are cataloguing a personal knowledge base.\n\
+         Read the document below and reply with JSON only, in the form \
+         {{\"summary\": \"...\", \"tags\": [\"...\"]}}.\n\
+         - summary: one paragraph of at most three sentences describing what the document is about.\n\
+         - tags: up to {} tags chosen only from this list: {}.\n\n\
+         Document name: {}\n\
+         ---\n{}\n---",
+        MAX_SUGGESTED_TAGS,
+        vocabulary.join(", "),
+        name,
+        excerpt
+    )
+}
+
+/// Summary and vocabulary tags from a model reply. Models often wrap the JSON in prose
+/// or code fences, so the outermost object is extracted first.
+pub fn parse_reply(reply: &str, vocabulary: &[String]) -> Result<(Option<String>, Vec<String>)> {
+    let start = reply.find('{').ok_or_else(|| anyhow!("Model reply contained no JSON"))?;
+    let end = reply.rfind('}').filter(|&end| end > start)
+        .ok_or_else(|| anyhow!("Model reply contained no JSON"))?;
+    let value: serde_json::Value = serde_json::from_str(&reply[start..=end])?;
+
+    let description = value["summary"].as_str()
+        .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|s| !s.is_empty());
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in value["tags"].as_array().into_iter().flatten().filter_map(|t| t.as_str()) {
+        let known = vocabulary.iter().find(|v| v.eq_ignore_ascii_case(tag.trim()));
+        if let Some(known) = known {
+            if !tags.contains(known) {
+                tags.push(known.clone());
+            }
+        }
+    }
+    tags.truncate(MAX_SUGGESTED_TAGS);
+
+    Ok((description, tags))
+}
+
+/// Generate pending suggestions for the given items, or for every document and dataset
+/// that has no description and has not been suggested for before. The manager is only
+/// locked to read each item and store its suggestion, not during model calls.
+pub async fn enrich_items<F>(manager: &SharedKnowledgeManager, item_ids: Option<Vec<String>>, on_progress: F) -> Result<EnrichmentSummary>
+where
+    F: Fn(&EnrichmentProgress) + Send + Sync,
+{
+    let item_ids = match item_ids {
+        Some(ids) => ids,
+        None => lock_manager(manager).await?.enrichment_candidates().await?,
+    };
+
+    let mut progress = EnrichmentProgress {
+        processed: 0,
+        total: item_ids.len(),
+        current: String::new(),
+        failed: 0,
+    };
+    let mut summary = EnrichmentSummary {
+        suggested: Vec::new(),
+        failed: Vec::new(),
+    };
+
+    for item_id in item_ids {
+        let job = lock_manager(manager).await?.prepare_enrichment(&item_id).await;
+        progress.current = job.as_ref().map(|job| job.item.name.clone()).unwrap_or_default();
+        on_progress(&progress);
+
+        let result = async {
+            let suggestion = job?.run().await?;
+            lock_manager(manager).await?.save_suggestion(&suggestion).await?;
+            anyhow::Ok(suggestion)
+        }.await;
+        match result {
+            Ok(suggestion) => summary.suggested.push(suggestion),
+            Err(e) => {
+                progress.failed += 1;
+                summary.failed.push(EnrichmentFailure { item_id, reason: e.to_string() });
+            }
+        }
+        progress.processed += 1;
+    }
+
+    progress.current.clear();
+    on_progress(&progress);
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply() {
+        let vocabulary = EnrichmentSettings::default().vocabulary;
+        let reply = "Sure! Here it is:\n```json\n{\"summary\": \"Notes from the API review.\\n  Covers pagination.\", \
+                     \"tags\": [\"API\", \"meeting-notes\", \"banana\", \"api\"]}\n```";
+
+        let (description, tags) = parse_reply(reply, &vocabulary).unwrap();
+        assert_eq!(description.as_deref(), Some("Notes from the API review. Covers pagination."));
+        assert_eq!(tags, vec!["api".to_string(), "meeting-notes".to_string()]);
+
+        assert!(parse_reply("I cannot help with that", &vocabulary).is_err());
+    }
+
+    #[test]
+    fn test_private_items_stay_local() {
+        let settings = EnrichmentSettings {
+            provider: EnrichmentProvider::Openai,
+            model: Some("gpt-4o-mini".to_string()),
+            local_model: Some("llama3:8b".to_string()),
+            ..Default::default()
+        };
+        let enricher = Enricher::new(settings, None);
+
+        assert_eq!(enricher.model_for(false), (EnrichmentProvider::Openai, "gpt-4o-mini".to_string()));
+        assert_eq!(enricher.model_for(true), (EnrichmentProvider::Ollama, "llama3:8b".to_string()));
+    }
+}
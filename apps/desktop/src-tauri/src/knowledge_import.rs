@@ -0,0 +1,525 @@
+use anyhow::{anyhow, Result};
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::knowledge::{lock_manager, KnowledgeItem, SharedKnowledgeManager};
+
+// Single files above this size are skipped
+pub const MAX_IMPORT_FILE_BYTES: u64 = 256 * 1024 * 1024;
+// Archives are unpacked in memory, so cap their total uncompressed size
+pub const MAX_ARCHIVE_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportKind {
+    Directory,
+    Archive,
+    WebPage,
+}
+
+/// Where an imported item came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Provenance {
+    pub kind: ImportKind,
+    pub source: String,                // Directory or archive path, or the page URL
+    pub original_path: Option<String>, // Path of the file within the source
+    pub imported_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportOptions {
+    pub target_folder: Option<String>, // Relative to the knowledge root
+    pub include: Vec<String>,          // Globs; when set, only matching files are imported
+    pub exclude: Vec<String>,
+    pub respect_gitignore: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            target_folder: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_gitignore: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportProgress {
+    pub source: String,
+    pub processed: usize,
+    pub total: usize,
+    pub current: String,
+    pub imported: usize,
+    pub duplicates: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSkip {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub imported: Vec<KnowledgeItem>,
+    pub duplicates: usize,
+    pub skipped: Vec<ImportSkip>,
+}
+
+pub enum ImportOutcome {
+    Imported(KnowledgeItem),
+    Duplicate(KnowledgeItem), // The item already holding these bytes
+    Skipped(String),
+}
+
+enum EntryData {
+    File(PathBuf), // Read when the entry is processed
+    Bytes(Vec<u8>),
+}
+
+struct ImportEntry {
+    relative: PathBuf,
+    data: EntryData,
+}
+
+/// Import a local directory, a .zip/.tar.gz archive or a saved .html/.mhtml page. Sources
+/// are read and unpacked without the manager; it is only locked to store each file.
+pub async fn import_knowledge<F>(manager: &SharedKnowledgeManager, source: &Path, options: &ImportOptions, on_progress: F) -> Result<ImportSummary>
+where
+    F: Fn(&ImportProgress) + Send + Sync,
+{
+    let file_name = source.file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .ok_or_else(|| anyhow!("Invalid import source: {}", source.display()))?;
+
+    let overrides = build_overrides(source, options)?;
+    let (kind, container, entries, origin) = if source.is_dir() {
+        let container = source.file_name().map(|n| n.to_string_lossy().to_string());
+        (ImportKind::Directory, container, directory_entries(source, options, overrides)?, None)
+    } else if file_name.ends_with(".zip") {
+        (ImportKind::Archive, Some(archive_stem(source)), zip_entries(source, &overrides)?, None)
+    } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+        (ImportKind::Archive, Some(archive_stem(source)), tar_gz_entries(source, &overrides)?, None)
+    } else if file_name.ends_with(".html") || file_name.ends_with(".htm") || file_name.ends_with(".mhtml") || file_name.ends_with(".mht") {
+        let (entry, url) = web_page_entry(source)?;
+        (ImportKind::WebPage, None, vec![entry], url)
+    } else {
+        return Err(anyhow!("Unsupported import source: {}", source.display()));
+    };
+
+    let target_folder = options.target_folder.as_deref().map(Path::new);
+    let target = lock_manager(manager).await?.prepare_import_target(target_folder, container.as_deref())?;
+    let source_label = origin.unwrap_or_else(|| source.to_string_lossy().to_string());
+
+    let mut progress = ImportProgress {
+        source: source_label.clone(),
+        processed: 0,
+        total: entries.len(),
+        current: String::new(),
+        imported: 0,
+        duplicates: 0,
+        skipped: 0,
+    };
+    let mut summary = ImportSummary {
+        imported: Vec::new(),
+        duplicates: 0,
+        skipped: Vec::new(),
+    };
+    on_progress(&progress);
+
+    for entry in entries {
+        let display = entry.relative.to_string_lossy().to_string();
+        progress.processed += 1;
+        progress.current = display.clone();
+
+        let content = match entry.data {
+            EntryData::Bytes(bytes) => Ok(bytes),
+            EntryData::File(path) => fs::read(&path),
+        };
+
+        let outcome = match content {
+            Ok(content) => {
+                let provenance = Provenance {
+                    kind: kind.clone(),
+                    source: source_label.clone(),
+                    original_path: Some(display.clone()),
+                    imported_at: Utc::now(),
+                };
+                lock_manager(manager).await?.import_entry(&target, &entry.relative, content, provenance).await?
+            }
+            Err(e) => ImportOutcome::Skipped(e.to_string()),
+        };
+
+        match outcome {
+            ImportOutcome::Imported(item) => {
+                progress.imported += 1;
+                summary.imported.push(item);
+            }
+            ImportOutcome::Duplicate(_) => {
+                progress.duplicates += 1;
+                summary.duplicates += 1;
+            }
+            ImportOutcome::Skipped(reason) => {
+                progress.skipped += 1;
+                summary.skipped.push(ImportSkip { path: display, reason });
+            }
+        }
+
+        on_progress(&progress);
+    }
+
+    lock_manager(manager).await?.refresh_folders(Some(&target), target.parent()).await?;
+
+    Ok(summary)
+}
+
+fn build_overrides(source: &Path, options: &ImportOptions) -> Result<ignore::overrides::Override> {
+    let root = if source.is_dir() { source } else { Path::new("") };
+    let mut builder = ignore::overrides::OverrideBuilder::new(root);
+    for glob in &options.include {
+        builder.add(glob)?;
+    }
+    for glob in &options.exclude {
+        builder.add(&format!("!{}", glob))?;
+    }
+    Ok(builder.build()?)
+}
+
+fn is_excluded(overrides: &ignore::overrides::Override, path: &Path) -> bool {
+    overrides.matched(path, false).is_ignore()
+}
+
+fn directory_entries(source: &Path, options: &ImportOptions, overrides: ignore::overrides::Override) -> Result<Vec<ImportEntry>> {
+    let walker = ignore::WalkBuilder::new(source)
+        .hidden(true)
+        .git_ignore(options.respect_gitignore)
+        .git_exclude(options.respect_gitignore)
+        .git_global(false)
+        .require_git(false)
+        .follow_links(false)
+        .overrides(overrides)
+        .build();
+
+    let mut entries = Vec::new();
+    for result in walker {
+        let entry = result?;
+        let is_file = entry.file_type().map(|t| t.is_file()).unwrap_or(false);
+        if !is_file {
+            continue;
+        }
+        if entry.metadata().map(|m| m.len() > MAX_IMPORT_FILE_BYTES).unwrap_or(true) {
+            continue;
+        }
+
+        let relative = entry.path().strip_prefix(source)?.to_path_buf();
+        entries.push(ImportEntry {
+            relative,
+            data: EntryData::File(entry.into_path()),
+        });
+    }
+
+    Ok(entries)
+}
+
+fn zip_entries(source: &Path, overrides: &ignore::overrides::Override) -> Result<Vec<ImportEntry>> {
+    let mut archive = zip::ZipArchive::new(fs::File::open(source)?)?;
+    let mut entries = Vec::new();
+    let mut total = 0u64;
+
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+        if file.is_dir() || file.is_symlink() {
+            continue;
+        }
+        // enclosed_name rejects absolute paths and `..` components
+        let relative = match file.enclosed_name() {
+            Some(path) => path,
+            None => continue,
+        };
+        if is_excluded(overrides, &relative) {
+            continue;
+        }
+
+        let bytes = read_limited(file, &mut total)?;
+        if let Some(bytes) = bytes {
+            entries.push(ImportEntry {
+                relative,
+                data: EntryData::Bytes(bytes),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+fn tar_gz_entries(source: &Path, overrides: &ignore::overrides::Override) -> Result<Vec<ImportEntry>> {
+    let decoder = flate2::read::GzDecoder::new(fs::File::open(source)?);
+    let mut archive = tar::Archive::new(decoder);
+    let mut entries = Vec::new();
+    let mut total = 0u64;
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        // Only regular files; links could point anywhere
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let relative = entry.path()?.into_owned();
+        if is_excluded(overrides, &relative) {
+            continue;
+        }
+
+        if let Some(bytes) = read_limited(entry, &mut total)? {
+            entries.push(ImportEntry {
+                relative,
+                data: EntryData::Bytes(bytes),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Read an archive member, returning None if it is too large on its own and failing once
+/// the archive as a whole exceeds the unpack limit
+pub fn read_limited<R: Read>(reader: R, total: &mut u64) -> Result<Option<Vec<u8>>> {
+    let mut bytes = Vec::new();
+    reader.take(MAX_IMPORT_FILE_BYTES + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_IMPORT_FILE_BYTES {
+        return Ok(None);
+    }
+
+    *total += bytes.len() as u64;
+    if *total > MAX_ARCHIVE_BYTES {
+        return Err(anyhow!("Archive expands to more than {} bytes", MAX_ARCHIVE_BYTES));
+    }
+    Ok(Some(bytes))
+}
+
+fn archive_stem(source: &Path) -> String {
+    let name = source.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let lower = name.to_lowercase();
+    let cut = [".tar.gz", ".tgz", ".zip"].iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map(|ext| name.len() - ext.len())
+        .unwrap_or(name.len());
+    name[..cut].to_string()
+}
+
+/// A saved page becomes a single .html item; MHTML archives are reduced to their HTML part
+fn web_page_entry(source: &Path) -> Result<(ImportEntry, Option<String>)> {
+    let data = fs::read(source)?;
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = source.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+
+    let (html, url) = if extension == "mhtml" || extension == "mht" {
+        parse_mhtml(&data)?
+    } else {
+        let url = page_url(&String::from_utf8_lossy(&data));
+        (data, url)
+    };
+
+    let url = url.or_else(|| page_url(&String::from_utf8_lossy(&html)));
+    let entry = ImportEntry {
+        relative: PathBuf::from(format!("{}.html", stem)),
+        data: EntryData::Bytes(html),
+    };
+    Ok((entry, url))
+}
+
+/// Original URL of a saved page, from the browser's "saved from" marker or the canonical link
+fn page_url(html: &str) -> Option<String> {
+    static SAVED_FROM: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<!--\s*saved from url=\(\d+\)(\S+?)\s*-->").unwrap());
+    static CANONICAL: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)<link[^>]+rel=["']canonical["'][^>]*href=["']([^"']+)["']"#).unwrap());
+
+    SAVED_FROM.captures(html)
+        .or_else(|| CANONICAL.captures(html))
+        .map(|c| c[1].to_string())
+}
+
+/// Extract the HTML document and its location from an MHTML (multipart/related) file
+fn parse_mhtml(data: &[u8]) -> Result<(Vec<u8>, Option<String>)> {
+    let text = String::from_utf8_lossy(data);
+    let (headers, body) = split_headers(&text)
+        .ok_or_else(|| anyhow!("Malformed MHTML file"))?;
+
+    let boundary = headers.get("content-type")
+        .and_then(|content_type| header_param(content_type, "boundary"))
+        .ok_or_else(|| anyhow!("MHTML file has no multipart boundary"))?;
+    let location = headers.get("snapshot-content-location").cloned();
+
+    for part in body.split(&format!("--{}", boundary)) {
+        let (part_headers, part_body) = match split_headers(part.trim_start_matches(['\r', '\n'])) {
+            Some(split) => split,
+            None => continue,
+        };
+
+        let is_html = part_headers.get("content-type")
+            .map(|t| t.to_lowercase().starts_with("text/html"))
+            .unwrap_or(false);
+        if !is_html {
+            continue;
+        }
+
+        let encoding = part_headers.get("content-transfer-encoding")
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+        let html = match encoding.as_str() {
+            "quoted-printable" => quoted_printable::decode(part_body.as_bytes(), quoted_printable::ParseMode::Robust)?,
+            "base64" => {
+                let compact: String = part_body.chars().filter(|c| !c.is_whitespace()).collect();
+                base64::engine::general_purpose::STANDARD.decode(compact)?
+            }
+            _ => part_body.as_bytes().to_vec(),
+        };
+
+        let location = location.or_else(|| part_headers.get("content-location").cloned());
+        return Ok((html, location));
+    }
+
+    Err(anyhow!("MHTML file contains no HTML document"))
+}
+
+/// Split a MIME block into unfolded, lowercase-keyed headers and its body
+fn split_headers(block: &str) -> Option<(HashMap<String, String>, &str)> {
+    let (head, body) = block.split_once("\r\n\r\n")
+        .or_else(|| block.split_once("\n\n"))?;
+
+    let mut headers: HashMap<String, String> = HashMap::new();
+    let mut last_key: Option<String> = None;
+    for line in head.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(value) = last_key.as_ref().and_then(|key| headers.get_mut(key)) {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            let key = key.trim().to_lowercase();
+            headers.insert(key.clone(), value.trim().to_string());
+            last_key = Some(key);
+        }
+    }
+
+    Some((headers, body))
+}
+
+fn header_param(value: &str, name: &str) -> Option<String> {
+    value.split(';')
+        .filter_map(|param| param.trim().split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.trim().trim_matches('"').to_string())
+}
+
+/// Readable text of an HTML page, for full-text indexing and embeddings
+pub fn html_to_text(html: &str) -> String {
+    static HIDDEN: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?is)<script\b.*?</script>|<style\b.*?</style>|<noscript\b.*?</noscript>|<head\b.*?</head>|<!--.*?-->").unwrap()
+    });
+    static BLOCK: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?i)</?(p|div|br|li|ul|ol|h[1-6]|tr|table|section|article|header|footer|blockquote|pre)\b[^>]*>").unwrap()
+    });
+    static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+    let text = HIDDEN.replace_all(html, " ");
+    let text = BLOCK.replace_all(&text, "\n");
+    let text = TAG.replace_all(&text, " ");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::KnowledgeManager;
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn test_import_directory_and_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = SharedKnowledgeManager::new(Some(KnowledgeManager::new(dir.path().join("knowledge")).unwrap()));
+
+        let docs = dir.path().join("docs");
+        fs::create_dir_all(docs.join("guide")).unwrap();
+        fs::create_dir_all(docs.join("build")).unwrap();
+        fs::write(docs.join(".gitignore"), "build/\n").unwrap();
+        fs::write(docs.join("readme.md"), "Setup instructions").unwrap();
+        fs::write(docs.join("guide/intro.md"), "Getting started").unwrap();
+        fs::write(docs.join("guide/copy.md"), "Getting started").unwrap();
+        fs::write(docs.join("guide/notes.txt"), "Scratch notes").unwrap();
+        fs::write(docs.join("build/output.md"), "Generated").unwrap();
+
+        let options = ImportOptions { exclude: vec!["*.txt".to_string()], ..Default::default() };
+        let summary = import_knowledge(&manager, &docs, &options, |_| {}).await.unwrap();
+        assert_eq!(summary.imported.len(), 2);
+        assert_eq!(summary.duplicates, 1);
+
+        let readme = summary.imported.iter().find(|i| i.name == "readme.md").unwrap();
+        let provenance = readme.provenance.as_ref().unwrap();
+        assert_eq!(provenance.kind, ImportKind::Directory);
+        assert_eq!(provenance.original_path.as_deref(), Some("readme.md"));
+
+        // A zip with one known file, one new file and one trying to escape
+        let archive_path = dir.path().join("bundle.zip");
+        let mut writer = zip::ZipWriter::new(fs::File::create(&archive_path).unwrap());
+        let options_zip = zip::write::SimpleFileOptions::default();
+        for (name, body) in [("readme.md", "Setup instructions"), ("new.md", "Fresh content"), ("../evil.md", "escape")] {
+            writer.start_file(name, options_zip).unwrap();
+            writer.write_all(body.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut events = std::sync::Mutex::new(Vec::new());
+        let summary = import_knowledge(&manager, &archive_path, &ImportOptions::default(), |p| {
+            events.lock().unwrap().push(p.processed);
+            // Other commands can use the knowledge base between files
+            assert!(manager.try_lock().is_ok());
+        }).await.unwrap();
+        assert_eq!(summary.imported.len(), 1);
+        assert_eq!(summary.imported[0].name, "new.md");
+        assert_eq!(summary.duplicates, 1);
+        assert_eq!(events.get_mut().unwrap().last(), Some(&2));
+        assert!(!dir.path().join("knowledge/evil.md").exists());
+    }
+
+    #[test]
+    fn test_parse_mhtml() {
+        let mhtml = "From: <Saved by Blink>\r\n\
+            Snapshot-Content-Location: https://example.com/article\r\n\
+            Content-Type: multipart/related;\r\n\
+            \ttype=\"text/html\";\r\n\
+            \tboundary=\"----Boundary--\"\r\n\
+            \r\n\
+            ------Boundary--\r\n\
+            Content-Type: text/html\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\
+            \r\n\
+            <html><body><p>Caf=C3=A9 menu</p><script>var x=3D1;</script></body></html>\r\n\
+            ------Boundary----\r\n";
+
+        let (html, url) = parse_mhtml(mhtml.as_bytes()).unwrap();
+        assert_eq!(url.as_deref(), Some("https://example.com/article"));
+        assert_eq!(html_to_text(&String::from_utf8(html).unwrap()), "Café menu");
+    }
+}
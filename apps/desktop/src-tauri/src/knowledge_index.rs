@@ -0,0 +1,1207 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+
+use crate::knowledge::{ItemType, KnowledgeItem};
+use crate::knowledge_collections::Collection;
+use crate::knowledge_enrichment::EnrichmentSuggestion;
+use crate::knowledge_import::Provenance;
+use crate::knowledge_links::{self, LinkKind, ParsedLink};
+use crate::knowledge_transcripts::TranscriptRecord;
+use crate::knowledge_versions::KnowledgeVersion;
+
+/// Name of the hidden directory under the knowledge root that holds index files
+pub const INDEX_DIR_NAME: &str = ".index";
+
+#[derive(Debug, Clone)]
+pub struct KeywordHit {
+    pub item_id: String,
+    pub score: f64,
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Fingerprint {
+    pub item_id: String,
+    pub content_hash: Option<String>,
+    pub minhash: Option<Vec<u32>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredLink {
+    pub source_id: String,
+    pub source_path: PathBuf,
+    pub kind: LinkKind,
+    pub target: String,
+    pub target_id: Option<String>,
+}
+
+/// Persistent metadata, full-text and embedding store for the knowledge base
+pub struct KnowledgeIndex {
+    conn: Arc<Mutex<Connection>>,
+    root: PathBuf,
+    changes: broadcast::Sender<()>, // Sent whenever item metadata changes
+    links_stale: AtomicBool,        // Set when files come, go or move, so link targets may be outdated
+}
+
+impl KnowledgeIndex {
+    pub fn open(knowledge_dir: &Path) -> Result<Self> {
+        let index_dir = knowledge_dir.join(INDEX_DIR_NAME);
+        std::fs::create_dir_all(&index_dir)?;
+
+        let conn = Connection::open(index_dir.join("knowledge.db"))?;
+        // Freed pages are zeroed so text dropped for private items is not left on disk
+        conn.pragma_update(None, "secure_delete", true)?;
+        Self::create_tables(&conn)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            root: knowledge_dir.to_path_buf(),
+            changes: broadcast::channel(16).0,
+            links_stale: AtomicBool::new(true),
+        })
+    }
+
+    /// Notified after items are added, removed, moved or have their metadata changed
+    pub fn subscribe(&self) -> broadcast::Receiver<()> {
+        self.changes.subscribe()
+    }
+
+    fn notify(&self) {
+        // No receivers is fine; nobody is watching
+        let _ = self.changes.send(());
+    }
+
+    fn create_tables(conn: &Connection) -> Result<()> {
+        // Item metadata keyed by path so ids survive rescans
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS items (
+                id TEXT PRIMARY KEY,
+                path TEXT NOT NULL UNIQUE,
+                name TEXT NOT NULL,
+                item_type TEXT NOT NULL,
+                size INTEGER NOT NULL DEFAULT 0,
+                modified TEXT NOT NULL,
+                author TEXT NOT NULL,
+                tags TEXT NOT NULL DEFAULT '[]',
+                description TEXT,
+                starred BOOLEAN NOT NULL DEFAULT 0,
+                private BOOLEAN NOT NULL DEFAULT 0,
+                vectorized BOOLEAN NOT NULL DEFAULT 0,
+                embedding_count INTEGER
+            )",
+            [],
+        )?;
+
+        // Full-text index over extracted document text
+        conn.execute(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS items_fts USING fts5(
+                item_id UNINDEXED,
+                name,
+                tags,
+                content,
+                tokenize = 'porter unicode61'
+            )",
+            [],
+        )?;
+
+        // Chunk embeddings, stored as little-endian f32 blobs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS embeddings (
+                item_id TEXT NOT NULL,
+                chunk_index INTEGER NOT NULL,
+                vector BLOB NOT NULL,
+                PRIMARY KEY (item_id, chunk_index)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS index_meta (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Snapshots of previous file contents; the bytes live in the version store
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS versions (
+                id TEXT PRIMARY KEY,
+                item_id TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                reason TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_versions_item ON versions(item_id, created_at)", [])?;
+
+        // Outgoing links parsed from document text, with the item each one resolves to
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS links (
+                source_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                target TEXT NOT NULL,
+                target_key TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_links_source ON links(source_id)", [])?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_links_target ON links(kind, target_key)", [])?;
+        Self::ensure_column(conn, "links", "target_id", "TEXT")?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_links_target_id ON links(target_id)", [])?;
+
+        // Model-generated descriptions and tags waiting for the user to accept them
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS suggestions (
+                item_id TEXT PRIMARY KEY,
+                description TEXT,
+                tags TEXT NOT NULL,
+                model TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                dismissed BOOLEAN NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+
+        // Media items and the transcript documents generated from them
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS transcripts (
+                media_id TEXT PRIMARY KEY,
+                transcript_id TEXT NOT NULL,
+                language TEXT,
+                model TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_transcripts_transcript ON transcripts(transcript_id)", [])?;
+
+        // Saved queries; their items are evaluated when listed
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS collections (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                query TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Columns added after the first release of the index
+        Self::ensure_column(conn, "items", "content_hash", "TEXT")?;
+        Self::ensure_column(conn, "items", "provenance", "TEXT")?;
+        Self::ensure_column(conn, "items", "minhash", "BLOB")?;
+        Self::ensure_column(conn, "items", "sealed_text", "BLOB")?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_items_content_hash ON items(content_hash)", [])?;
+
+        Ok(())
+    }
+
+    fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists = conn.prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))?
+            .exists(params![column])?;
+        if !exists {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        }
+        Ok(())
+    }
+
+    const ITEM_COLUMNS: &'static str = "id, path, name, item_type, size, modified, author, tags, description, starred, private, vectorized, embedding_count, provenance";
+
+    fn row_to_item(row: &Row) -> rusqlite::Result<KnowledgeItem> {
+        let item_type: String = row.get(3)?;
+        let modified: String = row.get(5)?;
+        let tags: String = row.get(7)?;
+
+        Ok(KnowledgeItem {
+            id: row.get(0)?,
+            path: PathBuf::from(row.get::<_, String>(1)?),
+            name: row.get(2)?,
+            item_type: ItemType::parse(&item_type).unwrap_or(ItemType::Document),
+            size: row.get::<_, i64>(4)? as u64,
+            modified: DateTime::parse_from_rfc3339(&modified)
+                .map(|d| d.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+            author: row.get(6)?,
+            tags: serde_json::from_str(&tags).unwrap_or_default(),
+            description: row.get(8)?,
+            starred: row.get(9)?,
+            private: row.get(10)?,
+            vectorized: row.get(11)?,
+            embedding_count: row.get(12)?,
+            provenance: row.get::<_, Option<String>>(13)?
+                .and_then(|p| serde_json::from_str(&p).ok()),
+            children: None,
+            content: None,
+        })
+    }
+
+    /// Insert an item or refresh the filesystem fields of the row with the same path.
+    /// Returns the stored item (keeping its id and user metadata) and whether its
+    /// content changed since it was last indexed.
+    pub async fn upsert_item(&self, item: &KnowledgeItem) -> Result<(KnowledgeItem, bool)> {
+        let conn = self.conn.lock().await;
+        let path = item.path.to_string_lossy().to_string();
+
+        let existing = conn.query_row(
+            &format!("SELECT {} FROM items WHERE path = ?1", Self::ITEM_COLUMNS),
+            params![path],
+            Self::row_to_item,
+        ).optional()?;
+
+        match existing {
+            Some(mut stored) => {
+                let changed = stored.size != item.size || stored.modified != item.modified;
+                if changed || stored.name != item.name || stored.item_type != item.item_type {
+                    self.notify();
+                }
+                stored.name = item.name.clone();
+                stored.item_type = item.item_type.clone();
+                stored.size = item.size;
+                stored.modified = item.modified;
+
+                conn.execute(
+                    "UPDATE items SET name = ?1, item_type = ?2, size = ?3, modified = ?4 WHERE id = ?5",
+                    params![
+                        stored.name,
+                        stored.item_type.as_str(),
+                        stored.size as i64,
+                        stored.modified.to_rfc3339(),
+                        stored.id
+                    ],
+                )?;
+
+                Ok((stored, changed))
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO items (id, path, name, item_type, size, modified, author, tags, description, starred, private, vectorized, embedding_count, provenance)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                    params![
+                        item.id,
+                        path,
+                        item.name,
+                        item.item_type.as_str(),
+                        item.size as i64,
+                        item.modified.to_rfc3339(),
+                        item.author,
+                        serde_json::to_string(&item.tags)?,
+                        item.description,
+                        item.starred,
+                        item.private,
+                        item.vectorized,
+                        item.embedding_count,
+                        item.provenance.as_ref().map(serde_json::to_string).transpose()?
+                    ],
+                )?;
+                self.links_stale.store(true, Ordering::SeqCst);
+                self.notify();
+
+                Ok((item.clone(), true))
+            }
+        }
+    }
+
+    /// Persist the user-editable fields of an item
+    pub async fn update_metadata(&self, item: &KnowledgeItem) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "UPDATE items SET tags = ?1, description = ?2, starred = ?3, private = ?4, vectorized = ?5, embedding_count = ?6
+             WHERE id = ?7",
+            params![
+                serde_json::to_string(&item.tags)?,
+                item.description,
+                item.starred,
+                item.private,
+                item.vectorized,
+                item.embedding_count,
+                item.id
+            ],
+        )?;
+
+        // Keep the tag column of the full-text index in sync
+        conn.execute(
+            "UPDATE items_fts SET tags = ?1 WHERE item_id = ?2",
+            params![item.tags.join(" "), item.id],
+        )?;
+        self.notify();
+
+        Ok(())
+    }
+
+    pub async fn get_item(&self, item_id: &str) -> Result<Option<KnowledgeItem>> {
+        let conn = self.conn.lock().await;
+        let item = conn.query_row(
+            &format!("SELECT {} FROM items WHERE id = ?1", Self::ITEM_COLUMNS),
+            params![item_id],
+            Self::row_to_item,
+        ).optional()?;
+
+        Ok(item)
+    }
+
+    pub async fn get_item_by_path(&self, path: &Path) -> Result<Option<KnowledgeItem>> {
+        let conn = self.conn.lock().await;
+        let item = conn.query_row(
+            &format!("SELECT {} FROM items WHERE path = ?1", Self::ITEM_COLUMNS),
+            params![path.to_string_lossy().to_string()],
+            Self::row_to_item,
+        ).optional()?;
+
+        Ok(item)
+    }
+
+    /// Re-point the row for `from` and every row beneath it at `to`, keeping their ids
+    pub async fn move_path(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut conn = self.conn.lock().await;
+        let from_str = from.to_string_lossy().to_string();
+        let to_str = to.to_string_lossy().to_string();
+        let prefix = format!("{}{}", from_str, std::path::MAIN_SEPARATOR);
+        let name = to.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE items SET path = ?1 || substr(path, ?2) WHERE path = ?3 OR substr(path, 1, ?4) = ?5",
+            params![
+                to_str,
+                from_str.chars().count() as i64 + 1,
+                from_str,
+                prefix.chars().count() as i64,
+                prefix
+            ],
+        )?;
+        tx.execute("UPDATE items SET name = ?1 WHERE path = ?2", params![name, to_str])?;
+        tx.execute(
+            "UPDATE items_fts SET name = ?1 WHERE item_id = (SELECT id FROM items WHERE path = ?2)",
+            params![name, to_str],
+        )?;
+        tx.commit()?;
+        self.links_stale.store(true, Ordering::SeqCst);
+        self.notify();
+
+        Ok(())
+    }
+
+    /// Duplicate a row under a new id and path together with its extracted text,
+    /// and its vectors when `with_vectors` is set
+    pub async fn copy_item(&self, from_id: &str, to: &KnowledgeItem, with_vectors: bool) -> Result<()> {
+        let mut conn = self.conn.lock().await;
+        let path = to.path.to_string_lossy().to_string();
+
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO items (id, path, name, item_type, size, modified, author, tags, description, starred, private, vectorized, embedding_count, content_hash, provenance, minhash, sealed_text)
+             SELECT ?1, ?2, ?3, item_type, ?4, ?5, author, tags, description, starred, private,
+                    CASE WHEN ?6 THEN vectorized ELSE 0 END,
+                    CASE WHEN ?6 THEN embedding_count ELSE NULL END,
+                    content_hash, provenance, minhash, sealed_text
+             FROM items WHERE id = ?7",
+            params![to.id, path, to.name, to.size as i64, to.modified.to_rfc3339(), with_vectors, from_id],
+        )?;
+        tx.execute(
+            "INSERT INTO items_fts (item_id, name, tags, content)
+             SELECT ?1, ?2, tags, content FROM items_fts WHERE item_id = ?3",
+            params![to.id, to.name, from_id],
+        )?;
+        tx.execute(
+            "INSERT INTO links (source_id, kind, target, target_key, target_id)
+             SELECT ?1, kind, target, target_key, target_id FROM links WHERE source_id = ?2",
+            params![to.id, from_id],
+        )?;
+        if with_vectors {
+            tx.execute(
+                "INSERT INTO embeddings (item_id, chunk_index, vector)
+                 SELECT ?1, chunk_index, vector FROM embeddings WHERE item_id = ?2",
+                params![to.id, from_id],
+            )?;
+        }
+        tx.commit()?;
+        self.links_stale.store(true, Ordering::SeqCst);
+        self.notify();
+
+        Ok(())
+    }
+
+    /// Record the SHA-256 of an item's bytes, used to skip re-importing identical files
+    pub async fn set_content_hash(&self, item_id: &str, hash: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute("UPDATE items SET content_hash = ?1 WHERE id = ?2", params![hash, item_id])?;
+        Ok(())
+    }
+
+    pub async fn set_provenance(&self, item_id: &str, provenance: Option<&Provenance>) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "UPDATE items SET provenance = ?1 WHERE id = ?2",
+            params![provenance.map(serde_json::to_string).transpose()?, item_id],
+        )?;
+        Ok(())
+    }
+
+    /// Who made an item and when it last changed, as recorded where it came from
+    pub async fn set_origin(&self, item_id: &str, author: &str, modified: DateTime<Utc>) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "UPDATE items SET author = ?1, modified = ?2 WHERE id = ?3",
+            params![author, modified.to_rfc3339(), item_id],
+        )?;
+        self.notify();
+        Ok(())
+    }
+
+    pub async fn set_minhash(&self, item_id: &str, signature: Option<&[u32]>) -> Result<()> {
+        let conn = self.conn.lock().await;
+        let blob = signature.map(|s| s.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>());
+        conn.execute("UPDATE items SET minhash = ?1 WHERE id = ?2", params![blob, item_id])?;
+        Ok(())
+    }
+
+    /// Content hashes and MinHash signatures of every file
+    pub async fn fingerprints(&self) -> Result<Vec<Fingerprint>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, content_hash, minhash FROM items
+             WHERE item_type != 'folder' AND (content_hash IS NOT NULL OR minhash IS NOT NULL)"
+        )?;
+
+        let fingerprints = stmt.query_map([], |row| {
+            let minhash: Option<Vec<u8>> = row.get(2)?;
+            Ok(Fingerprint {
+                item_id: row.get(0)?,
+                content_hash: row.get(1)?,
+                minhash: minhash.map(|blob| {
+                    blob.chunks_exact(4)
+                        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                        .collect()
+                }),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(fingerprints)
+    }
+
+    pub async fn find_by_hash(&self, hash: &str) -> Result<Option<KnowledgeItem>> {
+        let conn = self.conn.lock().await;
+        let item = conn.query_row(
+            &format!("SELECT {} FROM items WHERE content_hash = ?1 LIMIT 1", Self::ITEM_COLUMNS),
+            params![hash],
+            Self::row_to_item,
+        ).optional()?;
+
+        Ok(item)
+    }
+
+    /// Remove the row for `path` and every row beneath it, returning the removed ids
+    pub async fn remove_path(&self, path: &Path) -> Result<Vec<String>> {
+        let conn = self.conn.lock().await;
+        let path_str = path.to_string_lossy().to_string();
+        let prefix = format!("{}{}", path_str, std::path::MAIN_SEPARATOR);
+
+        let ids = {
+            let mut stmt = conn.prepare(
+                "SELECT id FROM items WHERE path = ?1 OR substr(path, 1, ?2) = ?3"
+            )?;
+            let ids = stmt.query_map(params![path_str, prefix.chars().count() as i64, prefix], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            ids
+        };
+
+        for id in &ids {
+            Self::delete_rows(&conn, id)?;
+        }
+        if !ids.is_empty() {
+            self.links_stale.store(true, Ordering::SeqCst);
+            self.notify();
+        }
+
+        Ok(ids)
+    }
+
+    /// Drop rows whose files no longer exist on disk, returning the removed ids
+    pub async fn prune_missing(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().await;
+
+        let rows = {
+            let mut stmt = conn.prepare("SELECT id, path FROM items")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        let mut removed = Vec::new();
+        for (id, path) in rows {
+            if !Path::new(&path).exists() {
+                Self::delete_rows(&conn, &id)?;
+                removed.push(id);
+            }
+        }
+        if !removed.is_empty() {
+            self.links_stale.store(true, Ordering::SeqCst);
+            self.notify();
+        }
+
+        Ok(removed)
+    }
+
+    fn delete_rows(conn: &Connection, item_id: &str) -> Result<()> {
+        conn.execute("DELETE FROM items WHERE id = ?1", params![item_id])?;
+        conn.execute("DELETE FROM items_fts WHERE item_id = ?1", params![item_id])?;
+        conn.execute("DELETE FROM embeddings WHERE item_id = ?1", params![item_id])?;
+        conn.execute("DELETE FROM versions WHERE item_id = ?1", params![item_id])?;
+        conn.execute("DELETE FROM links WHERE source_id = ?1", params![item_id])?;
+        conn.execute("DELETE FROM suggestions WHERE item_id = ?1", params![item_id])?;
+        conn.execute("DELETE FROM transcripts WHERE media_id = ?1 OR transcript_id = ?1", params![item_id])?;
+        Ok(())
+    }
+
+    // Full-text operations
+    pub async fn set_content(&self, item: &KnowledgeItem, content: &str) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute("DELETE FROM items_fts WHERE item_id = ?1", params![item.id])?;
+        conn.execute(
+            "INSERT INTO items_fts (item_id, name, tags, content) VALUES (?1, ?2, ?3, ?4)",
+            params![item.id, item.name, item.tags.join(" "), content],
+        )?;
+        Ok(())
+    }
+
+    /// Merge full-text segments so text dropped from sealed items is rewritten out of the index
+    pub async fn purge_deleted_text(&self) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute("INSERT INTO items_fts (items_fts) VALUES ('optimize')", [])?;
+        Ok(())
+    }
+
+    /// Encrypted extracted text of a private item, kept out of the full-text index
+    pub async fn set_sealed_text(&self, item_id: &str, sealed: Option<&[u8]>) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute("UPDATE items SET sealed_text = ?1 WHERE id = ?2", params![sealed, item_id])?;
+        Ok(())
+    }
+
+    pub async fn get_sealed_text(&self, item_id: &str) -> Result<Option<Vec<u8>>> {
+        let conn = self.conn.lock().await;
+        let sealed = conn.query_row(
+            "SELECT sealed_text FROM items WHERE id = ?1",
+            params![item_id],
+            |row| row.get::<_, Option<Vec<u8>>>(0),
+        ).optional()?;
+
+        Ok(sealed.flatten())
+    }
+
+    pub async fn get_content(&self, item_id: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().await;
+        let content = conn.query_row(
+            "SELECT content FROM items_fts WHERE item_id = ?1",
+            params![item_id],
+            |row| row.get::<_, String>(0),
+        ).optional()?;
+
+        Ok(content)
+    }
+
+    /// Total size of the extracted text held in the full-text index
+    pub async fn text_bytes(&self) -> Result<u64> {
+        let conn = self.conn.lock().await;
+        let bytes = conn.query_row(
+            "SELECT COALESCE(SUM(length(CAST(content AS BLOB))), 0) FROM items_fts",
+            [],
+            |row| row.get::<_, i64>(0),
+        )?;
+
+        Ok(bytes as u64)
+    }
+
+    /// Run an FTS5 MATCH expression, best BM25 score first
+    pub async fn keyword_search(&self, match_expr: &str, limit: usize) -> Result<Vec<KeywordHit>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT item_id, bm25(items_fts, 5.0, 2.0, 1.0) AS score,
+                    snippet(items_fts, 3, '<mark>', '</mark>', '…', 16)
+             FROM items_fts
+             WHERE items_fts MATCH ?1
+             ORDER BY score
+             LIMIT ?2"
+        )?;
+
+        let hits = stmt.query_map(params![match_expr, limit as i64], |row| {
+            Ok(KeywordHit {
+                item_id: row.get(0)?,
+                score: row.get(1)?,
+                snippet: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(hits)
+    }
+
+    // Link operations
+    /// Replace the links of a document, resolving each target as it is stored
+    pub async fn set_links(&self, source_id: &str, links: &[ParsedLink]) -> Result<()> {
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction()?;
+        let source_path: Option<String> = tx.query_row(
+            "SELECT path FROM items WHERE id = ?1",
+            params![source_id],
+            |row| row.get(0),
+        ).optional()?;
+        let source_path = PathBuf::from(source_path.unwrap_or_default());
+
+        tx.execute("DELETE FROM links WHERE source_id = ?1", params![source_id])?;
+        for link in links {
+            let key = match link.kind {
+                LinkKind::Wiki => knowledge_links::wiki_key(&link.target),
+                LinkKind::Markdown => link.target.clone(),
+                LinkKind::Tag => link.target.to_lowercase(),
+            };
+            let target_id = match link.kind {
+                LinkKind::Wiki => {
+                    let candidates = Self::wiki_candidates(&tx, &key)?;
+                    knowledge_links::nearest(&candidates, &source_path)
+                }
+                LinkKind::Markdown => match knowledge_links::resolve_markdown(&self.root, &source_path, &link.target) {
+                    Some(path) => tx.query_row(
+                        "SELECT id FROM items WHERE path = ?1",
+                        params![path.to_string_lossy()],
+                        |row| row.get(0),
+                    ).optional()?,
+                    None => None,
+                },
+                LinkKind::Tag => None,
+            };
+            tx.execute(
+                "INSERT INTO links (source_id, kind, target, target_key, target_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![source_id, link.kind.as_str(), link.target, key, target_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Files whose name, with or without extension, matches a wiki link key
+    fn wiki_candidates(conn: &Connection, key: &str) -> Result<Vec<(String, PathBuf)>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, path, name FROM items
+             WHERE item_type != 'folder'
+               AND (lower(name) = ?1 OR lower(substr(name, 1, length(?1) + 1)) = ?1 || '.')"
+        )?;
+        let rows = stmt.query_map(params![key], |row| {
+            Ok((row.get::<_, String>(0)?, PathBuf::from(row.get::<_, String>(1)?), row.get::<_, String>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        // The prefix test also matches "plan.v2.md" for "plan"; keep exact names and stems
+        Ok(rows.into_iter()
+            .filter(|(_, _, name)| knowledge_links::wiki_keys_for(name).iter().any(|k| k == key))
+            .map(|(id, path, _)| (id, path))
+            .collect())
+    }
+
+    /// Re-resolve every stored link in one pass after files were added, moved or removed
+    fn refresh_link_targets(&self, conn: &mut Connection) -> Result<()> {
+        if !self.links_stale.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let result = self.resolve_all_links(conn);
+        if result.is_err() {
+            self.links_stale.store(true, Ordering::SeqCst);
+        }
+        result
+    }
+
+    fn resolve_all_links(&self, conn: &mut Connection) -> Result<()> {
+        let mut by_path = HashMap::new();
+        let mut by_key: HashMap<String, Vec<(String, PathBuf)>> = HashMap::new();
+        {
+            let mut stmt = conn.prepare("SELECT id, path, name, item_type FROM items")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+            })?;
+            for row in rows {
+                let (id, path, name, item_type) = row?;
+                if item_type != ItemType::Folder.as_str() {
+                    for key in knowledge_links::wiki_keys_for(&name) {
+                        by_key.entry(key).or_default().push((id.clone(), PathBuf::from(&path)));
+                    }
+                }
+                by_path.insert(PathBuf::from(path), id);
+            }
+        }
+
+        let tx = conn.transaction()?;
+        {
+            let mut links = tx.prepare(
+                "SELECT l.rowid, l.kind, l.target, l.target_key, i.path FROM links l
+                 JOIN items i ON i.id = l.source_id
+                 WHERE l.kind != 'tag'"
+            )?;
+            let rows = links.query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    PathBuf::from(row.get::<_, String>(4)?),
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+            let mut update = tx.prepare("UPDATE links SET target_id = ?1 WHERE rowid = ?2")?;
+            for (rowid, kind, target, key, source_path) in rows {
+                let target_id = match LinkKind::parse(&kind) {
+                    Some(LinkKind::Wiki) => by_key.get(&key)
+                        .and_then(|candidates| knowledge_links::nearest(candidates, &source_path)),
+                    Some(LinkKind::Markdown) => knowledge_links::resolve_markdown(&self.root, &source_path, &target)
+                        .and_then(|path| by_path.get(&path).cloned()),
+                    _ => None,
+                };
+                update.execute(params![target_id, rowid])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn row_to_link(row: &Row) -> rusqlite::Result<StoredLink> {
+        let kind: String = row.get(1)?;
+        Ok(StoredLink {
+            source_id: row.get(0)?,
+            kind: LinkKind::parse(&kind).unwrap_or(LinkKind::Wiki),
+            target: row.get(2)?,
+            source_path: PathBuf::from(row.get::<_, String>(3)?),
+            target_id: row.get(4)?,
+        })
+    }
+
+    /// Every stored link with the path of its source, optionally limited to one source
+    pub async fn links(&self, source_id: Option<&str>) -> Result<Vec<StoredLink>> {
+        let mut conn = self.conn.lock().await;
+        self.refresh_link_targets(&mut conn)?;
+        let mut stmt = conn.prepare(
+            "SELECT l.source_id, l.kind, l.target, i.path, l.target_id FROM links l
+             JOIN items i ON i.id = l.source_id
+             WHERE ?1 IS NULL OR l.source_id = ?1
+             ORDER BY l.rowid"
+        )?;
+        let links = stmt.query_map(params![source_id], Self::row_to_link)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(links)
+    }
+
+    /// Links from other documents that resolve to an item
+    pub async fn links_to(&self, item_id: &str) -> Result<Vec<StoredLink>> {
+        let mut conn = self.conn.lock().await;
+        self.refresh_link_targets(&mut conn)?;
+        let mut stmt = conn.prepare(
+            "SELECT l.source_id, l.kind, l.target, i.path, l.target_id FROM links l
+             JOIN items i ON i.id = l.source_id
+             WHERE l.target_id = ?1 AND l.source_id != ?1
+             ORDER BY l.rowid"
+        )?;
+        let links = stmt.query_map(params![item_id], Self::row_to_link)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(links)
+    }
+
+    /// Document links whose target is not in the knowledge base
+    pub async fn unresolved_links(&self) -> Result<Vec<StoredLink>> {
+        let mut conn = self.conn.lock().await;
+        self.refresh_link_targets(&mut conn)?;
+        let mut stmt = conn.prepare(
+            "SELECT l.source_id, l.kind, l.target, i.path, l.target_id FROM links l
+             JOIN items i ON i.id = l.source_id
+             WHERE l.kind != 'tag' AND l.target_id IS NULL
+             ORDER BY l.rowid"
+        )?;
+        let links = stmt.query_map([], Self::row_to_link)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(links)
+    }
+
+    /// Every file in the index, without folders
+    pub async fn all_items(&self) -> Result<Vec<KnowledgeItem>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM items WHERE item_type != 'folder'",
+            Self::ITEM_COLUMNS
+        ))?;
+        let items = stmt.query_map([], Self::row_to_item)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(items)
+    }
+
+    /// Indexed text of every item, used to backfill links for existing documents
+    pub async fn all_content(&self) -> Result<Vec<(String, String)>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare("SELECT item_id, content FROM items_fts WHERE content != ''")?;
+        let content = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(content)
+    }
+
+    // Enrichment suggestions
+    pub async fn save_suggestion(&self, suggestion: &EnrichmentSuggestion) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "INSERT OR REPLACE INTO suggestions (item_id, description, tags, model, created_at, dismissed) VALUES (?1, ?2, ?3, ?4, ?5, 0)",
+            params![
+                suggestion.item_id,
+                suggestion.description,
+                serde_json::to_string(&suggestion.tags)?,
+                suggestion.model,
+                suggestion.created_at.to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn row_to_suggestion(row: &Row) -> rusqlite::Result<EnrichmentSuggestion> {
+        let tags: String = row.get(2)?;
+        let created_at: String = row.get(4)?;
+        Ok(EnrichmentSuggestion {
+            item_id: row.get(0)?,
+            description: row.get(1)?,
+            tags: serde_json::from_str(&tags).unwrap_or_default(),
+            model: row.get(3)?,
+            created_at: DateTime::parse_from_rfc3339(&created_at)
+                .map(|d| d.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+        })
+    }
+
+    pub async fn get_suggestion(&self, item_id: &str) -> Result<Option<EnrichmentSuggestion>> {
+        let conn = self.conn.lock().await;
+        let suggestion = conn.query_row(
+            "SELECT item_id, description, tags, model, created_at FROM suggestions WHERE item_id = ?1 AND dismissed = 0",
+            params![item_id],
+            Self::row_to_suggestion,
+        ).optional()?;
+        Ok(suggestion)
+    }
+
+    /// Pending suggestions, newest first
+    pub async fn list_suggestions(&self) -> Result<Vec<EnrichmentSuggestion>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT item_id, description, tags, model, created_at FROM suggestions
+             WHERE dismissed = 0 ORDER BY created_at DESC"
+        )?;
+        let suggestions = stmt.query_map([], Self::row_to_suggestion)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(suggestions)
+    }
+
+    /// Keep the row so the batch job does not suggest the same item again
+    pub async fn dismiss_suggestion(&self, item_id: &str) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute("UPDATE suggestions SET dismissed = 1 WHERE item_id = ?1", params![item_id])?;
+        Ok(())
+    }
+
+    pub async fn delete_suggestion(&self, item_id: &str) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute("DELETE FROM suggestions WHERE item_id = ?1", params![item_id])?;
+        Ok(())
+    }
+
+    /// Documents and datasets with no description and no pending or dismissed suggestion
+    pub async fn enrichment_candidates(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id FROM items
+             WHERE item_type IN ('document', 'dataset')
+               AND description IS NULL
+               AND id NOT IN (SELECT item_id FROM suggestions)
+             ORDER BY modified DESC"
+        )?;
+        let ids = stmt.query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
+    // Transcripts
+    pub async fn set_transcript(&self, record: &TranscriptRecord) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "INSERT OR REPLACE INTO transcripts (media_id, transcript_id, language, model, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                record.media_id,
+                record.transcript_id,
+                record.language,
+                record.model,
+                record.created_at.to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub async fn transcript_for(&self, media_id: &str) -> Result<Option<TranscriptRecord>> {
+        let conn = self.conn.lock().await;
+        let record = conn.query_row(
+            "SELECT media_id, transcript_id, language, model, created_at FROM transcripts WHERE media_id = ?1",
+            params![media_id],
+            |row| {
+                let created_at: String = row.get(4)?;
+                Ok(TranscriptRecord {
+                    media_id: row.get(0)?,
+                    transcript_id: row.get(1)?,
+                    language: row.get(2)?,
+                    model: row.get(3)?,
+                    created_at: DateTime::parse_from_rfc3339(&created_at)
+                        .map(|d| d.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            },
+        ).optional()?;
+        Ok(record)
+    }
+
+    /// The media item a transcript document was generated from
+    pub async fn transcript_source(&self, transcript_id: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().await;
+        let media_id = conn.query_row(
+            "SELECT media_id FROM transcripts WHERE transcript_id = ?1",
+            params![transcript_id],
+            |row| row.get(0),
+        ).optional()?;
+        Ok(media_id)
+    }
+
+    // Collections
+    pub async fn save_collection(&self, collection: &Collection) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "INSERT OR REPLACE INTO collections (id, name, query, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                collection.id,
+                collection.name,
+                collection.query,
+                collection.created_at.to_rfc3339(),
+                collection.updated_at.to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn row_to_collection(row: &Row) -> rusqlite::Result<Collection> {
+        let parse_time = |value: String| DateTime::parse_from_rfc3339(&value)
+            .map(|d| d.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+        Ok(Collection {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            query: row.get(2)?,
+            created_at: parse_time(row.get(3)?),
+            updated_at: parse_time(row.get(4)?),
+        })
+    }
+
+    pub async fn get_collection(&self, collection_id: &str) -> Result<Option<Collection>> {
+        let conn = self.conn.lock().await;
+        let collection = conn.query_row(
+            "SELECT id, name, query, created_at, updated_at FROM collections WHERE id = ?1",
+            params![collection_id],
+            Self::row_to_collection,
+        ).optional()?;
+        Ok(collection)
+    }
+
+    pub async fn list_collections(&self) -> Result<Vec<Collection>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, name, query, created_at, updated_at FROM collections ORDER BY name COLLATE NOCASE"
+        )?;
+        let collections = stmt.query_map([], Self::row_to_collection)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(collections)
+    }
+
+    pub async fn delete_collection(&self, collection_id: &str) -> Result<bool> {
+        let conn = self.conn.lock().await;
+        let deleted = conn.execute("DELETE FROM collections WHERE id = ?1", params![collection_id])?;
+        Ok(deleted > 0)
+    }
+
+    // Embedding operations
+    pub async fn save_embeddings(&self, item_id: &str, vectors: &[Vec<f32>]) -> Result<()> {
+        let blobs: Vec<Vec<u8>> = vectors.iter().map(|v| encode_vector(v)).collect();
+        self.save_embedding_blobs(item_id, &blobs).await
+    }
+
+    /// Store raw vector blobs for an item, replacing any it had. Private items store them encrypted.
+    pub async fn save_embedding_blobs(&self, item_id: &str, blobs: &[Vec<u8>]) -> Result<()> {
+        let mut conn = self.conn.lock().await;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM embeddings WHERE item_id = ?1", params![item_id])?;
+        for (index, blob) in blobs.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO embeddings (item_id, chunk_index, vector) VALUES (?1, ?2, ?3)",
+                params![item_id, index as i64, blob],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub async fn embedding_blobs(&self, item_id: &str) -> Result<Vec<Vec<u8>>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT vector FROM embeddings WHERE item_id = ?1 ORDER BY chunk_index"
+        )?;
+        let blobs = stmt.query_map(params![item_id], |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(blobs)
+    }
+
+    /// Vectors of every public item; private items keep theirs encrypted
+    pub async fn load_embeddings(&self) -> Result<HashMap<String, Vec<Vec<f32>>>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT e.item_id, e.vector FROM embeddings e
+             JOIN items i ON i.id = e.item_id
+             WHERE i.private = 0
+             ORDER BY e.item_id, e.chunk_index"
+        )?;
+
+        let mut embeddings: HashMap<String, Vec<Vec<f32>>> = HashMap::new();
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))?;
+        for row in rows {
+            let (item_id, blob) = row?;
+            embeddings.entry(item_id).or_default().push(decode_vector(&blob));
+        }
+
+        Ok(embeddings)
+    }
+
+    /// Number of stored chunks per public item, to check the ANN index against
+    pub async fn embedding_counts(&self) -> Result<HashMap<String, usize>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT e.item_id, COUNT(*) FROM embeddings e
+             JOIN items i ON i.id = e.item_id
+             WHERE i.private = 0
+             GROUP BY e.item_id"
+        )?;
+
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Forget every stored vector, e.g. after the embedding model changed
+    pub async fn clear_embeddings(&self) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute("DELETE FROM embeddings", [])?;
+        conn.execute("UPDATE items SET vectorized = 0, embedding_count = NULL", [])?;
+        Ok(())
+    }
+
+    // Version history
+    pub async fn add_version(&self, version: &KnowledgeVersion) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "INSERT INTO versions (id, item_id, content_hash, size, created_at, reason) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                version.id,
+                version.item_id,
+                version.content_hash,
+                version.size as i64,
+                version.created_at.to_rfc3339(),
+                version.reason
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Versions of an item, newest first
+    pub async fn list_versions(&self, item_id: &str) -> Result<Vec<KnowledgeVersion>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, item_id, content_hash, size, created_at, reason FROM versions
+             WHERE item_id = ?1 ORDER BY created_at DESC, rowid DESC"
+        )?;
+
+        let versions = stmt.query_map(params![item_id], |row| {
+            let created_at: String = row.get(4)?;
+            Ok(KnowledgeVersion {
+                id: row.get(0)?,
+                item_id: row.get(1)?,
+                content_hash: row.get(2)?,
+                size: row.get::<_, i64>(3)? as u64,
+                created_at: DateTime::parse_from_rfc3339(&created_at)
+                    .map(|d| d.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+                reason: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(versions)
+    }
+
+    pub async fn delete_versions(&self, ids: &[String]) -> Result<()> {
+        let conn = self.conn.lock().await;
+        for id in ids {
+            conn.execute("DELETE FROM versions WHERE id = ?1", params![id])?;
+        }
+        Ok(())
+    }
+
+    /// Point a version at a re-encoded blob, e.g. after its item became private
+    pub async fn set_version_hash(&self, version_id: &str, hash: &str) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute("UPDATE versions SET content_hash = ?1 WHERE id = ?2", params![hash, version_id])?;
+        Ok(())
+    }
+
+    /// Hashes still referenced by some version
+    pub async fn version_hashes(&self) -> Result<HashSet<String>> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn.prepare("SELECT DISTINCT content_hash FROM versions")?;
+        let hashes = stmt.query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<HashSet<_>, _>>()?;
+        Ok(hashes)
+    }
+
+    // Index metadata
+    pub async fn get_meta(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().await;
+        let value = conn.query_row(
+            "SELECT value FROM index_meta WHERE key = ?1",
+            params![key],
+            |row| row.get::<_, String>(0),
+        ).optional()?;
+
+        Ok(value)
+    }
+
+    pub async fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "INSERT OR REPLACE INTO index_meta (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+}
+
+pub fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub fn decode_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
@@ -0,0 +1,294 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+use crate::knowledge::KnowledgeItem;
+
+/// Deepest neighbourhood `knowledge_graph` will expand
+pub const MAX_GRAPH_DEPTH: usize = 3;
+
+static WIKI_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"!?\[\[([^\[\]\n]+?)\]\]").unwrap());
+static MARKDOWN_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"!?\[[^\]\n]*\]\(([^)\n]+)\)").unwrap());
+static HASHTAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:^|\s)#([A-Za-z][\w/-]*)").unwrap());
+static INLINE_CODE: Lazy<Regex> = Lazy::new(|| Regex::new(r"`[^`\n]*`").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    Wiki,     // [[Note Name]], matched against file names
+    Markdown, // [text](relative/path.md), resolved against the linking document
+    Tag,      // #tag in the text
+}
+
+impl LinkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkKind::Wiki => "wiki",
+            LinkKind::Markdown => "markdown",
+            LinkKind::Tag => "tag",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "wiki" => Some(LinkKind::Wiki),
+            "markdown" => Some(LinkKind::Markdown),
+            "tag" => Some(LinkKind::Tag),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedLink {
+    pub kind: LinkKind,
+    pub target: String, // Wiki name, relative path or tag, without alias or #heading
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeLink {
+    pub source_id: String,
+    pub kind: LinkKind,
+    pub target: String,
+    pub target_id: Option<String>, // None when nothing in the knowledge base matches
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: String,                 // Item id, or "tag:<name>" for tags
+    pub label: String,
+    pub item: Option<KnowledgeItem>,
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: LinkKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Wiki links, relative Markdown links and hashtags in a document, outside code
+pub fn parse_links(text: &str) -> Vec<ParsedLink> {
+    let mut links: Vec<ParsedLink> = Vec::new();
+    let mut push = |link: ParsedLink| {
+        if !link.target.is_empty() && !links.contains(&link) {
+            links.push(link);
+        }
+    };
+
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let line = INLINE_CODE.replace_all(line, "");
+
+        for capture in WIKI_LINK.captures_iter(&line) {
+            let inner = &capture[1];
+            let target = inner.split('|').next().unwrap_or(inner);
+            let target = target.split('#').next().unwrap_or(target).trim();
+            push(ParsedLink { kind: LinkKind::Wiki, target: target.to_string() });
+        }
+
+        for capture in MARKDOWN_LINK.captures_iter(&line) {
+            if let Some(target) = markdown_target(&capture[1]) {
+                push(ParsedLink { kind: LinkKind::Markdown, target });
+            }
+        }
+
+        for capture in HASHTAG.captures_iter(&line) {
+            let tag = capture[1].trim_end_matches(['/', '-']).to_lowercase();
+            push(ParsedLink { kind: LinkKind::Tag, target: tag });
+        }
+    }
+
+    links
+}
+
+/// The local path a Markdown link points at, or None for URLs and in-page anchors
+fn markdown_target(destination: &str) -> Option<String> {
+    let destination = destination.trim();
+    let destination = match destination.strip_prefix('<') {
+        Some(rest) => rest.split('>').next().unwrap_or(rest),
+        None => destination.split_whitespace().next().unwrap_or(""), // Drop a "title"
+    };
+
+    if destination.is_empty() || destination.starts_with('#') || destination.contains("://")
+        || destination.starts_with("mailto:") || destination.starts_with("data:")
+    {
+        return None;
+    }
+
+    let path = destination.split(['#', '?']).next().unwrap_or("");
+    let decoded = percent_decode(path);
+    if decoded.is_empty() { None } else { Some(decoded) }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                .filter(|h| h.chars().all(|c| c.is_ascii_hexdigit()));
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Key wiki links are matched on: the lowercased name without extension or folders
+pub fn wiki_key(target: &str) -> String {
+    let name = target.rsplit('/').next().unwrap_or(target);
+    name.trim().to_lowercase()
+}
+
+/// Names an item answers to in wiki links: with and without its extension
+pub fn wiki_keys_for(name: &str) -> Vec<String> {
+    let lower = name.to_lowercase();
+    let mut keys = vec![lower.clone()];
+    if let Some(stem) = Path::new(&lower).file_stem().map(|s| s.to_string_lossy().to_string()) {
+        if stem != lower {
+            keys.push(stem);
+        }
+    }
+    keys
+}
+
+/// Pick the target of a wiki link among files sharing its name, preferring the one next to
+/// the linking document
+pub fn nearest(candidates: &[(String, PathBuf)], source: &Path) -> Option<String> {
+    candidates.iter()
+        .find(|(_, path)| path.parent() == source.parent())
+        .or(candidates.first())
+        .map(|(id, _)| id.clone())
+}
+
+/// Where a relative Markdown link from `source` points, normalized without touching
+/// the filesystem. A leading '/' is taken relative to the knowledge root.
+pub fn resolve_markdown(root: &Path, source: &Path, target: &str) -> Option<PathBuf> {
+    let base = match target.strip_prefix('/') {
+        Some(_) => root.to_path_buf(),
+        None => source.parent()?.to_path_buf(),
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in base.join(target.trim_start_matches('/')).components() {
+        match component {
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return None;
+                }
+            }
+            Component::CurDir => {}
+            other => resolved.push(other),
+        }
+    }
+
+    if resolved.starts_with(root) { Some(resolved) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::KnowledgeManager;
+
+    #[test]
+    fn test_parse_links() {
+        let text = "See [[Project Plan]] and [[roadmap#Q3|the roadmap]], plus ![[diagram.png]].\n\
+                    Details in [notes](../meetings/2024%20kickoff.md#agenda \"Kickoff\") and [site](https://example.com).\n\
+                    # Heading, not a tag\n\
+                    Filed under #planning and #team/infra. Issue #42 is not a tag.\n\
+                    ```\n[[Inside Code]] #ignored\n```\n\
+                    Inline `[[also ignored]]` code.";
+
+        let links = parse_links(text);
+        let targets: Vec<(LinkKind, &str)> = links.iter().map(|l| (l.kind, l.target.as_str())).collect();
+        assert_eq!(targets, vec![
+            (LinkKind::Wiki, "Project Plan"),
+            (LinkKind::Wiki, "roadmap"),
+            (LinkKind::Wiki, "diagram.png"),
+            (LinkKind::Markdown, "../meetings/2024 kickoff.md"),
+            (LinkKind::Tag, "planning"),
+            (LinkKind::Tag, "team/infra"),
+        ]);
+    }
+
+    #[test]
+    fn test_resolve_markdown() {
+        let root = Path::new("/kb");
+        let source = Path::new("/kb/documents/notes/today.md");
+        assert_eq!(resolve_markdown(root, source, "../plan.md"), Some(PathBuf::from("/kb/documents/plan.md")));
+        assert_eq!(resolve_markdown(root, source, "/datasets/sales.csv"), Some(PathBuf::from("/kb/datasets/sales.csv")));
+        assert_eq!(resolve_markdown(root, source, "../../../etc/passwd"), None);
+    }
+
+    #[tokio::test]
+    async fn test_backlinks_and_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+        manager.create_folder(Some(&docs), "meetings").await.unwrap();
+        let meetings = docs.join("meetings");
+
+        let plan = manager.upload_file(Some(&docs), "Project Plan.md", b"Goals for the year. #planning".to_vec(), false).await.unwrap();
+        let kickoff = manager.upload_file(
+            Some(&meetings),
+            "kickoff.md",
+            b"We agreed on [[Project Plan]] and the [budget](../budget.md). See [[Retro]].".to_vec(),
+            false,
+        ).await.unwrap();
+        let budget = manager.upload_file(Some(&docs), "budget.md", b"Numbers".to_vec(), false).await.unwrap();
+
+        let outgoing = manager.outgoing_links(&kickoff.id).await.unwrap();
+        let resolved = |target: &str| outgoing.iter().find(|l| l.target == target).and_then(|l| l.target_id.clone());
+        assert_eq!(outgoing.len(), 3);
+        assert_eq!(resolved("Project Plan"), Some(plan.id.clone()));
+        assert_eq!(resolved("../budget.md"), Some(budget.id.clone()));
+
+        let backlinks = manager.backlinks(&budget.id).await.unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].source_id, kickoff.id);
+
+        let broken = manager.broken_links().await.unwrap();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].target, "Retro");
+
+        // Creating the missing note repairs the link
+        manager.upload_file(Some(&meetings), "Retro.md", b"What went well".to_vec(), false).await.unwrap();
+        assert!(manager.broken_links().await.unwrap().is_empty());
+
+        let graph = manager.knowledge_graph(&plan.id, 2).await.unwrap();
+        let ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert!(ids.contains(&kickoff.id.as_str()));
+        assert!(ids.contains(&budget.id.as_str()));
+        assert!(ids.contains(&"tag:planning"));
+        assert!(graph.edges.iter().any(|e| e.source == kickoff.id && e.target == plan.id && e.kind == LinkKind::Wiki));
+
+        // Stored targets follow files as they are renamed
+        manager.rename_item(&budget.id, "costs.md").await.unwrap();
+        assert!(manager.backlinks(&budget.id).await.unwrap().is_empty());
+        let broken = manager.broken_links().await.unwrap();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].target, "../budget.md");
+    }
+}
@@ -0,0 +1,429 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::embeddings;
+use crate::knowledge::KnowledgeItem;
+use crate::whisper::WhisperCpp;
+
+// Audio is decoded to 16 kHz mono 16-bit PCM, the input whisper expects
+const SAMPLE_RATE: u32 = 16_000;
+const BYTES_PER_SECOND: usize = SAMPLE_RATE as usize * 2;
+// The OpenAI endpoint rejects uploads over 25 MB, so longer audio is sent in pieces
+const OPENAI_CHUNK_SECONDS: usize = 10 * 60;
+
+static SEGMENT_LINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[(\d+):(\d{2}):(\d{2})\]\s+(.*)$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptionProvider {
+    Local,  // whisper-cpp on this machine
+    Openai, // OpenAI transcription API (leaves the machine)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptionSettings {
+    pub provider: TranscriptionProvider,
+    pub model: Option<String>,    // OpenAI model, defaults to whisper-1
+    pub local_model: String,      // whisper-cpp model name, also used for private items
+    pub language: Option<String>, // None lets the model detect it
+}
+
+impl Default for TranscriptionSettings {
+    fn default() -> Self {
+        Self {
+            provider: TranscriptionProvider::Local,
+            model: None,
+            local_model: "base".to_string(),
+            language: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    pub start: f64, // Seconds from the start of the media
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcript {
+    pub segments: Vec<TranscriptSegment>,
+    pub language: Option<String>,
+    pub model: String,
+}
+
+/// Which sidecar document holds the transcript of a media item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptRecord {
+    pub media_id: String,
+    pub transcript_id: String,
+    pub language: Option<String>,
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptionStage {
+    Extracting,
+    Transcribing,
+    Indexing,
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptionProgress {
+    pub item_id: String,
+    pub stage: TranscriptionStage,
+}
+
+#[derive(Debug, Clone)]
+pub struct Transcriber {
+    settings: TranscriptionSettings,
+    api_key: Option<String>,
+}
+
+impl Transcriber {
+    pub fn new(settings: TranscriptionSettings, api_key: Option<String>) -> Self {
+        Self { settings, api_key }
+    }
+
+    /// Private items are always transcribed locally
+    pub fn provider_for(&self, private: bool) -> TranscriptionProvider {
+        match self.settings.provider {
+            TranscriptionProvider::Openai if !private => TranscriptionProvider::Openai,
+            _ => TranscriptionProvider::Local,
+        }
+    }
+
+    /// `scratch_dir` holds the decoded audio whisper-cpp reads, for as long as it runs
+    pub async fn transcribe(&self, pcm: &[u8], private: bool, scratch_dir: &Path) -> Result<Transcript> {
+        let language = self.settings.language.as_deref();
+        match self.provider_for(private) {
+            TranscriptionProvider::Local => self.transcribe_local(pcm, language, scratch_dir).await,
+            TranscriptionProvider::Openai => self.transcribe_openai(pcm, language).await,
+        }
+    }
+
+    async fn transcribe_local(&self, pcm: &[u8], language: Option<&str>, scratch_dir: &Path) -> Result<Transcript> {
+        // Same models directory as the voice system, so downloaded models are shared
+        let models_dir = dirs::data_dir()
+            .map(|d| d.join("LocalBrain").join("models"))
+            .unwrap_or_else(|| PathBuf::from("./models"));
+        let model_name = &self.settings.local_model;
+        let model_path = models_dir.join(format!("ggml-{}.bin", model_name.replace(".en", "-en")));
+        if !model_path.exists() {
+            WhisperCpp::download_model(model_name, &models_dir).await?;
+        }
+        let whisper = WhisperCpp::new(model_path)?;
+
+        // Decoded audio and whisper's output live in an owner-only directory in app data,
+        // never the shared temp directory, and are removed as soon as whisper exits
+        std::fs::create_dir_all(scratch_dir)?;
+        let work_dir = tempfile::Builder::new().prefix("transcribe-").tempdir_in(scratch_dir)?;
+        let mut audio_file = tempfile::Builder::new().suffix(".wav").tempfile_in(work_dir.path())?;
+        audio_file.write_all(&wav_from_pcm(pcm))?;
+        let result = whisper.transcribe_file_in(audio_file.path(), language, work_dir.path()).await;
+        drop(audio_file);
+        drop(work_dir);
+        let result = result?;
+
+        Ok(Transcript {
+            segments: result.segments.into_iter()
+                .map(|s| TranscriptSegment { start: s.start as f64, end: s.end as f64, text: s.text })
+                .collect(),
+            language: result.language.or_else(|| language.map(|l| l.to_string())),
+            model: format!("whisper-cpp:{}", model_name),
+        })
+    }
+
+    async fn transcribe_openai(&self, pcm: &[u8], language: Option<&str>) -> Result<Transcript> {
+        let api_key = match self.api_key.as_ref() {
+            Some(key) => key.clone(),
+            None => crate::config::get_openai_api_key()
+                .ok_or_else(|| anyhow!("OpenAI API key not configured"))?,
+        };
+        let model = self.settings.model.clone().unwrap_or_else(|| "whisper-1".to_string());
+        let url = format!("{}{}", crate::config::CONFIG.api.openai_base_url, crate::config::CONFIG.api.openai_stt_endpoint);
+        let client = reqwest::Client::new();
+
+        let mut transcript = Transcript {
+            segments: Vec::new(),
+            language: language.map(|l| l.to_string()),
+            model: format!("openai:{}", model),
+        };
+
+        for (index, chunk) in pcm.chunks(OPENAI_CHUNK_SECONDS * BYTES_PER_SECOND).enumerate() {
+            let offset = (index * OPENAI_CHUNK_SECONDS) as f64;
+
+            let mut form = reqwest::multipart::Form::new()
+                .text("model", model.clone())
+                .text("response_format", "verbose_json")
+                .text("timestamp_granularities[]", "segment")
+                .part("file", reqwest::multipart::Part::bytes(wav_from_pcm(chunk))
+                    .file_name("audio.wav")
+                    .mime_str("audio/wav")?);
+            if let Some(language) = language {
+                form = form.text("language", language.to_string());
+            }
+
+            let response = client
+                .post(&url)
+                .header("Authorization", format!("Bearer {}", api_key))
+                .multipart(form)
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                return Err(anyhow!("Whisper API error: {}", error_text));
+            }
+
+            let result: serde_json::Value = response.json().await?;
+            if transcript.language.is_none() {
+                transcript.language = result["language"].as_str().map(|l| l.to_string());
+            }
+            transcript.segments.extend(parse_verbose_json(&result, offset));
+        }
+
+        Ok(transcript)
+    }
+}
+
+/// Everything needed to transcribe a media item, gathered under the knowledge lock so
+/// the slow decoding and transcription can run without holding it
+pub struct TranscriptionJob {
+    pub media: KnowledgeItem,
+    pub(crate) path: PathBuf,
+    pub(crate) decrypted: Option<Vec<u8>>,
+    pub(crate) transcriber: Transcriber,
+    pub(crate) scratch_dir: PathBuf,
+}
+
+impl TranscriptionJob {
+    pub async fn run<F>(self, on_progress: F) -> Result<(KnowledgeItem, Transcript)>
+    where
+        F: Fn(&TranscriptionProgress),
+    {
+        let report = |stage| on_progress(&TranscriptionProgress { item_id: self.media.id.clone(), stage });
+
+        report(TranscriptionStage::Extracting);
+        let pcm = extract_audio(self.path.clone(), self.decrypted).await?;
+
+        report(TranscriptionStage::Transcribing);
+        let transcript = self.transcriber.transcribe(&pcm, self.media.private, &self.scratch_dir).await?;
+        if transcript.segments.is_empty() {
+            return Err(anyhow!("No speech found in {}", self.media.name));
+        }
+
+        Ok((self.media, transcript))
+    }
+}
+
+/// Segments from an OpenAI `verbose_json` reply, shifted by where the chunk starts
+pub fn parse_verbose_json(result: &serde_json::Value, offset: f64) -> Vec<TranscriptSegment> {
+    result["segments"].as_array().into_iter().flatten()
+        .map(|segment| TranscriptSegment {
+            start: offset + segment["start"].as_f64().unwrap_or(0.0),
+            end: offset + segment["end"].as_f64().unwrap_or(0.0),
+            text: segment["text"].as_str().unwrap_or("").trim().to_string(),
+        })
+        .filter(|segment| !segment.text.is_empty())
+        .collect()
+}
+
+/// Decode the audio track of a media file to 16 kHz mono PCM with ffmpeg. `decrypted`
+/// content of a private item is piped in so it never reaches a temporary file.
+pub async fn extract_audio(path: PathBuf, decrypted: Option<Vec<u8>>) -> Result<Vec<u8>> {
+    let ffmpeg = which::which("ffmpeg")
+        .or_else(|_| {
+            ["/usr/local/bin/ffmpeg", "/opt/homebrew/bin/ffmpeg"].into_iter()
+                .find(|p| std::path::Path::new(p).exists())
+                .map(PathBuf::from)
+                .ok_or_else(|| anyhow!("ffmpeg not found; it is needed to extract audio"))
+        })?;
+
+    let output = tokio::task::spawn_blocking(move || -> Result<std::process::Output> {
+        let input = match decrypted {
+            Some(_) => "pipe:0".into(),
+            None => path.as_os_str().to_owned(),
+        };
+
+        let mut child = Command::new(ffmpeg)
+            .args(["-hide_banner", "-loglevel", "error", "-i"])
+            .arg(input)
+            .args(["-vn", "-ac", "1", "-ar", &SAMPLE_RATE.to_string(), "-f", "s16le", "-acodec", "pcm_s16le", "pipe:1"])
+            .stdin(if decrypted.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // Feed stdin from another thread so a full stdout pipe cannot deadlock us
+        let writer = match (decrypted, child.stdin.take()) {
+            (Some(data), Some(mut stdin)) => Some(std::thread::spawn(move || {
+                let _ = stdin.write_all(&data); // ffmpeg may stop reading early
+            })),
+            _ => None,
+        };
+        let output = child.wait_with_output()?;
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+        Ok(output)
+    }).await??;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Audio extraction failed: {}", stderr.trim()));
+    }
+    if output.stdout.is_empty() {
+        return Err(anyhow!("No audio track found"));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Wrap 16 kHz mono 16-bit PCM in a WAV header
+pub fn wav_from_pcm(pcm: &[u8]) -> Vec<u8> {
+    let data_len = pcm.len() as u32;
+    let mut wav = Vec::with_capacity(44 + pcm.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());                 // fmt chunk size
+    wav.extend_from_slice(&1u16.to_le_bytes());                  // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes());                  // Mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(BYTES_PER_SECOND as u32).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());                  // Block align
+    wav.extend_from_slice(&16u16.to_le_bytes());                 // Bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.extend_from_slice(pcm);
+    wav
+}
+
+pub fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    format!("{:02}:{:02}:{:02}", total / 3600, total / 60 % 60, total % 60)
+}
+
+/// Markdown sidecar for a media file: a link back to it, then one timestamped line per segment
+pub fn render_transcript(media_name: &str, transcript: &Transcript) -> String {
+    let mut text = format!("# Transcript of {}\n\nSource: [{}](<{}>)\n", media_name, media_name, media_name);
+    if let Some(language) = &transcript.language {
+        text.push_str(&format!("Language: {}\n", language));
+    }
+    text.push_str(&format!("Model: {}\n\n", transcript.model));
+
+    for segment in &transcript.segments {
+        text.push_str(&format!("[{}] {}\n", format_timestamp(segment.start), segment.text));
+    }
+    text
+}
+
+/// Start time of the transcript line matching the most query terms
+pub fn locate(transcript: &str, terms: &[String]) -> Option<f64> {
+    let mut best: Option<(usize, f64)> = None;
+
+    for line in transcript.lines() {
+        let Some(captures) = SEGMENT_LINE.captures(line.trim()) else {
+            continue;
+        };
+        let tokens = embeddings::tokenize(&captures[4]);
+        let matched = terms.iter().filter(|term| tokens.contains(term)).count();
+        if matched == 0 || best.is_some_and(|(most, _)| matched <= most) {
+            continue;
+        }
+
+        let part = |i: usize| captures[i].parse::<f64>().unwrap_or(0.0);
+        best = Some((matched, part(1) * 3600.0 + part(2) * 60.0 + part(3)));
+    }
+
+    best.map(|(_, start)| start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::KnowledgeManager;
+
+    fn sample_transcript() -> Transcript {
+        Transcript {
+            segments: vec![
+                TranscriptSegment { start: 0.0, end: 4.2, text: "Welcome to the quarterly review.".to_string() },
+                TranscriptSegment { start: 65.5, end: 71.0, text: "The hiring budget for next year is approved.".to_string() },
+                TranscriptSegment { start: 3725.0, end: 3730.0, text: "Budget questions go to finance.".to_string() },
+            ],
+            language: Some("en".to_string()),
+            model: "whisper-cpp:base".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_and_locate() {
+        let text = render_transcript("q3 review.mp4", &sample_transcript());
+        assert!(text.contains("Source: [q3 review.mp4](<q3 review.mp4>)"));
+        assert!(text.contains("[00:01:05] The hiring budget"));
+        assert!(text.contains("[01:02:05] Budget questions"));
+
+        let terms = |query: &str| embeddings::tokenize(query);
+        assert_eq!(locate(&text, &terms("hiring budget")), Some(65.0));
+        assert_eq!(locate(&text, &terms("finance")), Some(3725.0));
+        assert_eq!(locate(&text, &terms("holiday")), None);
+
+        let wav = wav_from_pcm(&[0u8; 64]);
+        assert_eq!(wav.len(), 44 + 64);
+        assert_eq!(&wav[36..40], b"data");
+    }
+
+    #[tokio::test]
+    async fn test_transcript_search_points_to_timestamp() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let audio_dir = dir.path().join("audio");
+        let media = manager.upload_file(Some(&audio_dir), "standup.mp3", vec![0u8; 128], false).await.unwrap();
+
+        let sidecar = manager.save_transcript(&media, &sample_transcript()).await.unwrap();
+        assert_eq!(sidecar.name, "standup.transcript.md");
+        assert!(sidecar.tags.contains(&"transcript".to_string()));
+        assert!(sidecar.vectorized);
+
+        // The sidecar links back to the media item
+        let backlinks = manager.backlinks(&media.id).await.unwrap();
+        assert_eq!(backlinks[0].source_id, sidecar.id);
+
+        let results = manager.search("hiring budget", &Default::default(), 10).await.unwrap();
+        assert_eq!(results[0].item.id, sidecar.id);
+        assert_eq!(results[0].transcript_of.as_deref(), Some(media.id.as_str()));
+        assert_eq!(results[0].timestamp, Some(65.0));
+
+        // Transcribing again replaces the same sidecar
+        let again = manager.save_transcript(&media, &sample_transcript()).await.unwrap();
+        assert_eq!(again.id, sidecar.id);
+    }
+
+    #[tokio::test]
+    async fn test_private_media_gets_sealed_transcript() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let audio_dir = dir.path().join("audio");
+        let media = manager.upload_file(Some(&audio_dir), "one-on-one.mp3", vec![0u8; 128], false).await.unwrap();
+        manager.set_private(&media.id, true).await.unwrap();
+        let media = manager.get_item(&media.id).await.unwrap();
+
+        let sidecar = manager.save_transcript(&media, &sample_transcript()).await.unwrap();
+        assert!(sidecar.private);
+        assert!(crate::knowledge_crypto::is_sealed(&std::fs::read(&sidecar.path).unwrap()));
+        assert!(manager.list_versions(&sidecar.id).await.unwrap().is_empty());
+        assert!(manager.search("hiring budget", &Default::default(), 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the hidden directory under the knowledge root that holds previous file contents
+pub const VERSIONS_DIR_NAME: &str = ".versions";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VersionRetention {
+    pub max_versions: usize,       // Per item; 0 turns history off
+    pub max_age_days: Option<u32>,
+}
+
+impl Default for VersionRetention {
+    fn default() -> Self {
+        Self {
+            max_versions: 20,
+            max_age_days: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnowledgeVersion {
+    pub id: String,
+    pub item_id: String,
+    pub content_hash: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+    pub reason: String, // What replaced this content: "overwrite" or "restore"
+}
+
+/// Content-addressed blob store, so identical snapshots are kept once
+pub struct VersionStore {
+    objects_dir: PathBuf,
+}
+
+impl VersionStore {
+    pub fn open(knowledge_dir: &Path) -> Result<Self> {
+        let objects_dir = knowledge_dir.join(VERSIONS_DIR_NAME).join("objects");
+        fs::create_dir_all(&objects_dir)?;
+        Ok(Self { objects_dir })
+    }
+
+    fn object_path(&self, hash: &str) -> Result<PathBuf> {
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!("Invalid version hash: {}", hash));
+        }
+        Ok(self.objects_dir.join(&hash[..2]).join(hash))
+    }
+
+    pub fn put(&self, bytes: &[u8]) -> Result<String> {
+        let hash = crate::knowledge_dedupe::content_hash(bytes);
+        let path = self.object_path(&hash)?;
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let tmp_path = path.with_extension("tmp");
+            fs::write(&tmp_path, bytes)?;
+            fs::rename(&tmp_path, &path)?;
+        }
+        Ok(hash)
+    }
+
+    pub fn get(&self, hash: &str) -> Result<Vec<u8>> {
+        fs::read(self.object_path(hash)?)
+            .map_err(|e| anyhow!("Version content {} unavailable: {}", hash, e))
+    }
+
+    /// Delete blobs no version refers to any more
+    pub fn gc(&self, referenced: &HashSet<String>) -> Result<()> {
+        for entry in walkdir::WalkDir::new(&self.objects_dir).min_depth(2).into_iter().filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type().is_file() && !referenced.contains(&name) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn size(&self) -> u64 {
+        walkdir::WalkDir::new(&self.objects_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum()
+    }
+}
+
+/// Ids of versions that fall outside the retention limits. `versions` is newest first.
+pub fn expired(versions: &[KnowledgeVersion], retention: &VersionRetention, now: DateTime<Utc>) -> Vec<String> {
+    let cutoff = retention.max_age_days.map(|days| now - Duration::days(days as i64));
+
+    versions.iter()
+        .enumerate()
+        .filter(|(index, version)| {
+            *index >= retention.max_versions || cutoff.map(|c| version.created_at < c).unwrap_or(false)
+        })
+        .map(|(_, version)| version.id.clone())
+        .collect()
+}
+
+/// Unified line diff between two texts
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_label, new_label)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::{KnowledgeManager, KnowledgeSettings};
+
+    #[tokio::test]
+    async fn test_overwrite_diff_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = KnowledgeManager::new(dir.path().to_path_buf()).unwrap();
+        let docs = dir.path().join("documents");
+
+        let settings = KnowledgeSettings {
+            version_retention: VersionRetention { max_versions: 2, max_age_days: None },
+            ..Default::default()
+        };
+        manager.configure(settings, None).await.unwrap();
+
+        let item = manager.upload_file(Some(&docs), "todo.md", b"buy milk\nwalk dog\n".to_vec(), false).await.unwrap();
+        for body in ["buy milk\nwalk cat\n", "buy milk\nwalk dog\n", "buy bread\nwalk dog\n"] {
+            manager.upload_file(Some(&docs), "todo.md", body.as_bytes().to_vec(), true).await.unwrap();
+        }
+
+        let versions = manager.list_versions(&item.id).await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].size, 18);
+
+        let diff = manager.diff_versions(&item.id, Some(&versions[0].id), None).await.unwrap();
+        assert!(diff.contains("-buy milk"));
+        assert!(diff.contains("+buy bread"));
+
+        let restored = manager.restore_version(&item.id, &versions[1].id).await.unwrap();
+        assert_eq!(restored.id, item.id);
+        assert_eq!(fs::read_to_string(docs.join("todo.md")).unwrap(), "buy milk\nwalk cat\n");
+
+        // Restoring snapshots the content it replaced, and retention still applies
+        let versions = manager.list_versions(&item.id).await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].reason, "restore");
+    }
+}
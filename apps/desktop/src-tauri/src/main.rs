@@ -13,8 +13,24 @@ mod whisper;
 mod piper;
 mod plugin_system;
 mod agents;
+mod agent_conditions;
+mod agent_store;
+mod agent_files;
 mod tools;
 mod knowledge;
+mod knowledge_index;
+mod embeddings;
+mod vector_index;
+mod knowledge_import;
+mod knowledge_dedupe;
+mod knowledge_versions;
+mod knowledge_crypto;
+mod knowledge_links;
+mod knowledge_enrichment;
+mod knowledge_datasets;
+mod knowledge_transcripts;
+mod knowledge_collections;
+mod knowledge_bundle;
 mod config;
 mod state;
 mod security;
@@ -74,6 +90,10 @@ fn main() {
             update_agent_workflow,
             list_agent_workflows,
             execute_agent_workflow,
+            list_agent_workflow_runs,
+            get_agent_workflow_run,
+            export_workflow,
+            import_workflow,
             get_available_agents,
             list_tools,
             get_tool,
@@ -85,14 +105,43 @@ fn main() {
             get_knowledge_items,
             get_knowledge_item,
             read_knowledge_content,
+            preview_dataset,
             create_knowledge_folder,
             upload_knowledge_file,
+            import_knowledge,
+            export_knowledge,
+            verify_knowledge_bundle,
+            import_knowledge_bundle,
             delete_knowledge_item,
+            move_knowledge_item,
+            rename_knowledge_item,
+            copy_knowledge_item,
+            find_knowledge_duplicates,
+            merge_duplicates,
+            list_knowledge_versions,
+            diff_knowledge_versions,
+            restore_knowledge_version,
+            get_knowledge_links,
+            get_knowledge_backlinks,
+            get_broken_knowledge_links,
+            get_knowledge_graph,
+            enrich_knowledge_items,
+            list_knowledge_suggestions,
+            accept_knowledge_suggestion,
+            dismiss_knowledge_suggestion,
+            transcribe_knowledge_item,
             update_knowledge_tags,
             toggle_knowledge_star,
             toggle_knowledge_private,
             vectorize_knowledge_item,
             search_similar_knowledge,
+            search_knowledge,
+            list_knowledge_collections,
+            create_knowledge_collection,
+            update_knowledge_collection,
+            delete_knowledge_collection,
+            list_collection_items,
+            query_knowledge_items,
             get_knowledge_storage_info,
             create_realtime_session,
             send_realtime_audio,
@@ -118,6 +167,9 @@ fn main() {
                 }
             };
             
+            // Shared by the terminal tool and workflow agents
+            let security_manager = Arc::new(security::SecurityManager::new());
+            
             // Initialize SQLite database
             let app_data_dir_clone = app_data_dir.clone();
             tauri::async_runtime::spawn(async move {
@@ -166,13 +218,6 @@ fn main() {
                 }
             });
             
-            // Initialize agent system
-            tauri::async_runtime::spawn(async move {
-                if let Err(e) = agents::initialize_agent_system().await {
-                    eprintln!("Failed to initialize agent system: {}", e);
-                }
-            });
-            
             // Initialize tools manager
             let tools_dir = app_data_dir.join("tools");
             tauri::async_runtime::spawn(async move {
@@ -183,9 +228,19 @@ fn main() {
             
             // Initialize knowledge manager
             let knowledge_dir = app_data_dir.join("knowledge");
+            let knowledge_handle = app_handle.clone();
             tauri::async_runtime::spawn(async move {
                 if let Err(e) = knowledge::initialize_knowledge_manager(knowledge_dir).await {
                     eprintln!("Failed to initialize knowledge manager: {}", e);
+                    return;
+                }
+                
+                // Collections are queries, so any item change can change what they hold
+                use tauri::Emitter;
+                if let Err(e) = knowledge::watch_changes(|| {
+                    let _ = knowledge_handle.emit("knowledge-collections-changed", ());
+                }).await {
+                    eprintln!("Failed to watch knowledge changes: {}", e);
                 }
             });
             
@@ -198,7 +253,6 @@ fn main() {
                 let state = state_manager.state.lock().unwrap();
                 state.settings.allowed_roots.clone()
             };
-            let security_manager = Arc::new(security::SecurityManager::new());
             tauri::async_runtime::spawn(async move {
                 use std::sync::Arc;
                 use crate::tool_executor::{FileSystemTool, TerminalTool, MCPBridgeTool};
@@ -222,8 +276,10 @@ fn main() {
                 }
             });
             
-            // Load settings from database on app startup
+            // Load settings from database on app startup, then start the agent system with them
             let app_handle_for_settings = app_handle.clone();
+            let agents_dir = app_data_dir.clone();
+            let agents_security = security_manager.clone();
             tauri::async_runtime::spawn(async move {
                 // Wait a moment for database to initialize
                 tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -236,6 +292,7 @@ fn main() {
                 }).await {
                     Ok(settings_vec) => {
                         let mut api_key_to_set = None;
+                        let mut knowledge_settings = None;
                         
                         // Update settings from database
                         {
@@ -264,6 +321,12 @@ fn main() {
                                                 app_state.settings.openai_model = val;
                                             }
                                         }
+                                        "knowledge_settings" => {
+                                            if let Ok(val) = serde_json::from_value::<crate::knowledge::KnowledgeSettings>(setting.value) {
+                                                app_state.settings.knowledge_settings = val.clone();
+                                                knowledge_settings = Some(val);
+                                            }
+                                        }
                                         _ => {}
                                     }
                                 }
@@ -277,6 +340,18 @@ fn main() {
                             };
                         }; // Mutex guard is dropped here
                         
+                        // Apply stored knowledge settings
+                        if let Some(knowledge_settings) = knowledge_settings {
+                            let api_key = api_key_to_set.clone();
+                            if let Err(e) = crate::knowledge::with_knowledge_manager(|manager| {
+                                Box::pin(async move {
+                                    manager.configure(knowledge_settings, api_key).await
+                                })
+                            }).await {
+                                eprintln!("Failed to apply knowledge settings: {}", e);
+                            }
+                        }
+                        
                         // Set API key in voice manager if available
                         if let Some(api_key) = api_key_to_set {
                             let _ = voice::with_voice_manager(|manager| {
@@ -289,6 +364,15 @@ fn main() {
                         eprintln!("Failed to load settings from database: {}", e);
                     }
                 }
+                
+                // Initialize agent system only once settings are known, so offline mode, allowed
+                // roots and the API key apply from the first run
+                let agent_environment = app_handle_for_settings.state::<AppStateManager>().state.lock()
+                    .map(|app_state| app_state.settings.agent_environment())
+                    .unwrap_or_default();
+                if let Err(e) = agents::initialize_agent_system(agents_dir, agents_security, agent_environment).await {
+                    eprintln!("Failed to initialize agent system: {}", e);
+                }
             });
             
             Ok(())
@@ -38,7 +38,7 @@ impl OllamaClient {
     }
     
     pub async fn is_available(&self) -> bool {
-        match self.client.get(&format!("{}/api/tags", self.base_url)).send().await {
+        match self.client.get(format!("{}/api/tags", self.base_url)).send().await {
             Ok(response) => response.status().is_success(),
             Err(_) => false,
         }
@@ -56,7 +56,7 @@ impl OllamaClient {
         };
         
         let response = self.client
-            .post(&format!("{}/api/generate", self.base_url))
+            .post(format!("{}/api/generate", self.base_url))
             .json(&request)
             .send()
             .await?;
@@ -69,9 +69,29 @@ impl OllamaClient {
         }
     }
     
+    pub async fn embed(&self, model: &str, prompt: &str) -> Result<Vec<f32>> {
+        let response = self.client
+            .post(format!("{}/api/embeddings", self.base_url))
+            .json(&serde_json::json!({
+                "model": model,
+                "prompt": prompt,
+            }))
+            .send()
+            .await?;
+        
+        if response.status().is_success() {
+            let data: serde_json::Value = response.json().await?;
+            data["embedding"].as_array()
+                .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+                .ok_or_else(|| anyhow!("Invalid embedding response from Ollama"))
+        } else {
+            Err(anyhow!("Ollama API error: {}", response.status()))
+        }
+    }
+    
     pub async fn list_models(&self) -> Result<Vec<String>> {
         let response = self.client
-            .get(&format!("{}/api/tags", self.base_url))
+            .get(format!("{}/api/tags", self.base_url))
             .send()
             .await?;
         
@@ -189,21 +189,92 @@ impl TerminalTool {
         Self { security_manager }
     }
     
-    fn is_command_allowed(&self, command: &str) -> bool {
-        // Whitelist of safe commands
+    /// Parses and vets a command line. Commands run without a shell, so anything that only a
+    /// shell would interpret is rejected rather than passed through as a literal argument.
+    fn parse_command(&self, command: &str) -> Result<Vec<String>> {
+        // Whitelist of safe commands. Package managers are left out since their subcommands
+        // run project scripts.
         let safe_commands = [
             "ls", "pwd", "echo", "cat", "grep", "find", "which",
-            "git", "npm", "node", "python", "pip", "cargo",
+            "git", "node", "python", "python3",
             "date", "whoami", "df", "du", "ps", "top"
         ];
         
-        let cmd_parts: Vec<&str> = command.split_whitespace().collect();
-        if cmd_parts.is_empty() {
-            return false;
+        if let Some(c) = command.chars().find(|c| SHELL_METACHARACTERS.contains(c)) {
+            return Err(anyhow!("Command not allowed: shell syntax '{}' is not supported", c.escape_default()));
+        }
+        if !self.security_manager.check_command_permission(command) {
+            return Err(anyhow!("Command not allowed: blocked by security policy"));
         }
         
-        let base_cmd = cmd_parts[0];
-        safe_commands.contains(&base_cmd)
+        let argv = split_command(command)?;
+        let Some(program) = argv.first() else {
+            return Err(anyhow!("Command not allowed: empty command"));
+        };
+        if !safe_commands.contains(&program.as_str()) {
+            return Err(anyhow!("Command not allowed: '{}' is not on the allowlist", program));
+        }
+        if let Some(arg) = argv[1..].iter().find(|arg| is_forbidden_argument(program, arg)) {
+            return Err(anyhow!("Command not allowed: '{}' cannot be used with {}", arg, program));
+        }
+        Ok(argv)
+    }
+}
+
+/// Characters with meaning to a shell (chaining, pipes, redirects, substitution, globs)
+const SHELL_METACHARACTERS: &[char] = &[
+    ';', '|', '&', '$', '`', '<', '>', '(', ')', '{', '}', '*', '?', '[', ']', '~', '!', '\\', '\n', '\r',
+];
+
+/// Splits a command line into arguments, honouring single and double quotes
+fn split_command(command: &str) -> Result<Vec<String>> {
+    let mut argv = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    argv.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(anyhow!("Command has an unterminated quote"));
+    }
+    if in_word {
+        argv.push(current);
+    }
+    Ok(argv)
+}
+
+/// Arguments that turn an allowlisted command into running arbitrary code or deleting files
+fn is_forbidden_argument(program: &str, arg: &str) -> bool {
+    let flag = arg.split('=').next().unwrap_or(arg);
+    // Clustered short flags such as `-Bc` still carry the dangerous letter
+    let short = |letters: &[char]| {
+        arg.starts_with('-') && !arg.starts_with("--") && arg[1..].contains(letters)
+    };
+    match program {
+        "python" | "python3" => short(&['c', 'm']) || flag == "--command",
+        "node" => short(&['e', 'p', 'r']) || matches!(flag, "--eval" | "--print" | "--require" | "--import" | "--loader"),
+        "find" => matches!(flag, "-delete" | "-exec" | "-execdir" | "-ok" | "-okdir" | "-fprint" | "-fprint0" | "-fprintf" | "-fls"),
+        "git" => matches!(flag, "-c" | "-C" | "--config-env" | "--exec-path" | "--upload-pack" | "--receive-pack" | "--output"),
+        _ => false,
     }
 }
 
@@ -238,16 +309,18 @@ impl Tool for TerminalTool {
         let command = args["command"].as_str()
             .ok_or_else(|| anyhow!("Missing 'command' parameter"))?;
         
-        if !self.is_command_allowed(command) {
-            return Ok(ToolResult {
+        let argv = match self.parse_command(command) {
+            Ok(argv) => argv,
+            Err(e) => return Ok(ToolResult {
                 success: false,
                 output: String::new(),
-                error: Some("Command not allowed".to_string()),
-            });
-        }
+                error: Some(e.to_string()),
+            }),
+        };
         
-        let mut cmd = Command::new("sh");
-        cmd.arg("-c").arg(command);
+        let mut cmd = Command::new(&argv[0]);
+        // Dropping the future (an aborted run or a timed-out attempt) kills the process
+        cmd.args(&argv[1..]).stdin(std::process::Stdio::null()).kill_on_drop(true);
         
         if let Some(cwd) = args["working_dir"].as_str() {
             cmd.current_dir(cwd);
@@ -0,0 +1,823 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"LBHNSW01";
+const FORMAT_VERSION: u32 = 1;
+const LOG_MAGIC: &[u8; 8] = b"LBHNSWLG";
+const LOG_INSERT: u8 = 1;
+const LOG_REMOVE: u8 = 2;
+// First result count tried by `search_within` before widening
+const WITHIN_START: usize = 64;
+
+/// HNSW tuning knobs. Higher values trade speed and memory for recall.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HnswParams {
+    pub m: usize,               // Links per node on upper layers (twice this on layer 0)
+    pub ef_construction: usize, // Candidate list size while inserting
+    pub ef_search: usize,       // Candidate list size while querying
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VectorHit {
+    pub item_id: String,
+    pub chunk: u32,
+    pub similarity: f32,
+}
+
+struct Node {
+    item_id: String,
+    chunk: u32,
+    vector: Vec<f32>,
+    neighbors: Vec<Vec<u32>>, // One adjacency list per layer
+    deleted: bool,
+}
+
+#[derive(PartialEq)]
+struct Candidate {
+    distance: f32,
+    id: u32,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Hierarchical navigable small world graph over normalized vectors (cosine distance).
+/// Deletes are tombstones; the graph is compacted once enough of it is dead.
+pub struct HnswIndex {
+    model_id: String,
+    dim: usize,
+    params: HnswParams,
+    nodes: Vec<Node>,
+    entry_point: Option<u32>,
+    by_item: HashMap<String, Vec<u32>>,
+    deleted: usize,
+    rng: u64,
+    snapshot: Option<[u8; 32]>, // Checksum of the snapshot on disk, None until one is written
+    snapshot_bytes: u64,
+    log_bytes: u64,
+    journal: Vec<u8>, // Log records not yet appended to disk
+}
+
+impl HnswIndex {
+    pub fn new(model_id: &str, params: HnswParams) -> Self {
+        Self {
+            model_id: model_id.to_string(),
+            dim: 0,
+            params,
+            nodes: Vec::new(),
+            entry_point: None,
+            by_item: HashMap::new(),
+            deleted: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+            snapshot: None,
+            snapshot_bytes: 0,
+            log_bytes: 0,
+            journal: Vec::new(),
+        }
+    }
+
+    /// Whether an index loaded from disk can be reused with the current model and settings
+    pub fn is_compatible(&self, model_id: &str, params: &HnswParams) -> bool {
+        self.model_id == model_id
+            && self.params.m == params.m
+            && self.params.ef_construction == params.ef_construction
+    }
+
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.params.ef_search = ef_search.max(1);
+    }
+
+    pub fn live_count(&self) -> usize {
+        self.nodes.len() - self.deleted
+    }
+
+    pub fn has_tombstones(&self) -> bool {
+        self.deleted > 0
+    }
+
+    pub fn contains_item(&self, item_id: &str) -> bool {
+        self.by_item.contains_key(item_id)
+    }
+
+    /// Number of live chunks per indexed item
+    pub fn item_chunks(&self) -> HashMap<String, usize> {
+        self.by_item.iter().map(|(id, nodes)| (id.clone(), nodes.len())).collect()
+    }
+
+    pub fn item_vectors(&self, item_id: &str) -> Vec<Vec<f32>> {
+        self.by_item.get(item_id)
+            .map(|ids| ids.iter().map(|&id| self.nodes[id as usize].vector.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn insert(&mut self, item_id: &str, chunk: u32, mut vector: Vec<f32>) -> Result<()> {
+        if self.dim == 0 {
+            self.dim = vector.len();
+        }
+        if vector.len() != self.dim || vector.is_empty() {
+            return Err(anyhow!("Vector dimension {} does not match index dimension {}", vector.len(), self.dim));
+        }
+        crate::embeddings::normalize(&mut vector);
+
+        if self.snapshot.is_some() {
+            let mut record = vec![LOG_INSERT];
+            put_str(&mut record, item_id);
+            put_u32(&mut record, chunk);
+            put_u32(&mut record, vector.len() as u32);
+            for value in &vector {
+                record.extend_from_slice(&value.to_le_bytes());
+            }
+            self.journal_record(&record);
+        }
+
+        let level = self.random_level();
+        let id = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            item_id: item_id.to_string(),
+            chunk,
+            vector,
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.by_item.entry(item_id.to_string()).or_default().push(id);
+
+        let entry = match self.entry_point {
+            Some(entry) => entry,
+            None => {
+                self.entry_point = Some(id);
+                return Ok(());
+            }
+        };
+
+        let top = self.level_of(entry);
+        let query = self.nodes[id as usize].vector.clone();
+
+        let mut ep = entry;
+        for layer in (level + 1..=top).rev() {
+            ep = self.greedy_closest(&query, ep, layer);
+        }
+
+        let mut entry_points = vec![ep];
+        for layer in (0..=level.min(top)).rev() {
+            let candidates = self.search_layer(&query, &entry_points, self.params.ef_construction, layer);
+            let max_links = self.max_links(layer);
+
+            let selected: Vec<u32> = candidates.iter().take(self.params.m).map(|c| c.id).collect();
+            self.nodes[id as usize].neighbors[layer] = selected.clone();
+
+            for neighbor in selected {
+                self.nodes[neighbor as usize].neighbors[layer].push(id);
+                if self.nodes[neighbor as usize].neighbors[layer].len() > max_links {
+                    self.prune_links(neighbor, layer, max_links);
+                }
+            }
+
+            entry_points = candidates.into_iter().map(|c| c.id).collect();
+        }
+
+        if level > top {
+            self.entry_point = Some(id);
+        }
+
+        Ok(())
+    }
+
+    /// Tombstone every chunk of an item. Returns false if the item was not indexed.
+    pub fn remove_item(&mut self, item_id: &str) -> bool {
+        match self.by_item.remove(item_id) {
+            Some(ids) => {
+                if self.snapshot.is_some() {
+                    let mut record = vec![LOG_REMOVE];
+                    put_str(&mut record, item_id);
+                    self.journal_record(&record);
+                }
+                for id in ids {
+                    let node = &mut self.nodes[id as usize];
+                    if !node.deleted {
+                        node.deleted = true;
+                        self.deleted += 1;
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn should_compact(&self) -> bool {
+        self.deleted > 0 && self.deleted * 4 > self.nodes.len()
+    }
+
+    /// Rebuild the graph from live nodes only
+    pub fn compact(&mut self) -> Result<()> {
+        let mut rebuilt = HnswIndex::new(&self.model_id, self.params);
+        for node in self.nodes.iter().filter(|n| !n.deleted) {
+            rebuilt.insert(&node.item_id, node.chunk, node.vector.clone())?;
+        }
+        *self = rebuilt;
+        Ok(())
+    }
+
+    /// Approximate k nearest chunks, most similar first
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<VectorHit> {
+        let entry = match self.entry_point {
+            Some(entry) if query.len() == self.dim && k > 0 => entry,
+            _ => return Vec::new(),
+        };
+
+        let mut query = query.to_vec();
+        crate::embeddings::normalize(&mut query);
+
+        let mut ep = entry;
+        for layer in (1..=self.level_of(entry)).rev() {
+            ep = self.greedy_closest(&query, ep, layer);
+        }
+
+        // Widen the beam so tombstones don't starve the result list
+        let ef = self.params.ef_search.max(k) + self.deleted.min(k);
+        self.search_layer(&query, &[ep], ef, 0)
+            .into_iter()
+            .filter(|c| !self.nodes[c.id as usize].deleted)
+            .take(k)
+            .map(|c| {
+                let node = &self.nodes[c.id as usize];
+                VectorHit {
+                    item_id: node.item_id.clone(),
+                    chunk: node.chunk,
+                    similarity: 1.0 - c.distance,
+                }
+            })
+            .collect()
+    }
+
+    fn level_of(&self, id: u32) -> usize {
+        self.nodes[id as usize].neighbors.len() - 1
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 { self.params.m * 2 } else { self.params.m }
+    }
+
+    fn distance(&self, query: &[f32], id: u32) -> f32 {
+        let vector = &self.nodes[id as usize].vector;
+        1.0 - query.iter().zip(vector).map(|(a, b)| a * b).sum::<f32>()
+    }
+
+    fn random_level(&mut self) -> usize {
+        // xorshift64*
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        let uniform = ((bits >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+
+        let ml = 1.0 / (self.params.m.max(2) as f64).ln();
+        ((-uniform.ln() * ml).floor() as usize).min(16)
+    }
+
+    fn greedy_closest(&self, query: &[f32], start: u32, layer: usize) -> u32 {
+        let mut current = start;
+        let mut best = self.distance(query, current);
+        loop {
+            let mut changed = false;
+            for &neighbor in &self.nodes[current as usize].neighbors[layer] {
+                let distance = self.distance(query, neighbor);
+                if distance < best {
+                    best = distance;
+                    current = neighbor;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return current;
+            }
+        }
+    }
+
+    /// Chunks with at least `min_similarity` to `query`, most similar first. The search
+    /// widens until its least similar hit falls below the threshold, so only the matching
+    /// part of the index is scored rather than every chunk.
+    pub fn search_within(&self, query: &[f32], min_similarity: f32) -> Vec<VectorHit> {
+        let mut k = WITHIN_START;
+        loop {
+            let hits = self.search(query, k);
+            let complete = hits.len() < k
+                || k >= self.live_count()
+                || hits.last().is_some_and(|hit| hit.similarity < min_similarity);
+            if complete {
+                return hits.into_iter().filter(|hit| hit.similarity >= min_similarity).collect();
+            }
+            k *= 4;
+        }
+    }
+
+    /// Beam search on one layer, returning up to `ef` candidates nearest first
+    fn search_layer(&self, query: &[f32], entry_points: &[u32], ef: usize, layer: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = entry_points.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut results = BinaryHeap::new();
+
+        for &ep in entry_points {
+            let distance = self.distance(query, ep);
+            candidates.push(Reverse(Candidate { distance, id: ep }));
+            results.push(Candidate { distance, id: ep });
+        }
+        while results.len() > ef {
+            results.pop();
+        }
+
+        while let Some(Reverse(current)) = candidates.pop() {
+            let furthest = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
+            if current.distance > furthest && results.len() >= ef {
+                break;
+            }
+
+            for &neighbor in &self.nodes[current.id as usize].neighbors[layer] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+
+                let distance = self.distance(query, neighbor);
+                let furthest = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
+                if results.len() < ef || distance < furthest {
+                    candidates.push(Reverse(Candidate { distance, id: neighbor }));
+                    results.push(Candidate { distance, id: neighbor });
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        results.into_sorted_vec()
+    }
+
+    fn prune_links(&mut self, id: u32, layer: usize, max_links: usize) {
+        let vector = self.nodes[id as usize].vector.clone();
+        let mut links: Vec<Candidate> = self.nodes[id as usize].neighbors[layer].iter()
+            .map(|&n| Candidate { distance: self.distance(&vector, n), id: n })
+            .collect();
+        links.sort();
+        links.truncate(max_links);
+        self.nodes[id as usize].neighbors[layer] = links.into_iter().map(|c| c.id).collect();
+    }
+
+    fn journal_record(&mut self, record: &[u8]) {
+        put_u32(&mut self.journal, record.len() as u32);
+        self.journal.extend_from_slice(record);
+        self.journal.extend_from_slice(ring::digest::digest(&ring::digest::SHA256, record).as_ref());
+    }
+
+    /// Persist changes since the last save. Inserts and removals are appended to a log next
+    /// to the snapshot, which is only rewritten once the log outgrows half its size or after
+    /// the graph was rebuilt.
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        let pending = self.log_bytes + self.journal.len() as u64;
+        let checksum = match self.snapshot {
+            Some(checksum) if pending <= self.snapshot_bytes / 2 => checksum,
+            _ => return self.write_snapshot(path),
+        };
+        if self.journal.is_empty() {
+            return Ok(());
+        }
+
+        if let Err(e) = self.append_log(&log_path(path), &checksum) {
+            // The log may now end in a torn record, so fall back to a full snapshot
+            self.snapshot = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // Log: LOG_MAGIC | checksum of the snapshot it extends | (length | record | SHA-256 of record)*
+    fn append_log(&mut self, path: &Path, checksum: &[u8; 32]) -> Result<()> {
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        if self.log_bytes == 0 {
+            // Start over; anything already there belongs to an older snapshot
+            file.set_len(0)?;
+            file.write_all(LOG_MAGIC)?;
+            file.write_all(checksum)?;
+            self.log_bytes = (LOG_MAGIC.len() + checksum.len()) as u64;
+        }
+        file.write_all(&self.journal)?;
+        file.sync_data()?;
+        self.log_bytes += self.journal.len() as u64;
+        self.journal.clear();
+        Ok(())
+    }
+
+    // Snapshot: MAGIC | payload length | payload | SHA-256 of payload
+    fn write_snapshot(&mut self, path: &Path) -> Result<()> {
+        let mut payload = Vec::new();
+        put_u32(&mut payload, FORMAT_VERSION);
+        put_str(&mut payload, &self.model_id);
+        put_u32(&mut payload, self.dim as u32);
+        put_u32(&mut payload, self.params.m as u32);
+        put_u32(&mut payload, self.params.ef_construction as u32);
+        put_u32(&mut payload, self.params.ef_search as u32);
+        put_u32(&mut payload, self.entry_point.map(|e| e + 1).unwrap_or(0));
+        put_u32(&mut payload, self.nodes.len() as u32);
+
+        for node in &self.nodes {
+            put_str(&mut payload, &node.item_id);
+            put_u32(&mut payload, node.chunk);
+            payload.push(node.deleted as u8);
+            put_u32(&mut payload, node.neighbors.len() as u32);
+            for links in &node.neighbors {
+                put_u32(&mut payload, links.len() as u32);
+                for &link in links {
+                    put_u32(&mut payload, link);
+                }
+            }
+            for value in &node.vector {
+                payload.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        let checksum = ring::digest::digest(&ring::digest::SHA256, &payload);
+        let mut file = Vec::with_capacity(payload.len() + 48);
+        file.extend_from_slice(MAGIC);
+        file.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        file.extend_from_slice(&payload);
+        file.extend_from_slice(checksum.as_ref());
+
+        // Write atomically so a crash never leaves a half-written index behind. A log left
+        // over from a crash before its removal names the old snapshot and is ignored on load.
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, &file)?;
+        std::fs::rename(&tmp_path, path)?;
+        match std::fs::remove_file(log_path(path)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+
+        self.snapshot = Some(checksum.as_ref().try_into()?);
+        self.snapshot_bytes = file.len() as u64;
+        self.log_bytes = 0;
+        self.journal.clear();
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::read(path)?;
+        if file.len() < 16 || &file[..8] != MAGIC {
+            return Err(anyhow!("Not a vector index file"));
+        }
+
+        let payload_len = u64::from_le_bytes(file[8..16].try_into()?) as usize;
+        if file.len() != 16 + payload_len + 32 {
+            return Err(anyhow!("Vector index is truncated"));
+        }
+        let payload = &file[16..16 + payload_len];
+        let checksum = ring::digest::digest(&ring::digest::SHA256, payload);
+        if checksum.as_ref() != &file[16 + payload_len..] {
+            return Err(anyhow!("Vector index checksum mismatch"));
+        }
+
+        let mut reader = Reader { data: payload, pos: 0 };
+        if reader.u32()? != FORMAT_VERSION {
+            return Err(anyhow!("Unsupported vector index version"));
+        }
+
+        let model_id = reader.string()?;
+        let dim = reader.u32()? as usize;
+        let params = HnswParams {
+            m: reader.u32()? as usize,
+            ef_construction: reader.u32()? as usize,
+            ef_search: reader.u32()? as usize,
+        };
+        let entry_point = match reader.u32()? {
+            0 => None,
+            e => Some(e - 1),
+        };
+        let node_count = reader.u32()?;
+
+        let mut index = HnswIndex::new(&model_id, params);
+        index.dim = dim;
+        index.rng ^= node_count as u64;
+
+        for id in 0..node_count {
+            let item_id = reader.string()?;
+            let chunk = reader.u32()?;
+            let deleted = reader.byte()? != 0;
+            let layers = reader.u32()? as usize;
+            if layers == 0 {
+                return Err(anyhow!("Vector index node without layers"));
+            }
+
+            let mut neighbors = Vec::with_capacity(layers);
+            for _ in 0..layers {
+                let count = reader.u32()? as usize;
+                let mut links = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let link = reader.u32()?;
+                    if link >= node_count {
+                        return Err(anyhow!("Vector index link out of range"));
+                    }
+                    links.push(link);
+                }
+                neighbors.push(links);
+            }
+
+            let mut vector = Vec::with_capacity(dim);
+            for _ in 0..dim {
+                vector.push(f32::from_le_bytes(reader.take(4)?.try_into()?));
+            }
+
+            if deleted {
+                index.deleted += 1;
+            } else {
+                index.by_item.entry(item_id.clone()).or_default().push(id);
+            }
+            index.nodes.push(Node {
+                item_id,
+                chunk,
+                vector,
+                neighbors,
+                deleted,
+            });
+        }
+
+        if entry_point.map(|e| e >= node_count).unwrap_or(false) {
+            return Err(anyhow!("Vector index entry point out of range"));
+        }
+        index.entry_point = entry_point;
+        index.snapshot = Some(checksum.as_ref().try_into()?);
+        index.snapshot_bytes = file.len() as u64;
+
+        index.replay_log(&log_path(path))?;
+        Ok(index)
+    }
+
+    /// Apply the changes logged since the snapshot was written. A torn final record from a
+    /// crash mid-append is dropped, and the next save writes a fresh snapshot.
+    fn replay_log(&mut self, path: &Path) -> Result<()> {
+        let log = match std::fs::read(path) {
+            Ok(log) => log,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let header = LOG_MAGIC.len() + 32;
+        if log.len() < header || &log[..LOG_MAGIC.len()] != LOG_MAGIC
+            || self.snapshot.as_ref().map(|c| &c[..]) != Some(&log[LOG_MAGIC.len()..header])
+        {
+            return Ok(());
+        }
+
+        let mut pos = header;
+        while log.len() >= pos + 4 {
+            let len = u32::from_le_bytes(log[pos..pos + 4].try_into()?) as usize;
+            let end = pos + 4 + len + 32;
+            if end > log.len() {
+                break;
+            }
+            let record = &log[pos + 4..pos + 4 + len];
+            if ring::digest::digest(&ring::digest::SHA256, record).as_ref() != &log[pos + 4 + len..end] {
+                break;
+            }
+            self.apply_record(record)?;
+            pos = end;
+        }
+
+        self.journal.clear();
+        if pos == log.len() {
+            self.log_bytes = pos as u64;
+        } else {
+            self.snapshot = None;
+        }
+        Ok(())
+    }
+
+    fn apply_record(&mut self, record: &[u8]) -> Result<()> {
+        let mut reader = Reader { data: record, pos: 0 };
+        match reader.byte()? {
+            LOG_INSERT => {
+                let item_id = reader.string()?;
+                let chunk = reader.u32()?;
+                let len = reader.u32()? as usize;
+                let mut vector = Vec::with_capacity(len);
+                for _ in 0..len {
+                    vector.push(f32::from_le_bytes(reader.take(4)?.try_into()?));
+                }
+                self.insert(&item_id, chunk, vector)
+            }
+            LOG_REMOVE => {
+                self.remove_item(&reader.string()?);
+                Ok(())
+            }
+            op => Err(anyhow!("Unknown vector index log record {}", op)),
+        }
+    }
+}
+
+/// Where the change log for the snapshot at `path` is kept
+pub fn log_path(path: &Path) -> PathBuf {
+    path.with_extension("hnsw-log")
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_str(buf: &mut Vec<u8>, value: &str) {
+    put_u32(buf, value.len() as u32);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.pos + len > self.data.len() {
+            return Err(anyhow!("Vector index is truncated"));
+        }
+        let slice = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_vectors(count: usize, dim: usize) -> Vec<Vec<f32>> {
+        let mut state = 42u64;
+        (0..count)
+            .map(|_| {
+                (0..dim)
+                    .map(|_| {
+                        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recall_against_brute_force() {
+        let vectors = random_vectors(2000, 32);
+        let mut index = HnswIndex::new("test", HnswParams::default());
+        for (i, v) in vectors.iter().enumerate() {
+            index.insert(&format!("item-{}", i), 0, v.clone()).unwrap();
+        }
+
+        let queries = random_vectors(50, 32);
+        let mut found = 0;
+        for query in &queries {
+            let mut q = query.clone();
+            crate::embeddings::normalize(&mut q);
+            let mut exact: Vec<(usize, f32)> = vectors.iter().enumerate()
+                .map(|(i, v)| {
+                    let mut v = v.clone();
+                    crate::embeddings::normalize(&mut v);
+                    (i, q.iter().zip(&v).map(|(a, b)| a * b).sum())
+                })
+                .collect();
+            exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+            let hits: HashSet<String> = index.search(query, 10).into_iter().map(|h| h.item_id).collect();
+            found += exact.iter().take(10).filter(|(i, _)| hits.contains(&format!("item-{}", i))).count();
+        }
+
+        let recall = found as f32 / (queries.len() * 10) as f32;
+        assert!(recall > 0.9, "recall too low: {}", recall);
+    }
+
+    #[test]
+    fn test_search_within_threshold() {
+        let vectors = random_vectors(2000, 3);
+        let mut index = HnswIndex::new("test", HnswParams::default());
+        for (i, v) in vectors.iter().enumerate() {
+            index.insert(&format!("item-{}", i), 0, v.clone()).unwrap();
+        }
+
+        let query = &vectors[0];
+        let expected = index.search(query, index.live_count()).into_iter().filter(|h| h.similarity >= 0.5).count();
+        let hits = index.search_within(query, 0.5);
+        assert!(expected > WITHIN_START, "threshold should need widening, got {}", expected);
+        assert!(hits.iter().all(|h| h.similarity >= 0.5));
+        assert!(hits.len() as f32 >= expected as f32 * 0.9, "{} of {}", hits.len(), expected);
+    }
+
+    #[test]
+    fn test_delete_save_and_detect_corruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.hnsw");
+
+        let mut index = HnswIndex::new("test", HnswParams::default());
+        for (i, v) in random_vectors(200, 8).into_iter().enumerate() {
+            index.insert(&format!("item-{}", i % 50), (i / 50) as u32, v).unwrap();
+        }
+        assert!(index.remove_item("item-7"));
+        index.save(&path).unwrap();
+
+        let loaded = HnswIndex::load(&path).unwrap();
+        assert_eq!(loaded.live_count(), 196);
+        assert!(!loaded.contains_item("item-7"));
+        let query = loaded.item_vectors("item-3")[0].clone();
+        assert_eq!(loaded.search(&query, 1)[0].item_id, "item-3");
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+        assert!(HnswIndex::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_changes_are_logged_until_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.hnsw");
+        let vectors = random_vectors(200, 8);
+
+        let mut index = HnswIndex::new("test", HnswParams::default());
+        for (i, v) in vectors.iter().take(50).enumerate() {
+            index.insert(&format!("item-{}", i), 0, v.clone()).unwrap();
+        }
+        index.save(&path).unwrap();
+        let snapshot = std::fs::read(&path).unwrap();
+
+        index.insert("item-50", 0, vectors[50].clone()).unwrap();
+        assert!(index.remove_item("item-3"));
+        index.save(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), snapshot);
+
+        let loaded = HnswIndex::load(&path).unwrap();
+        assert_eq!(loaded.live_count(), 50);
+        assert!(loaded.contains_item("item-50") && !loaded.contains_item("item-3"));
+
+        // A torn record from a crash mid-append is dropped
+        let log = log_path(&path);
+        let mut bytes = std::fs::read(&log).unwrap();
+        bytes.extend_from_slice(&[9, 0, 0, 0, LOG_REMOVE]);
+        std::fs::write(&log, bytes).unwrap();
+        let mut loaded = HnswIndex::load(&path).unwrap();
+        assert!(loaded.contains_item("item-50"));
+        loaded.save(&path).unwrap();
+        assert!(!log.exists());
+
+        // Once the log outgrows half the snapshot it is folded back in
+        for (i, v) in vectors.iter().enumerate().skip(51) {
+            index.insert(&format!("item-{}", i), 0, v.clone()).unwrap();
+            index.save(&path).unwrap();
+        }
+        let size = std::fs::metadata(&path).unwrap().len();
+        assert!(size > snapshot.len() as u64);
+        assert!(!log.exists() || std::fs::metadata(&log).unwrap().len() <= size / 2);
+        assert_eq!(HnswIndex::load(&path).unwrap().live_count(), 199);
+
+        // A log left behind by an older snapshot is ignored
+        index.compact().unwrap();
+        index.save(&path).unwrap();
+        assert!(!log.exists());
+        let mut stale = LOG_MAGIC.to_vec();
+        stale.extend_from_slice(&[0; 32]);
+        std::fs::write(&log, stale).unwrap();
+        assert_eq!(HnswIndex::load(&path).unwrap().live_count(), 199);
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -94,6 +94,40 @@ impl WhisperCpp {
         })
     }
     
+    /// Transcribe a 16 kHz WAV file with per-segment timestamps
+    pub async fn transcribe_file(&self, audio_path: &Path, language: Option<&str>) -> Result<WhisperResult> {
+        let output_dir = tempfile::tempdir()?;
+        self.transcribe_file_in(audio_path, language, output_dir.path()).await
+    }
+    
+    /// Transcribe with whisper-cpp's JSON output written to `output_dir`
+    pub async fn transcribe_file_in(&self, audio_path: &Path, language: Option<&str>, output_dir: &Path) -> Result<WhisperResult> {
+        // whisper-cpp writes its JSON next to the given output prefix
+        let output_prefix = output_dir.join("transcript");
+        
+        let mut cmd = Command::new(&self.whisper_path);
+        cmd.arg("-m").arg(&self.model_path)
+           .arg("-f").arg(audio_path)
+           .arg("--output-json")
+           .arg("--output-file").arg(&output_prefix);
+        
+        if let Some(lang) = language {
+            cmd.arg("-l").arg(lang);
+        }
+        
+        let output = tokio::task::spawn_blocking(move || {
+            cmd.output()
+        }).await??;
+        
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("Whisper transcription failed: {}", stderr));
+        }
+        
+        let json = fs::read_to_string(output_prefix.with_extension("json"))?;
+        parse_json_output(&json)
+    }
+    
     pub async fn download_model(model_name: &str, target_dir: &PathBuf) -> Result<PathBuf> {
         // Common whisper model names and URLs
         let model_url = match model_name {
@@ -129,6 +163,28 @@ impl WhisperCpp {
     }
 }
 
+/// Segments from whisper-cpp's `--output-json` file. Offsets are in milliseconds.
+pub fn parse_json_output(json: &str) -> Result<WhisperResult> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    let entries = value["transcription"].as_array()
+        .ok_or_else(|| anyhow!("Whisper output has no transcription"))?;
+    
+    let segments: Vec<WhisperSegment> = entries.iter()
+        .map(|entry| WhisperSegment {
+            start: entry["offsets"]["from"].as_f64().unwrap_or(0.0) as f32 / 1000.0,
+            end: entry["offsets"]["to"].as_f64().unwrap_or(0.0) as f32 / 1000.0,
+            text: entry["text"].as_str().unwrap_or("").trim().to_string(),
+        })
+        .filter(|segment| !segment.text.is_empty())
+        .collect();
+    
+    Ok(WhisperResult {
+        text: segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" "),
+        language: value["result"]["language"].as_str().map(|s| s.to_string()),
+        segments,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,4 +198,22 @@ mod tests {
             Err(e) => println!("Whisper initialization failed (expected if not installed): {}", e),
         }
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_parse_json_output() {
+        let json = r#"{
+            "result": {"language": "en"},
+            "transcription": [
+                {"timestamps": {"from": "00:00:00,000", "to": "00:00:04,200"}, "offsets": {"from": 0, "to": 4200}, "text": " Welcome to the review."},
+                {"timestamps": {"from": "00:00:04,200", "to": "00:00:05,000"}, "offsets": {"from": 4200, "to": 5000}, "text": " "},
+                {"timestamps": {"from": "00:01:02,500", "to": "00:01:07,000"}, "offsets": {"from": 62500, "to": 67000}, "text": " Budget is next."}
+            ]
+        }"#;
+        
+        let result = parse_json_output(json).unwrap();
+        assert_eq!(result.language.as_deref(), Some("en"));
+        assert_eq!(result.segments.len(), 2);
+        assert_eq!(result.segments[1].start, 62.5);
+        assert_eq!(result.text, "Welcome to the review. Budget is next.");
+    }
+}
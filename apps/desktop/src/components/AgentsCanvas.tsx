@@ -7,11 +7,12 @@ import { ApiResponse } from '../types';
 interface AgentNode {
   id: string;
   name: string;
+  agent_key: string;
   agent_type: 'input' | 'processor' | 'output' | 'condition' | 'transform' | 'storage';
   x: number;
   y: number;
   config: Record<string, unknown>;
-  status: 'idle' | 'running' | 'success' | 'error' | 'disabled';
+  status: 'idle' | 'running' | 'success' | 'error' | 'disabled' | 'skipped' | 'cancelled';
   created_at: string;
   updated_at: string;
 }
@@ -208,13 +209,14 @@ export const AgentsCanvas: React.FC = () => {
     setDraggedAgent(null);
   };
 
-  const addAgent = async (agentType: string, agentName: string) => {
+  const addAgent = async (agentKey: string, agentName: string, agentType: string) => {
     if (!currentWorkflow) return;
 
     const newNode: AgentNode = {
       id: `agent${Date.now()}`,
       name: agentName,
-      agent_type: agentType as any,
+      agent_key: agentKey,
+      agent_type: agentType as AgentNode['agent_type'],
       x: 400,
       y: 300,
       config: {},
@@ -305,7 +307,7 @@ export const AgentsCanvas: React.FC = () => {
             {availableAgents.map(([id, name, type]) => (
               <button
                 key={id}
-                onClick={() => addAgent(id, name)}
+                onClick={() => addAgent(id, name, type)}
                 className="w-full text-left px-1.5 py-0.5 text-xs text-gray-300 hover:bg-gray-900 rounded transition-colors flex items-center gap-1"
               >
                 {getNodeIcon(type)}
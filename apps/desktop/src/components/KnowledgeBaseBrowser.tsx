@@ -129,7 +129,7 @@ export const KnowledgeBaseBrowser: React.FC = () => {
       setIsUploading(true);
       try {
         const content = await file.arrayBuffer();
-        const response = await invoke<ApiResponse<KnowledgeItem>>('upload_knowledge_file', {
+        const response = await invoke<ApiResponse<KnowledgeItem & { duplicate_of?: KnowledgeItem | null }>>('upload_knowledge_file', {
           fileName: file.name,
           content: Array.from(new Uint8Array(content)),
           parentPath: selectedItem?.item_type === 'folder' ? selectedItem.path : null
@@ -137,6 +137,10 @@ export const KnowledgeBaseBrowser: React.FC = () => {
         if (response.success) {
           await loadItems();
           await loadStorageInfo();
+          const duplicate = response.data?.duplicate_of;
+          if (duplicate) {
+            alert(`${file.name} has the same content as ${duplicate.name}`);
+          }
         }
       } catch (error) {
       } finally {